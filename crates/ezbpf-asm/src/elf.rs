@@ -0,0 +1,646 @@
+use ezbpf_core::{
+    dynamic::{DynamicEntry, DYNAMIC_ENTRY_SIZE},
+    elf_header::{
+        ELFHeader, EI_ABIVERSION, EI_CLASS, EI_DATA, EI_MAGIC, EI_OSABI, EI_PAD, EI_VERSION,
+        ELF_HEADER_SIZE, E_MACHINE_SBPF, E_TYPE, E_VERSION,
+    },
+    instructions::{is_name, parse_int, Ix},
+    opcodes::OpCode,
+    program_header::{
+        ProgramFlags, ProgramHeader, ProgramType, PF_R, PF_W, PF_X, PROGRAM_HEADER_SIZE,
+    },
+    relocation::{Relocation, RelocationType, RELOCATION_SIZE},
+    sbpf_version::SbpfVersion,
+    section_header::{
+        SectionFlags, SectionHeader, SectionHeaderType, SECTION_HEADER_SIZE, SHF_ALLOC,
+        SHF_EXECINSTR, SHF_WRITE,
+    },
+    symbol::{Symbol, SymbolBinding, SymbolType, SymbolVisibility, SHN_UNDEF, SYMBOL_SIZE},
+    syscalls::SyscallRegistry,
+};
+
+use crate::{
+    errors::AsmError,
+    parser::{Label, Line, Module, Section},
+};
+
+const DF_TEXTREL: u64 = 0x04; // Relocations may write to .text
+
+// A section of the output, in file order
+struct Out {
+    name: &'static str,
+    sh_type: SectionHeaderType,
+    flags: u64,
+    data: Vec<u8>,
+    align: u64,
+    entsize: u64,
+    offset: u64,
+}
+
+/// Lays out a parsed module as an SBPFv0 shared object the way lld does:
+/// .text, .rodata, .dynamic, .dynsym, .dynstr and .rel.dyn, each loaded at its
+/// file offset. Jumps and calls to labels become relative, calls to one of
+/// `registry` become syscalls relocated against an undefined symbol of that
+/// name, and lddw of a label is an R_BPF_64_RELATIVE relocation to its address.
+pub fn write(module: &Module, registry: &SyscallRegistry) -> Result<Vec<u8>, AsmError> {
+    let has_rodata = !module.rodata.is_empty();
+    let phnum: u64 = if has_rodata { 4 } else { 3 };
+    let text_offset = ELF_HEADER_SIZE + phnum * PROGRAM_HEADER_SIZE;
+    let rodata_offset = text_offset + module.text_size;
+    let address = |label: &Label| match label.section {
+        Section::Text => text_offset + label.offset,
+        Section::Rodata => rodata_offset + label.offset,
+    };
+
+    let mut text = Vec::with_capacity(module.text_size as usize);
+    let mut relocations = vec![];
+    let mut syscalls: Vec<String> = vec![];
+    for line in &module.text {
+        let (ix, relocation) = resolve(module, line, &address, registry)?;
+        let r_offset = text_offset + line.offset;
+        match relocation {
+            Some(Target::Address) => {
+                relocations.push((r_offset, RelocationType::R_BPF_64_RELATIVE, None))
+            }
+            Some(Target::Syscall(name)) => {
+                let index = match syscalls.iter().position(|s| *s == name) {
+                    Some(index) => index,
+                    None => {
+                        syscalls.push(name);
+                        syscalls.len() - 1
+                    }
+                };
+                relocations.push((r_offset, RelocationType::R_BPF_64_32, Some(index)));
+            }
+            None => {}
+        }
+        let b = ix.to_bytes(SbpfVersion::V0).map_err(|error| AsmError::Instruction {
+            line: line.line,
+            error,
+        })?;
+        text.extend_from_slice(&b);
+    }
+    // Relative relocations come first, counted by DT_RELCOUNT
+    relocations
+        .sort_by_key(|(offset, r_type, _)| (*r_type != RelocationType::R_BPF_64_RELATIVE, *offset));
+
+    // .dynsym holds the null symbol, the exported labels and then the syscalls
+    let entrypoint = module
+        .labels
+        .get("entrypoint")
+        .filter(|l| l.section == Section::Text && l.offset < module.text_size)
+        .ok_or(AsmError::MissingEntrypoint)?;
+    let mut globals: Vec<&str> = vec!["entrypoint"];
+    for (name, line) in &module.globals {
+        if !module.labels.contains_key(name) {
+            return Err(AsmError::UndefinedLabel {
+                line: *line,
+                label: name.clone(),
+            });
+        }
+        if !globals.contains(&name.as_str()) {
+            globals.push(name);
+        }
+    }
+    let text_index: usize = 1;
+    let rodata_index: usize = 2;
+    let mut dynstr = vec![0];
+    let mut name = |s: &str| {
+        let offset = dynstr.len() as u32;
+        dynstr.extend_from_slice(s.as_bytes());
+        dynstr.push(0);
+        offset
+    };
+    let mut dynsym = vec![symbol(
+        0,
+        SymbolBinding::STB_LOCAL,
+        SymbolType::STT_NOTYPE,
+        SHN_UNDEF,
+        0,
+        0,
+    )];
+    for global in &globals {
+        let label = module.labels[*global];
+        // Symbols run until the next label in their section
+        let end = module
+            .labels
+            .values()
+            .filter(|l| l.section == label.section && l.offset > label.offset)
+            .map(|l| l.offset)
+            .min()
+            .unwrap_or(match label.section {
+                Section::Text => module.text_size,
+                Section::Rodata => module.rodata.len() as u64,
+            });
+        let (st_type, shndx) = match label.section {
+            Section::Text => (SymbolType::STT_FUNC, text_index),
+            Section::Rodata => (SymbolType::STT_OBJECT, rodata_index),
+        };
+        let st_name = name(global);
+        dynsym.push(symbol(
+            st_name,
+            SymbolBinding::STB_GLOBAL,
+            st_type,
+            shndx as u16,
+            address(&label),
+            end - label.offset,
+        ));
+    }
+    let first_syscall = dynsym.len();
+    for syscall in &syscalls {
+        let st_name = name(syscall);
+        dynsym.push(symbol(
+            st_name,
+            SymbolBinding::STB_GLOBAL,
+            SymbolType::STT_NOTYPE,
+            SHN_UNDEF,
+            0,
+            0,
+        ));
+    }
+
+    let rel: Vec<u8> = relocations
+        .iter()
+        .flat_map(|(r_offset, r_type, syscall)| {
+            Relocation {
+                r_offset: *r_offset,
+                r_type: r_type.clone(),
+                r_sym: syscall.map(|i| (first_syscall + i) as u32).unwrap_or(0),
+                symbol: String::new(),
+            }
+            .to_bytes()
+        })
+        .collect();
+    let relative = relocations
+        .iter()
+        .filter(|(_, r_type, _)| *r_type == RelocationType::R_BPF_64_RELATIVE)
+        .count();
+
+    let mut sections = vec![Out::new(
+        ".text",
+        SectionHeaderType::SHT_PROGBITS,
+        SHF_ALLOC | SHF_EXECINSTR,
+        text,
+        8,
+        0,
+    )];
+    if has_rodata {
+        sections.push(Out::new(
+            ".rodata",
+            SectionHeaderType::SHT_PROGBITS,
+            SHF_ALLOC,
+            module.rodata.clone(),
+            1,
+            0,
+        ));
+    }
+    let dynamic_index = sections.len() + 1;
+    // Filled in once everything else has an address
+    let dynamic_size = 11 * DYNAMIC_ENTRY_SIZE as usize;
+    sections.push(Out::new(
+        ".dynamic",
+        SectionHeaderType::SHT_DYNAMIC,
+        SHF_ALLOC | SHF_WRITE,
+        vec![0; dynamic_size],
+        8,
+        DYNAMIC_ENTRY_SIZE,
+    ));
+    sections.push(Out::new(
+        ".dynsym",
+        SectionHeaderType::SHT_DYNSYM,
+        SHF_ALLOC,
+        dynsym.iter().flat_map(Symbol::to_bytes).collect(),
+        8,
+        SYMBOL_SIZE,
+    ));
+    sections.push(Out::new(
+        ".dynstr",
+        SectionHeaderType::SHT_STRTAB,
+        SHF_ALLOC,
+        dynstr,
+        1,
+        0,
+    ));
+    sections.push(Out::new(
+        ".rel.dyn",
+        SectionHeaderType::SHT_REL,
+        SHF_ALLOC,
+        rel,
+        8,
+        RELOCATION_SIZE,
+    ));
+    let mut shstrtab = vec![0];
+    let mut sh_names = vec![0];
+    for s in sections.iter().map(|s| s.name).chain([".shstrtab"]) {
+        sh_names.push(shstrtab.len() as u32);
+        shstrtab.extend_from_slice(s.as_bytes());
+        shstrtab.push(0);
+    }
+    sections.push(Out::new(
+        ".shstrtab",
+        SectionHeaderType::SHT_STRTAB,
+        0,
+        shstrtab,
+        1,
+        0,
+    ));
+
+    let mut offset = text_offset;
+    for s in &mut sections {
+        s.offset = offset.next_multiple_of(s.align);
+        offset = s.offset + s.data.len() as u64;
+    }
+    let (dynsym_index, dynstr_index, rel_index) =
+        (dynamic_index + 1, dynamic_index + 2, dynamic_index + 3);
+    let offsets: Vec<u64> = sections.iter().map(|s| s.offset).collect();
+    let at = |index: usize| offsets[index - 1];
+    let shoff = offset.next_multiple_of(8);
+
+    let dynamic: Vec<u8> = [
+        DynamicEntry::DT_FLAGS(DF_TEXTREL),
+        DynamicEntry::DT_REL(at(rel_index)),
+        DynamicEntry::DT_RELSZ(relocations.len() as u64 * RELOCATION_SIZE),
+        DynamicEntry::DT_RELENT(RELOCATION_SIZE),
+        DynamicEntry::DT_RELCOUNT(relative as u64),
+        DynamicEntry::DT_SYMTAB(at(dynsym_index)),
+        DynamicEntry::DT_SYMENT(SYMBOL_SIZE),
+        DynamicEntry::DT_STRTAB(at(dynstr_index)),
+        DynamicEntry::DT_STRSZ(sections[dynstr_index - 1].data.len() as u64),
+        DynamicEntry::DT_TEXTREL(0),
+        DynamicEntry::DT_NULL,
+    ]
+    .iter()
+    .flat_map(DynamicEntry::to_bytes)
+    .collect();
+    sections[dynamic_index - 1].data = dynamic;
+
+    let segment = |p_type, flags: u8, first: usize, last: usize| {
+        let start = at(first);
+        let size = at(last) + sections[last - 1].data.len() as u64 - start;
+        ProgramHeader {
+            p_type,
+            p_flags: ProgramFlags(flags as u32),
+            p_offset: start,
+            p_vaddr: start,
+            p_paddr: start,
+            p_filesz: size,
+            p_memsz: size,
+            p_align: 8,
+        }
+    };
+    let mut program_headers = vec![segment(
+        ProgramType::PT_LOAD,
+        PF_R | PF_X,
+        text_index,
+        text_index,
+    )];
+    if has_rodata {
+        program_headers.push(segment(
+            ProgramType::PT_LOAD,
+            PF_R,
+            rodata_index,
+            rodata_index,
+        ));
+    }
+    program_headers.push(segment(ProgramType::PT_LOAD, PF_R, dynsym_index, rel_index));
+    program_headers.push(segment(
+        ProgramType::PT_DYNAMIC,
+        PF_R | PF_W,
+        dynamic_index,
+        dynamic_index,
+    ));
+
+    let elf_header = ELFHeader {
+        ei_magic: EI_MAGIC,
+        ei_class: EI_CLASS,
+        ei_data: EI_DATA,
+        ei_version: EI_VERSION,
+        ei_osabi: EI_OSABI,
+        ei_abiversion: EI_ABIVERSION,
+        ei_pad: EI_PAD,
+        e_type: E_TYPE,
+        e_machine: E_MACHINE_SBPF,
+        e_version: E_VERSION,
+        e_entry: address(entrypoint),
+        e_phoff: ELF_HEADER_SIZE,
+        e_shoff: shoff,
+        e_flags: u32::from(SbpfVersion::V0),
+        e_ehsize: ELF_HEADER_SIZE as u16,
+        e_phentsize: PROGRAM_HEADER_SIZE as u16,
+        e_phnum: program_headers.len() as u16,
+        e_shentsize: SECTION_HEADER_SIZE as u16,
+        e_shnum: sections.len() as u16 + 1,
+        e_shstrndx: sections.len() as u16,
+    };
+
+    let mut section_headers = vec![SectionHeader {
+        sh_name: 0,
+        sh_type: SectionHeaderType::SHT_NULL,
+        sh_flags: SectionFlags(0),
+        sh_addr: 0,
+        sh_offset: 0,
+        sh_size: 0,
+        sh_link: 0,
+        sh_info: 0,
+        sh_addralign: 0,
+        sh_entsize: 0,
+    }];
+    for (i, s) in sections.iter().enumerate() {
+        let index = i + 1;
+        let (sh_link, sh_info) = match index {
+            i if i == dynamic_index => (dynstr_index as u32, 0),
+            // sh_info is the first global symbol, after the null symbol
+            i if i == dynsym_index => (dynstr_index as u32, 1),
+            i if i == rel_index => (dynsym_index as u32, 0),
+            _ => (0, 0),
+        };
+        section_headers.push(SectionHeader {
+            sh_name: sh_names[index],
+            sh_type: s.sh_type.clone(),
+            sh_flags: SectionFlags(s.flags),
+            sh_addr: match s.flags & SHF_ALLOC {
+                0 => 0,
+                _ => s.offset,
+            },
+            sh_offset: s.offset,
+            sh_size: s.data.len() as u64,
+            sh_link,
+            sh_info,
+            sh_addralign: s.align,
+            sh_entsize: s.entsize,
+        });
+    }
+
+    let mut b = elf_header.to_bytes();
+    for h in &program_headers {
+        b.extend_from_slice(&h.to_bytes());
+    }
+    for s in &sections {
+        b.resize(s.offset as usize, 0);
+        b.extend_from_slice(&s.data);
+    }
+    b.resize(shoff as usize, 0);
+    for h in &section_headers {
+        b.extend_from_slice(&h.to_bytes());
+    }
+    Ok(b)
+}
+
+impl Out {
+    fn new(
+        name: &'static str,
+        sh_type: SectionHeaderType,
+        flags: u64,
+        data: Vec<u8>,
+        align: u64,
+        entsize: u64,
+    ) -> Self {
+        Self {
+            name,
+            sh_type,
+            flags,
+            data,
+            align,
+            entsize,
+            offset: 0,
+        }
+    }
+}
+
+fn symbol(
+    st_name: u32,
+    st_bind: SymbolBinding,
+    st_type: SymbolType,
+    st_shndx: u16,
+    st_value: u64,
+    st_size: u64,
+) -> Symbol {
+    Symbol {
+        name: String::new(),
+        st_name,
+        st_bind,
+        st_type,
+        st_other: SymbolVisibility::STV_DEFAULT as u8,
+        st_shndx,
+        st_value,
+        st_size,
+    }
+}
+
+// What the loader has to fill in for an instruction
+enum Target {
+    Address,         // The address of a label, loaded with lddw.
+    Syscall(String), // A syscall, called by name.
+}
+
+// Replaces labels in an instruction with the offsets, immediates and
+// relocations they stand for, then encodes it
+fn resolve(
+    module: &Module,
+    line: &Line,
+    address: &dyn Fn(&Label) -> u64,
+    registry: &SyscallRegistry,
+) -> Result<(Ix, Option<Target>), AsmError> {
+    let (mnemonic, rest) = line
+        .text
+        .split_once(char::is_whitespace)
+        .unwrap_or((&line.text, ""));
+    let mut operands: Vec<String> = match rest.trim() {
+        "" => vec![],
+        rest => rest.split(',').map(|o| o.trim().to_string()).collect(),
+    };
+    let pc = (line.offset / 8) as i64;
+    // The slot of an instruction in .text that a label points to
+    let slot = |label: &str| -> Result<i64, AsmError> {
+        let target = module
+            .labels
+            .get(label)
+            .ok_or_else(|| AsmError::UndefinedLabel {
+                line: line.line,
+                label: label.to_string(),
+            })?;
+        let is_ix = target.section == Section::Text
+            && module.text.iter().any(|l| l.offset == target.offset);
+        if is_ix {
+            Ok((target.offset / 8) as i64)
+        } else {
+            Err(AsmError::InvalidTarget {
+                line: line.line,
+                label: label.to_string(),
+            })
+        }
+    };
+    let out_of_range = |label: &str| AsmError::OutOfRange {
+        line: line.line,
+        label: label.to_string(),
+    };
+    let is_jump =
+        OpCode::all(SbpfVersion::V0).any(|op| op.is_jump() && Into::<&str>::into(op) == mnemonic);
+    let mut target = None;
+    let mut relative = false;
+    match (mnemonic, operands.last_mut()) {
+        (_, Some(label)) if is_jump && is_label(label) => {
+            let off = slot(label)? - pc - 1;
+            if i16::try_from(off).is_err() {
+                return Err(out_of_range(label));
+            }
+            *label = off.to_string();
+        }
+        // A call to itself would have an immediate of -1, which the
+        // loader takes to be a syscall
+        ("call", Some(label)) if is_label(label) && module.labels.contains_key(label.as_str()) => {
+            let imm = slot(label)? - pc - 1;
+            if imm == -1 || i32::try_from(imm).is_err() {
+                return Err(out_of_range(label));
+            }
+            *label = imm.to_string();
+            relative = true;
+        }
+        ("call", Some(name)) if is_label(name) => {
+            if !registry.contains(name) {
+                return Err(AsmError::UndefinedLabel {
+                    line: line.line,
+                    label: name.clone(),
+                });
+            }
+            target = Some(Target::Syscall(name.clone()));
+            *name = "-1".to_string();
+        }
+        ("lddw", Some(operand)) => {
+            // A label, with an optional constant added
+            let split = operand.find(['+', '-']).unwrap_or(operand.len());
+            let (label, addend) = operand.split_at(split);
+            let label = label.trim();
+            if is_label(label) {
+                let defined = module
+                    .labels
+                    .get(label)
+                    .ok_or_else(|| AsmError::UndefinedLabel {
+                        line: line.line,
+                        label: label.to_string(),
+                    })?;
+                let addend = match addend.trim() {
+                    "" => 0,
+                    a => parse_int(&a.replace(' ', "")).ok_or_else(|| out_of_range(label))?,
+                };
+                *operand = (address(defined) as i64).wrapping_add(addend).to_string();
+                target = Some(Target::Address);
+            }
+        }
+        _ => {}
+    }
+    let text = if operands.is_empty() {
+        mnemonic.to_string()
+    } else {
+        format!("{} {}", mnemonic, operands.join(", "))
+    };
+    let mut ix = Ix::parse_asm(&text, SbpfVersion::V0).map_err(|error| AsmError::Instruction {
+        line: line.line,
+        error,
+    })?;
+    // Before static syscalls, a source of 1 marks a call as relative
+    if relative {
+        ix.src = 1;
+    }
+    Ok((ix, target))
+}
+
+// Labels are symbols other than registers
+fn is_label(s: &str) -> bool {
+    let is_reg = s
+        .strip_prefix('r')
+        .or_else(|| s.strip_prefix('w'))
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    is_name(s) && !is_reg
+}
+
+#[cfg(test)]
+mod tests {
+    use ezbpf_core::{instructions::Ix, sbpf_version::SbpfVersion, syscalls::SyscallRegistry};
+
+    use crate::{
+        elf::{resolve, Target},
+        errors::AsmError,
+        parser::parse,
+    };
+
+    // Resolves every instruction as if .text started at 0x120
+    fn resolve_all(source: &str) -> Result<Vec<(Ix, Option<Target>)>, AsmError> {
+        resolve_with(source, &SyscallRegistry::default())
+    }
+
+    fn resolve_with(
+        source: &str,
+        registry: &SyscallRegistry,
+    ) -> Result<Vec<(Ix, Option<Target>)>, AsmError> {
+        let module = parse(source)?;
+        let address = |label: &crate::parser::Label| 0x120 + label.offset;
+        module
+            .text
+            .iter()
+            .map(|line| resolve(&module, line, &address, registry))
+            .collect()
+    }
+
+    #[test]
+    fn offsets() {
+        let ixs =
+            resolve_all("top: lddw r1, top+8\n jne r1, 0, top\n call top\n call abort\n ja top\n")
+                .unwrap();
+        assert_eq!(ixs[0].0.imm, 0x128);
+        assert!(matches!(ixs[0].1, Some(Target::Address)));
+        assert_eq!(ixs[1].0.off, -3);
+        assert_eq!((ixs[2].0.imm, ixs[2].0.src), (-4, 1));
+        assert_eq!((ixs[3].0.imm, ixs[3].0.src), (-1, 0));
+        assert!(matches!(&ixs[3].1, Some(Target::Syscall(name)) if name == "abort"));
+        assert_eq!(ixs[4].0.off, -6);
+        assert!(ixs[4].1.is_none());
+        assert_eq!(ixs[4].0.to_asm(SbpfVersion::V0).unwrap(), "ja -6");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            resolve_all("ja nowhere").err(),
+            Some(AsmError::UndefinedLabel {
+                line: 1,
+                label: "nowhere".to_string()
+            })
+        );
+        assert_eq!(
+            resolve_all("exit\n.rodata\ndata: .byte 0\n.text\nja data").err(),
+            Some(AsmError::InvalidTarget {
+                line: 5,
+                label: "data".to_string()
+            })
+        );
+        assert_eq!(
+            resolve_all("spin: call spin").err(),
+            Some(AsmError::OutOfRange {
+                line: 1,
+                label: "spin".to_string()
+            })
+        );
+        assert!(matches!(
+            resolve_all("mov64 r11, 0"),
+            Err(AsmError::Instruction { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn syscalls() {
+        // Calls to names that are neither labels nor known syscalls are typos
+        // rather than syscalls the loader would find
+        assert_eq!(
+            resolve_all("call sol_lgo_").err(),
+            Some(AsmError::UndefinedLabel {
+                line: 1,
+                label: "sol_lgo_".to_string()
+            })
+        );
+        let mut registry = SyscallRegistry::default();
+        registry.register("my_runtime_syscall");
+        let ixs = resolve_with("call my_runtime_syscall", &registry).unwrap();
+        assert!(matches!(&ixs[0].1, Some(Target::Syscall(name)) if name == "my_runtime_syscall"));
+    }
+}
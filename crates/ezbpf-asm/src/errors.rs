@@ -0,0 +1,34 @@
+use ezbpf_core::errors::EZBpfError;
+use thiserror::Error;
+
+// Every error but a missing entrypoint carries the line of the source it was
+// raised on, counting from 1
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum AsmError {
+    #[error("line {line}: {error}")]
+    Instruction { line: usize, error: EZBpfError },
+    #[error("line {line}: unknown directive {directive}")]
+    UnknownDirective { line: usize, directive: String },
+    #[error("line {line}: invalid operand {value:?} for {directive}")]
+    InvalidOperand {
+        line: usize,
+        directive: String,
+        value: String,
+    },
+    #[error("line {line}: {what} can't go in {section}")]
+    WrongSection {
+        line: usize,
+        what: String,
+        section: &'static str,
+    },
+    #[error("line {line}: label {label} is already defined")]
+    DuplicateLabel { line: usize, label: String },
+    #[error("line {line}: label {label} is not defined")]
+    UndefinedLabel { line: usize, label: String },
+    #[error("line {line}: {label} is not an instruction in .text")]
+    InvalidTarget { line: usize, label: String },
+    #[error("line {line}: {label} is out of range")]
+    OutOfRange { line: usize, label: String },
+    #[error("No entrypoint label in .text")]
+    MissingEntrypoint,
+}
@@ -0,0 +1,161 @@
+pub mod elf;
+pub mod errors;
+pub mod parser;
+
+use errors::AsmError;
+use ezbpf_core::syscalls::SyscallRegistry;
+
+/// Assembles a source file into an SBPFv0 shared object the Solana loader
+/// will deploy, calling the built-in Solana syscalls by name.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble_with(source, &SyscallRegistry::default())
+}
+
+/// Like `assemble`, but with the syscalls in `syscalls` callable by name.
+pub fn assemble_with(source: &str, syscalls: &SyscallRegistry) -> Result<Vec<u8>, AsmError> {
+    let module = parser::parse(source)?;
+    elf::write(&module, syscalls)
+}
+
+#[cfg(test)]
+mod tests {
+    use ezbpf_core::{
+        disassembler::CallKind, program::Program, relocation::RelocationType,
+        sbpf_version::SbpfVersion, syscalls::SyscallRegistry,
+    };
+
+    use crate::{assemble, errors::AsmError};
+
+    const HELLO: &str = r#"
+        .globl entrypoint
+        .globl finish
+        entrypoint:
+            lddw r1, message
+            mov64 r2, 14
+            call sol_log_
+            ja done
+            mov64 r0, 1
+        done:
+            call finish
+            exit
+        finish:
+            mov64 r0, 0
+            exit
+        .rodata
+        message: .ascii "Hello, world!\n"
+    "#;
+
+    #[test]
+    fn hello_world() {
+        let b = assemble(HELLO).unwrap();
+        let program = Program::from_bytes(&b).unwrap();
+        assert_eq!(program.validate(), []);
+        assert_eq!(program.sbpf_version, SbpfVersion::V0);
+        assert_eq!(program.to_bytes().unwrap(), b);
+
+        let text = program
+            .section_header_entries
+            .iter()
+            .find(|e| e.label == ".text")
+            .unwrap();
+        assert_eq!(program.elf_header.e_entry, text.offset as u64);
+        let rodata = program
+            .section_header_entries
+            .iter()
+            .find(|e| e.label == ".rodata")
+            .unwrap();
+        assert_eq!(rodata.data, b"Hello, world!\n");
+
+        let relocations: Vec<_> = program
+            .relocations
+            .iter()
+            .map(|r| {
+                (
+                    r.r_offset - text.offset as u64,
+                    r.r_type.clone(),
+                    r.symbol.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            relocations,
+            [
+                (0, RelocationType::R_BPF_64_RELATIVE, ""),
+                (24, RelocationType::R_BPF_64_32, "sol_log_"),
+            ]
+        );
+        // The file holds the address lddw loads, which the loader rebases
+        let lo = u32::from_le_bytes(text.data[4..8].try_into().unwrap());
+        let hi = u32::from_le_bytes(text.data[12..16].try_into().unwrap());
+        assert_eq!((hi as u64) << 32 | lo as u64, rodata.offset as u64);
+
+        let calls = program.classify_calls(&SyscallRegistry::default());
+        let kinds: Vec<_> = calls
+            .iter()
+            .map(|c| (c.kind, c.name.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (CallKind::Syscall, "sol_log_"),
+                (CallKind::Relative, "finish")
+            ]
+        );
+        let asm = program.to_asm().unwrap();
+        // The address lddw loads into r1 is only known once relocated
+        let call = asm.lines().find(|l| l.starts_with("call sol_log_"));
+        assert_eq!(call, Some("call sol_log_ // r1 message: *const u8, r2 len: u64 = 14"));
+        assert!(asm.contains("call finish"));
+        assert!(asm.contains("ja lbb_6"));
+    }
+
+    #[test]
+    fn llvm_syntax() {
+        // The lddw written in llvm's syntax takes two slots like any other, so
+        // the jump and the relocation after it land where they should
+        let source = r#"
+            entrypoint: ja skip
+                r1 = 5 ll
+            skip: lddw r2, msg
+                exit
+            .rodata
+            msg: .asciz "hi"
+        "#;
+        let b = assemble(source).unwrap();
+        let program = Program::from_bytes(&b).unwrap();
+        assert_eq!(program.validate(), []);
+        let text = program
+            .section_header_entries
+            .iter()
+            .find(|e| e.label == ".text")
+            .unwrap();
+        assert_eq!(text.data.len(), 48);
+        let ja = text.items[0].ix().unwrap();
+        assert_eq!(ja.off, 2);
+        assert_eq!(text.items[1].ix().unwrap().imm, 5);
+        let relocations: Vec<_> = program
+            .relocations
+            .iter()
+            .map(|r| (r.r_offset - text.offset as u64, r.r_type.clone()))
+            .collect();
+        assert_eq!(relocations, [(24, RelocationType::R_BPF_64_RELATIVE)]);
+    }
+
+    #[test]
+    fn without_rodata() {
+        let b = assemble("entrypoint:\n  mov64 r0, 0\n  exit").unwrap();
+        let program = Program::from_bytes(&b).unwrap();
+        assert_eq!(program.validate(), []);
+        assert_eq!(program.program_headers.len(), 3);
+        assert_eq!(program.relocations, []);
+    }
+
+    #[test]
+    fn missing_entrypoint() {
+        assert_eq!(assemble("main:\n  exit"), Err(AsmError::MissingEntrypoint));
+        assert_eq!(
+            assemble(".rodata\nentrypoint: .byte 0"),
+            Err(AsmError::MissingEntrypoint)
+        );
+    }
+}
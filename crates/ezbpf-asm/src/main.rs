@@ -0,0 +1,38 @@
+use anyhow::Result;
+use clap::Parser;
+use ezbpf_asm::assemble_with;
+use ezbpf_core::syscalls::SyscallRegistry;
+use std::fs;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Filename of the assembly source
+    #[arg(short, long)]
+    filename: String,
+    /// Where to write the shared object, next to the source with a .so extension by default
+    #[arg(short, long)]
+    output: Option<String>,
+    /// Name of a syscall to allow besides the built-in Solana ones, may be repeated
+    #[arg(long = "syscall")]
+    syscalls: Vec<String>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let mut syscalls = SyscallRegistry::default();
+    for name in &args.syscalls {
+        syscalls.register(name);
+    }
+    let source = fs::read_to_string(&args.filename)?;
+    let b = assemble_with(&source, &syscalls).map_err(|e| anyhow::anyhow!("{}: {}", args.filename, e))?;
+    let output = match args.output {
+        Some(output) => output,
+        None => {
+            let stem = args.filename.strip_suffix(".s").unwrap_or(&args.filename);
+            format!("{}.so", stem)
+        }
+    };
+    fs::write(&output, b)?;
+    Ok(())
+}
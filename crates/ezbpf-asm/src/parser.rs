@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use ezbpf_core::{
+    instructions::{is_name, parse_int, Ix},
+    sbpf_version::SbpfVersion,
+};
+
+use crate::errors::AsmError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Text,   // Instructions.
+    Rodata, // Read only data, such as strings.
+}
+
+impl Section {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Text => ".text",
+            Self::Rodata => ".rodata",
+        }
+    }
+}
+
+/// Where a label points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label {
+    pub section: Section,
+    pub offset: u64, // From the start of the section.
+    pub line: usize,
+}
+
+/// An instruction as written, before labels are resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub line: usize,
+    pub offset: u64, // From the start of .text.
+    pub text: String,
+}
+
+/// A parsed source file, with .text still as text and .rodata already laid out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Module {
+    pub text: Vec<Line>,
+    pub text_size: u64,
+    pub rodata: Vec<u8>,
+    pub labels: HashMap<String, Label>,
+    pub globals: Vec<(String, usize)>, // Names given to .globl and the line they were given on.
+}
+
+/// Splits a source file into instructions, data and labels. Lines hold any
+/// number of `label:` definitions followed by an instruction or a directive,
+/// with `//` and `;` starting a comment.
+pub fn parse(source: &str) -> Result<Module, AsmError> {
+    let mut module = Module::default();
+    let mut section = Section::Text;
+    for (i, raw) in source.lines().enumerate() {
+        let line = i + 1;
+        let mut text = strip_comment(raw).trim();
+        while let Some((label, rest)) = text.split_once(':').filter(|(l, _)| is_name(l)) {
+            let offset = match section {
+                Section::Text => module.text_size,
+                Section::Rodata => module.rodata.len() as u64,
+            };
+            let defined = Label {
+                section,
+                offset,
+                line,
+            };
+            if module.labels.insert(label.to_string(), defined).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        if text.starts_with('.') {
+            directive(&mut module, &mut section, line, text)?;
+            continue;
+        }
+        if section != Section::Text {
+            return Err(AsmError::WrongSection {
+                line,
+                what: text.to_string(),
+                section: section.name(),
+            });
+        }
+        let mnemonic = text.split_whitespace().next().unwrap_or_default();
+        module.text.push(Line {
+            line,
+            offset: module.text_size,
+            text: text.to_string(),
+        });
+        // Sized by the instruction the line encodes to, so that llvm's
+        // `rX = imm ll` takes two slots like lddw does. A line naming a label
+        // only encodes once the label is resolved, and of those only lddw
+        // loads a 64 bit immediate.
+        module.text_size += match Ix::parse_asm(text, SbpfVersion::V0) {
+            Ok(ix) => ix.size() as u64,
+            Err(_) if mnemonic == "lddw" => 16,
+            Err(_) => 8,
+        };
+    }
+    Ok(module)
+}
+
+fn directive(
+    module: &mut Module,
+    section: &mut Section,
+    line: usize,
+    text: &str,
+) -> Result<(), AsmError> {
+    let (name, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let args = args.trim();
+    let invalid = |value: &str| AsmError::InvalidOperand {
+        line,
+        directive: name.to_string(),
+        value: value.to_string(),
+    };
+    let data = match name {
+        ".globl" | ".global" => {
+            if !is_name(args) {
+                return Err(invalid(args));
+            }
+            module.globals.push((args.to_string(), line));
+            return Ok(());
+        }
+        ".text" => {
+            *section = Section::Text;
+            return Ok(());
+        }
+        ".rodata" => {
+            *section = Section::Rodata;
+            return Ok(());
+        }
+        ".section" => {
+            let name = args.split(',').next().unwrap_or_default().trim();
+            *section = match name {
+                ".text" => Section::Text,
+                n if n == ".rodata" || n.starts_with(".rodata.") => Section::Rodata,
+                n => return Err(invalid(n)),
+            };
+            return Ok(());
+        }
+        ".ascii" | ".asciz" => {
+            let mut data = parse_string(args).ok_or_else(|| invalid(args))?;
+            if name == ".asciz" {
+                data.push(0);
+            }
+            data
+        }
+        ".byte" | ".short" | ".long" | ".quad" => {
+            let size = match name {
+                ".byte" => 1,
+                ".short" => 2,
+                ".long" => 4,
+                _ => 8,
+            };
+            let mut data = vec![];
+            for value in args.split(',').map(str::trim) {
+                let n = parse_int(value)
+                    .filter(|n| fits(*n, size))
+                    .ok_or_else(|| invalid(value))?;
+                data.extend_from_slice(&n.to_le_bytes()[..size]);
+            }
+            data
+        }
+        _ => {
+            return Err(AsmError::UnknownDirective {
+                line,
+                directive: name.to_string(),
+            })
+        }
+    };
+    if *section != Section::Rodata {
+        return Err(AsmError::WrongSection {
+            line,
+            what: name.to_string(),
+            section: section.name(),
+        });
+    }
+    module.rodata.extend_from_slice(&data);
+    Ok(())
+}
+
+// Whether a value fits in `size` bytes, signed or not
+fn fits(n: i64, size: usize) -> bool {
+    match size {
+        8 => true,
+        _ => {
+            let bits = size as u32 * 8;
+            n >= -(1 << (bits - 1)) && n < (1 << bits)
+        }
+    }
+}
+
+// Cuts a line off at the first comment outside of a string
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            '/' if !quoted && line[i..].starts_with("//") => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// A double quoted string with C escapes
+fn parse_string(s: &str) -> Option<Vec<u8>> {
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut data = vec![];
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            data.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        data.push(match chars.next()? {
+            'n' => b'\n',
+            't' => b'\t',
+            'r' => b'\r',
+            '0' => 0,
+            '\\' => b'\\',
+            '"' => b'"',
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&hex, 16).ok()?
+            }
+            _ => return None,
+        });
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::AsmError,
+        parser::{parse, Label, Section},
+    };
+
+    #[test]
+    fn sections_and_labels() {
+        let module = parse(
+            r#"
+            .globl entrypoint
+            entrypoint: lddw r1, message // the lddw takes two slots
+                mov64 r2, 14 ; and this one
+            done:
+                exit
+            .section .rodata
+            message: .ascii "Hello, world!\n"
+            bytes: .byte 1, 0xff
+                .asciz "a;b"
+                .quad -1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(module.globals, [("entrypoint".to_string(), 2)]);
+        let texts: Vec<&str> = module.text.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["lddw r1, message", "mov64 r2, 14", "exit"]);
+        assert_eq!(module.text_size, 32);
+        assert_eq!(
+            module.labels["done"],
+            Label {
+                section: Section::Text,
+                offset: 24,
+                line: 5,
+            }
+        );
+        assert_eq!(module.labels["bytes"].offset, 14);
+        assert_eq!(&module.rodata[..14], b"Hello, world!\n");
+        assert_eq!(&module.rodata[14..20], b"\x01\xffa;b\0");
+        assert_eq!(&module.rodata[20..], [0xff; 8]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("a:\na:"),
+            Err(AsmError::DuplicateLabel {
+                line: 2,
+                label: "a".to_string()
+            })
+        );
+        assert_eq!(
+            parse(".data"),
+            Err(AsmError::UnknownDirective {
+                line: 1,
+                directive: ".data".to_string()
+            })
+        );
+        assert!(matches!(
+            parse(".rodata\nexit"),
+            Err(AsmError::WrongSection { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse(".ascii \"a\""),
+            Err(AsmError::WrongSection { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse(".rodata\n.byte 256"),
+            Err(AsmError::InvalidOperand { line: 2, .. })
+        ));
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::Result;
-use clap::Parser;
-use ezbpf_core::errors::EZBpfError;
+use clap::{Parser, ValueEnum};
+use ezbpf_core::disassembler::Strategy;
 use ezbpf_core::program::Program;
+use ezbpf_core::symbol::Symbol;
+use ezbpf_core::syscalls::SyscallRegistry;
 use std::fs::File;
 use std::io::Read;
 
@@ -13,17 +15,201 @@ struct Args {
     filename: String,
     #[arg(short, long)]
     asm: Option<String>,
+    /// How to tell code from data when disassembling
+    #[arg(long, value_enum, default_value_t)]
+    disassembler: Disassembler,
+    /// Print the .symtab and .dynsym symbol tables
+    #[arg(short, long)]
+    symbols: bool,
+    /// Print the section headers
+    #[arg(short = 'S', long)]
+    sections: bool,
+    /// Keep going past malformed headers and instructions, reporting them as diagnostics
+    #[arg(short, long)]
+    lenient: bool,
+    /// Print which sections each program header contains
+    #[arg(long)]
+    segments: bool,
+    /// Translate a VM address (0x100000000 and up for the program region) to its
+    /// virtual address, file offset, section and instruction
+    #[arg(long, value_parser = parse_address)]
+    address: Option<u64>,
+    /// Check the program against the rules the Solana ELF loader enforces
+    #[arg(long)]
+    validate: bool,
+    /// Name of a syscall to recognise besides the built-in Solana ones, may be repeated
+    #[arg(long = "syscall")]
+    syscalls: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum Disassembler {
+    /// Decode every slot of .text in order
+    #[default]
+    Linear,
+    /// Follow control flow from the entrypoint, function symbols and relocations
+    Recursive,
+}
+
+impl From<Disassembler> for Strategy {
+    fn from(val: Disassembler) -> Self {
+        match val {
+            Disassembler::Linear => Strategy::LinearSweep,
+            Disassembler::Recursive => Strategy::RecursiveDescent,
+        }
+    }
+}
+
+fn parse_address(s: &str) -> Result<u64, std::num::ParseIntError> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
+fn print_address(program: &Program, vm_addr: u64) -> Result<()> {
+    let Some(address) = program.address_space().resolve_vm(vm_addr) else {
+        anyhow::bail!("{:#x} is not mapped by the program", vm_addr);
+    };
+    println!("{}", address);
+    if let Some(e) = address.section.and_then(|i| program.section_header_entries.get(i)) {
+        let item = address.ix.and_then(|i| e.items.get(i));
+        match item {
+            Some(item) => println!("{}: {}", e.label, item.to_asm(program.sbpf_version)?),
+            None => println!("{}", e.label),
+        }
+    }
+    Ok(())
+}
+
+fn print_symbols(label: &str, symbols: &[Symbol]) {
+    println!("Symbol table '{}' contains {} entries:", label, symbols.len());
+    println!("   Num:    Value          Size Type    Bind   Vis      Ndx Name");
+    for (i, s) in symbols.iter().enumerate() {
+        println!(
+            "{:>6}: {:016x} {:>5} {:<7} {:<6} {:<8} {:>3} {}",
+            i,
+            s.st_value,
+            s.st_size,
+            s.st_type,
+            s.st_bind,
+            s.visibility(),
+            s.shndx_str(),
+            s.name
+        );
+    }
+}
+
+fn print_sections(program: &Program) {
+    println!("There are {} section headers:", program.section_headers.len());
+    println!("  [Nr] Name              Type            Address          Off    Size   ES Flg Lk Inf Al");
+    for (i, h) in program.section_headers.iter().enumerate() {
+        let name = program
+            .section_header_entries
+            .get(i)
+            .map(|e| e.label.as_str())
+            .unwrap_or_default();
+        let sh_type = h.sh_type.to_string();
+        println!(
+            "  [{:>2}] {:<17} {:<15} {:016x} {:06x} {:06x} {:02x} {:>3} {:>2} {:>3} {:>2}",
+            i,
+            name,
+            sh_type.trim_start_matches("SHT_"),
+            h.sh_addr,
+            h.sh_offset,
+            h.sh_size,
+            h.sh_entsize,
+            h.sh_flags,
+            h.sh_link,
+            h.sh_info,
+            h.sh_addralign
+        );
+    }
+    println!("Key to Flags:");
+    println!("  W (write), A (alloc), X (execute), M (merge), S (strings), I (info),");
+    println!("  L (link order), O (extra OS processing required), G (group), T (TLS),");
+    println!("  C (compressed), x (unknown), o (OS specific), E (exclude),");
+    println!("  p (processor specific)");
+}
+
+fn print_segments(program: &Program) {
+    let map = program.segment_map();
+    let label = |i: &usize| {
+        program
+            .section_header_entries
+            .get(*i)
+            .map(|e| e.label.as_str())
+            .unwrap_or_default()
+    };
+    println!(" Section to Segment mapping:");
+    println!("  Segment Type            Flags Sections...");
+    for (i, (h, sections)) in program.program_headers.iter().zip(&map.segments).enumerate() {
+        let labels: Vec<&str> = sections.iter().map(label).collect();
+        println!(
+            "   {:02}     {:<15} {:<5} {}",
+            i,
+            h.p_type,
+            h.p_flags,
+            labels.join(" ")
+        );
+    }
+    for i in &map.unmapped {
+        println!("Section [{:>2}] {} is not in any loadable segment", i, label(i));
+    }
+    for i in &map.straddling {
+        println!("Section [{:>2}] {} straddles a loadable segment", i, label(i));
+    }
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let mut syscalls = SyscallRegistry::default();
+    for name in &args.syscalls {
+        syscalls.register(name);
+    }
     let mut file = File::open(args.filename)?;
     let mut b = vec![];
     file.read_to_end(&mut b)?;
-    let program = Program::from_bytes(b.as_ref())?;
+    let program = if args.lenient {
+        Program::from_bytes_lenient(b.as_ref())?
+    } else {
+        Program::from_bytes(b.as_ref())?
+    };
+    for d in &program.diagnostics {
+        eprintln!("{}", d);
+    }
+    if args.validate {
+        let errors = program.validate();
+        for e in &errors {
+            println!("{}", e);
+        }
+        if !errors.is_empty() {
+            anyhow::bail!("{} loader check(s) failed", errors.len());
+        }
+        return Ok(());
+    }
+    if let Some(vm_addr) = args.address {
+        return print_address(&program, vm_addr);
+    }
+    if args.segments {
+        print_segments(&program);
+        return Ok(());
+    }
+    if args.sections {
+        print_sections(&program);
+        return Ok(());
+    }
+    if args.symbols {
+        print_symbols(".dynsym", program.dynamic_symbols());
+        if !program.symbols().is_empty() {
+            println!();
+            print_symbols(".symtab", program.symbols());
+        }
+        return Ok(());
+    }
     match args.asm {
-        Some(_) => println!("{}", program.section_header_entries.iter().map(|h| h.ixs.clone()).filter(|ixs| !ixs.is_empty()).map(|ixs| ixs.iter().map(|i| i.to_asm().unwrap()).collect::<Vec<String>>().join("\n")).collect::<Vec<String>>().join("\n")),
-        None => println!("{}", serde_json::to_string_pretty(&program)?)
+        Some(_) => println!("{}", program.to_asm_with(args.disassembler.into(), &syscalls)?),
+        None => println!("{}", serde_json::to_string_pretty(&program.to_json(&syscalls))?)
     }
     Ok(())
 }
@@ -0,0 +1,13 @@
+#![no_main]
+
+use ezbpf_core::{instructions::Ix, sbpf_version::SbpfVersion};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    for version in [SbpfVersion::V0, SbpfVersion::V1, SbpfVersion::V2, SbpfVersion::V3] {
+        if let Ok(ix) = Ix::from_bytes(data, version) {
+            let _ = ix.to_asm(version);
+            assert_eq!(ix.to_bytes(version).unwrap(), data[..ix.size()]);
+        }
+    }
+});
@@ -0,0 +1,23 @@
+#![no_main]
+
+use ezbpf_core::{program::Program, program_ref::ProgramRef};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(program) = ProgramRef::from_bytes(data) {
+        for section in &program.sections {
+            section.ixs(program.sbpf_version).for_each(drop);
+            section.items(program.sbpf_version).for_each(drop);
+        }
+    }
+    if let Ok(program) = Program::from_bytes(data) {
+        let _ = program.to_asm();
+        let _ = program.check_dynamic();
+        if let Ok(b) = program.to_bytes() {
+            let _ = Program::from_bytes(&b);
+        }
+    }
+    if let Ok(program) = Program::from_bytes_lenient(data) {
+        let _ = program.to_asm();
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use ezbpf_core::program_header::ProgramHeader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(h) = ProgramHeader::from_bytes(data) {
+        let _ = h.to_bytes();
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use ezbpf_core::section_header::SectionHeader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(h) = SectionHeader::from_bytes(data) {
+        let _ = h.to_bytes();
+    }
+});
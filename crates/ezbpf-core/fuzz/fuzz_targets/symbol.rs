@@ -0,0 +1,12 @@
+#![no_main]
+
+use ezbpf_core::symbol::Symbol;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = Symbol::from_bytes(data) {
+        // Every field is kept as found, so the entry round trips
+        let b = s.to_bytes();
+        assert_eq!(b, data[..b.len()]);
+    }
+});
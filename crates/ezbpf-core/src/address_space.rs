@@ -0,0 +1,287 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    program::Program,
+    program_header::ProgramType,
+    sbpf_version::SbpfVersion,
+    section_header::SHF_ALLOC,
+};
+
+pub const MM_BYTECODE_START: u64 = 0; // Where V3 programs map their bytecode
+pub const MM_PROGRAM_START: u64 = 0x100000000; // The program region of the Solana VM
+
+// A contiguous run of the file that is loaded at a virtual address
+#[derive(Debug, Clone)]
+struct Mapping {
+    offset: u64,
+    vaddr: u64,
+    size: u64,
+}
+
+impl Mapping {
+    fn offset_to_vaddr(&self, offset: u64) -> Option<u64> {
+        let delta = offset.checked_sub(self.offset)?;
+        (delta < self.size).then(|| self.vaddr.wrapping_add(delta))
+    }
+
+    fn vaddr_to_offset(&self, vaddr: u64) -> Option<u64> {
+        let delta = vaddr.checked_sub(self.vaddr)?;
+        (delta < self.size).then(|| self.offset.wrapping_add(delta))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    offset: u64,
+    size: u64,
+    ixs: Vec<u64>, // Offset of each instruction or data slot from the start of the section
+}
+
+/// One location in a program, in each of the ways it can be addressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Address {
+    pub offset: u64,            // Offset in the file.
+    pub vaddr: Option<u64>,     // Virtual address given by the ELF headers, if the offset is loaded.
+    pub vm_addr: Option<u64>,   // Address the Solana VM sees, if the offset is loaded.
+    pub section: Option<usize>, // Index of the section containing the offset.
+    pub ix: Option<usize>,      // Index of the instruction containing the offset, within its section.
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "offset {:#x}", self.offset)?;
+        if let Some(vaddr) = self.vaddr {
+            write!(f, ", vaddr {:#x}", vaddr)?;
+        }
+        if let Some(vm_addr) = self.vm_addr {
+            write!(f, ", vm {:#x}", vm_addr)?;
+        }
+        if let Some(section) = self.section {
+            write!(f, ", section #{}", section)?;
+        }
+        if let Some(ix) = self.ix {
+            write!(f, ", instruction #{}", ix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Translates between file offsets, the virtual addresses in the ELF headers,
+/// addresses in the Solana VM and section and instruction indices.
+///
+/// Virtual addresses come from the PT_LOAD segments, falling back to the
+/// addresses of SHF_ALLOC sections for anything the segments don't cover. The
+/// VM maps the program region at MM_PROGRAM_START, so before V3 virtual
+/// addresses are offset into it the way the loader does. V3 programs place
+/// their segments at VM addresses directly.
+#[derive(Debug, Clone)]
+pub struct AddressSpace {
+    version: SbpfVersion,
+    segments: Vec<Mapping>,
+    allocated: Vec<Mapping>,
+    sections: Vec<Section>,
+}
+
+impl AddressSpace {
+    pub fn new(program: &Program) -> Self {
+        let segments = program
+            .program_headers
+            .iter()
+            .filter(|h| h.p_type == ProgramType::PT_LOAD)
+            .map(|h| Mapping {
+                offset: h.p_offset,
+                vaddr: h.p_vaddr,
+                size: h.p_filesz,
+            })
+            .collect();
+        let allocated = program
+            .section_headers
+            .iter()
+            .filter(|h| h.sh_flags.contains(SHF_ALLOC))
+            .map(|h| Mapping {
+                offset: h.sh_offset,
+                vaddr: h.sh_addr,
+                size: h.sh_size,
+            })
+            .collect();
+        let sections = program
+            .section_header_entries
+            .iter()
+            .map(|e| {
+                let mut ixs = Vec::with_capacity(e.items.len());
+                let mut offset = 0u64;
+                for item in &e.items {
+                    ixs.push(offset);
+                    offset = offset.wrapping_add(item.size() as u64);
+                }
+                Section {
+                    offset: e.offset as u64,
+                    size: e.data.len() as u64,
+                    ixs,
+                }
+            })
+            .collect();
+        Self {
+            version: program.sbpf_version,
+            segments,
+            allocated,
+            sections,
+        }
+    }
+
+    pub fn offset_to_vaddr(&self, offset: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .chain(&self.allocated)
+            .find_map(|m| m.offset_to_vaddr(offset))
+    }
+
+    pub fn vaddr_to_offset(&self, vaddr: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .chain(&self.allocated)
+            .find_map(|m| m.vaddr_to_offset(vaddr))
+    }
+
+    pub fn vaddr_to_vm(&self, vaddr: u64) -> u64 {
+        if self.version.enable_stricter_elf_headers()
+            || (self.version.enable_elf_vaddr() && vaddr >= MM_PROGRAM_START)
+        {
+            vaddr
+        } else {
+            vaddr.saturating_add(MM_PROGRAM_START)
+        }
+    }
+
+    pub fn vm_to_vaddr(&self, vm_addr: u64) -> Option<u64> {
+        if self.version.enable_stricter_elf_headers() {
+            return Some(vm_addr);
+        }
+        // Before V1 every address is offset into the program region, so a VM
+        // address maps back to at most one virtual address. After it, an address
+        // in the region may also have been given by the ELF headers as is.
+        let offset = vm_addr.checked_sub(MM_PROGRAM_START);
+        if self.version.enable_elf_vaddr() {
+            match offset {
+                Some(vaddr) if self.vaddr_to_offset(vaddr).is_some() => Some(vaddr),
+                _ if self.vaddr_to_offset(vm_addr).is_some() => Some(vm_addr),
+                _ => offset,
+            }
+        } else {
+            offset
+        }
+    }
+
+    pub fn offset_to_vm(&self, offset: u64) -> Option<u64> {
+        self.offset_to_vaddr(offset).map(|vaddr| self.vaddr_to_vm(vaddr))
+    }
+
+    pub fn vm_to_offset(&self, vm_addr: u64) -> Option<u64> {
+        self.vm_to_vaddr(vm_addr)
+            .and_then(|vaddr| self.vaddr_to_offset(vaddr))
+    }
+
+    /// Index of the section whose data contains `offset`.
+    pub fn section_at(&self, offset: u64) -> Option<usize> {
+        self.sections
+            .iter()
+            .position(|s| offset >= s.offset && offset - s.offset < s.size)
+    }
+
+    /// The section and index of the instruction whose bytes contain `offset`.
+    pub fn ix_at(&self, offset: u64) -> Option<(usize, usize)> {
+        let index = self.section_at(offset)?;
+        let section = &self.sections[index];
+        let delta = offset - section.offset;
+        let ix = section.ixs.partition_point(|start| *start <= delta).checked_sub(1)?;
+        Some((index, ix))
+    }
+
+    /// File offset of instruction `ix` of section `section`.
+    pub fn ix_offset(&self, section: usize, ix: usize) -> Option<u64> {
+        let section = self.sections.get(section)?;
+        section.ixs.get(ix).map(|start| section.offset + start)
+    }
+
+    pub fn resolve_offset(&self, offset: u64) -> Address {
+        let vaddr = self.offset_to_vaddr(offset);
+        let ix = self.ix_at(offset);
+        Address {
+            offset,
+            vaddr,
+            vm_addr: vaddr.map(|vaddr| self.vaddr_to_vm(vaddr)),
+            section: self.section_at(offset),
+            ix: ix.map(|(_, ix)| ix),
+        }
+    }
+
+    pub fn resolve_vaddr(&self, vaddr: u64) -> Option<Address> {
+        self.vaddr_to_offset(vaddr)
+            .map(|offset| self.resolve_offset(offset))
+    }
+
+    pub fn resolve_vm(&self, vm_addr: u64) -> Option<Address> {
+        self.vm_to_offset(vm_addr)
+            .map(|offset| self.resolve_offset(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        address_space::{Address, MM_PROGRAM_START},
+        fixtures::{STRICT_HEADER, SYSCALL_RELOC_64_32},
+        program::Program,
+    };
+
+    #[test]
+    fn translate_v0() {
+        let program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        let space = program.address_space();
+        assert_eq!(space.offset_to_vaddr(0x138), Some(0x138));
+        assert_eq!(space.offset_to_vm(0x138), Some(MM_PROGRAM_START + 0x138));
+        assert_eq!(space.vm_to_offset(MM_PROGRAM_START + 0x138), Some(0x138));
+        assert_eq!(space.vm_to_offset(0x138), None);
+        assert_eq!(space.section_at(0x138), Some(1));
+        // The second half of lddw belongs to the lddw
+        assert_eq!(space.ix_at(0x128), Some((1, 0)));
+        assert_eq!(space.ix_at(0x13c), Some((1, 2)));
+        assert_eq!(space.ix_offset(1, 2), Some(0x138));
+        assert_eq!(space.ix_offset(2, 0), None);
+        assert_eq!(
+            space.resolve_vm(MM_PROGRAM_START + 0x150),
+            Some(Address {
+                offset: 0x150,
+                vaddr: Some(0x150),
+                vm_addr: Some(MM_PROGRAM_START + 0x150),
+                section: Some(2),
+                ix: None,
+            })
+        );
+        assert_eq!(
+            space.resolve_offset(0x138).to_string(),
+            "offset 0x138, vaddr 0x138, vm 0x100000138, section #1, instruction #2"
+        );
+
+        // Sections outside of any PT_LOAD or SHF_ALLOC section have no address
+        let comment = &program.section_headers[7];
+        let address = space.resolve_offset(comment.sh_offset);
+        assert_eq!(address.vaddr, None);
+        assert_eq!(address.section, Some(7));
+    }
+
+    #[test]
+    fn translate_v3() {
+        let program = Program::from_bytes(&STRICT_HEADER).unwrap();
+        let space = program.address_space();
+        // Bytecode is at the bottom of the address space and read only data
+        // in the program region, both used by the VM as is
+        assert_eq!(space.offset_to_vm(0x1a0), Some(0x10));
+        assert_eq!(space.offset_to_vm(0x1c0), Some(MM_PROGRAM_START));
+        assert_eq!(space.vm_to_offset(MM_PROGRAM_START + 8), Some(0x1c8));
+        assert_eq!(space.ix_at(0x1a0), Some((1, 2)));
+        assert_eq!(space.ix_offset(1, 5), Some(0x1b8));
+    }
+}
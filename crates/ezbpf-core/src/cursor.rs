@@ -1,21 +1,27 @@
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::io::{Cursor, Read};
 
 use crate::{
-    elf_header::{
-        ELFHeader, EI_ABIVERSION, EI_CLASS, EI_DATA, EI_MAGIC, EI_OSABI, EI_PAD, EI_VERSION, E_MACHINE, E_MACHINE_SBPF, E_TYPE, E_VERSION
-    },
+    dynamic::DynamicEntry,
+    elf_header::ELFHeader,
     errors::EZBpfError,
     instructions::Ix,
     opcodes::OpCode,
     program_header::{ProgramFlags, ProgramHeader, ProgramType},
-    section_header::{SectionHeader, SectionHeaderType},
+    relocation::{Relocation, RelocationType},
+    sbpf_version::SbpfVersion,
+    section_header::{SectionFlags, SectionHeader, SectionHeaderType},
+    symbol::{Symbol, SymbolBinding, SymbolType},
 };
 
 pub trait ELFCursor {
     fn read_elf_header(&mut self) -> Result<ELFHeader, EZBpfError>;
+    fn read_elf_header_unchecked(&mut self) -> Result<ELFHeader, EZBpfError>;
     fn read_program_header(&mut self) -> Result<ProgramHeader, EZBpfError>;
     fn read_section_header(&mut self) -> Result<SectionHeader, EZBpfError>;
-    fn read_ix(&mut self) -> Result<Ix, EZBpfError>;
+    fn read_symbol(&mut self) -> Result<Symbol, EZBpfError>;
+    fn read_relocation(&mut self) -> Result<Relocation, EZBpfError>;
+    fn read_dynamic_entry(&mut self) -> Result<DynamicEntry, EZBpfError>;
+    fn read_ix(&mut self, version: SbpfVersion) -> Result<Ix, EZBpfError>;
     fn read_lddw_imm(&mut self) -> Result<i64, EZBpfError>;
     fn read_u8(&mut self) -> Result<u8, EZBpfError>;
     fn read_i16(&mut self) -> Result<i16, EZBpfError>;
@@ -25,10 +31,20 @@ pub trait ELFCursor {
     fn read_u64(&mut self) -> Result<u64, EZBpfError>;
     fn read_bytes(&mut self, l: usize) -> Result<Vec<u8>, EZBpfError>;
     fn remainder(&mut self) -> u64;
+    fn read_into(&mut self, b: &mut [u8]) -> Result<(), EZBpfError>;
 }
 
 impl ELFCursor for Cursor<&[u8]> {
     fn read_elf_header(&mut self) -> Result<ELFHeader, EZBpfError> {
+        let start = self.position();
+        let h = self.read_elf_header_unchecked()?;
+        match h.non_standard_fields().into_iter().next() {
+            Some(e) => Err(e.shift(start)),
+            None => Ok(h),
+        }
+    }
+
+    fn read_elf_header_unchecked(&mut self) -> Result<ELFHeader, EZBpfError> {
         let ei_magic = self.read_u32()?.to_le_bytes();
         let ei_class = self.read_u8()?;
         let ei_data = self.read_u8()?;
@@ -40,20 +56,6 @@ impl ELFCursor for Cursor<&[u8]> {
         let e_type = self.read_u16()?;
         let e_machine = self.read_u16()?;
         let e_version = self.read_u32()?;
-        if ei_magic.ne(&EI_MAGIC)
-            || ei_class.ne(&EI_CLASS)
-            || ei_data.ne(&EI_DATA)
-            || ei_version.ne(&EI_VERSION)
-            || ei_osabi.ne(&EI_OSABI)
-            || ei_abiversion.ne(&EI_ABIVERSION)
-            || ei_pad.ne(&EI_PAD)
-            || e_type.ne(&E_TYPE)
-            || (e_machine.ne(&E_MACHINE) && e_machine.ne(&E_MACHINE_SBPF))
-            || e_version.ne(&E_VERSION)
-        {
-            return Err(EZBpfError::NonStandardElfHeader);
-        }
-
         let e_entry = self.read_u64()?;
         let e_phoff = self.read_u64()?;
         let e_shoff = self.read_u64()?;
@@ -89,7 +91,7 @@ impl ELFCursor for Cursor<&[u8]> {
     }
 
     fn read_program_header(&mut self) -> Result<ProgramHeader, EZBpfError> {
-        let p_type = ProgramType::try_from(self.read_u32()?)?;
+        let p_type = ProgramType::from(self.read_u32()?);
         let p_flags = ProgramFlags::from(self.read_u32()?);
         let p_offset = self.read_u64()?;
         let p_vaddr = self.read_u64()?;
@@ -111,8 +113,8 @@ impl ELFCursor for Cursor<&[u8]> {
 
     fn read_section_header(&mut self) -> Result<SectionHeader, EZBpfError> {
         let sh_name = self.read_u32()?;
-        let sh_type = SectionHeaderType::try_from(self.read_u32()?)?;
-        let sh_flags = self.read_u64()?;
+        let sh_type = SectionHeaderType::from(self.read_u32()?);
+        let sh_flags = SectionFlags::from(self.read_u64()?);
         let sh_addr = self.read_u64()?;
         let sh_offset = self.read_u64()?;
         let sh_size = self.read_u64()?;
@@ -134,60 +136,97 @@ impl ELFCursor for Cursor<&[u8]> {
         })
     }
 
+    fn read_symbol(&mut self) -> Result<Symbol, EZBpfError> {
+        let st_name = self.read_u32()?;
+        let st_info = self.read_u8()?;
+        let st_other = self.read_u8()?;
+        let st_shndx = self.read_u16()?;
+        let st_value = self.read_u64()?;
+        let st_size = self.read_u64()?;
+        Ok(Symbol {
+            name: String::new(),
+            st_name,
+            st_bind: SymbolBinding::from(st_info >> 4),
+            st_type: SymbolType::from(st_info & 0x0f),
+            st_other,
+            st_shndx,
+            st_value,
+            st_size,
+        })
+    }
+
+    fn read_relocation(&mut self) -> Result<Relocation, EZBpfError> {
+        let r_offset = self.read_u64()?;
+        let r_type = RelocationType::from(self.read_u32()?);
+        let r_sym = self.read_u32()?;
+        Ok(Relocation {
+            r_offset,
+            r_type,
+            r_sym,
+            symbol: String::new(),
+        })
+    }
+
+    fn read_dynamic_entry(&mut self) -> Result<DynamicEntry, EZBpfError> {
+        let d_tag = self.read_u64()?;
+        let d_val = self.read_u64()?;
+        Ok(DynamicEntry::new(d_tag, d_val))
+    }
+
     fn read_u8(&mut self) -> Result<u8, EZBpfError> {
         let mut b = [0u8];
-        self.read_exact(&mut b)
-            .map_err(|_| EZBpfError::CursorError)?;
+        self.read_into(&mut b)?;
         Ok(b[0])
     }
 
     fn read_u16(&mut self) -> Result<u16, EZBpfError> {
         let mut b = [0u8; 2];
-        self.read_exact(&mut b)
-            .map_err(|_| EZBpfError::CursorError)?;
+        self.read_into(&mut b)?;
         Ok(u16::from_le_bytes(b))
     }
 
     fn read_i16(&mut self) -> Result<i16, EZBpfError> {
         let mut b = [0u8; 2];
-        self.read_exact(&mut b)
-            .map_err(|_| EZBpfError::CursorError)?;
+        self.read_into(&mut b)?;
         Ok(i16::from_le_bytes(b))
     }
 
     fn read_u32(&mut self) -> Result<u32, EZBpfError> {
         let mut b = [0u8; 4];
-        self.read_exact(&mut b)
-            .map_err(|_| EZBpfError::CursorError)?;
+        self.read_into(&mut b)?;
         Ok(u32::from_le_bytes(b))
     }
 
     fn read_i32(&mut self) -> Result<i32, EZBpfError> {
         let mut b = [0u8; 4];
-        self.read_exact(&mut b)
-            .map_err(|_| EZBpfError::CursorError)?;
+        self.read_into(&mut b)?;
         Ok(i32::from_le_bytes(b))
     }
 
     fn read_u64(&mut self) -> Result<u64, EZBpfError> {
         let mut b = [0u8; 8];
-        self.read_exact(&mut b)
-            .map_err(|_| EZBpfError::CursorError)?;
+        self.read_into(&mut b)?;
         Ok(u64::from_le_bytes(b))
     }
 
     fn read_lddw_imm(&mut self) -> Result<i64, EZBpfError> {
         let mut b = [0u8; 8];
         b[0..4].clone_from_slice(&self.read_bytes(4)?);
-        if self.read_u32()? != 0 {
-            return Err(EZBpfError::InvalidImmediate);
+        let offset = self.position();
+        let reserved = self.read_u32()?;
+        if reserved != 0 {
+            return Err(EZBpfError::InvalidImmediate {
+                offset: Some(offset),
+                value: reserved as i64,
+            });
         }
         b[4..8].clone_from_slice(&self.read_bytes(4)?);
         Ok(i64::from_le_bytes(b))
     }
 
-    fn read_ix(&mut self) -> Result<Ix, EZBpfError> {
-        let op = OpCode::try_from(self.read_u8()?)?;
+    fn read_ix(&mut self, version: SbpfVersion) -> Result<Ix, EZBpfError> {
+        let offset = self.position();
+        let op = OpCode::try_from((self.read_u8()?, version)).map_err(|e| e.at(offset))?;
         let reg = self.read_u8()?;
         let src = reg >> 4;
         let dst = reg & 0x0f;
@@ -207,15 +246,19 @@ impl ELFCursor for Cursor<&[u8]> {
 
     fn read_bytes(&mut self, l: usize) -> Result<Vec<u8>, EZBpfError> {
         let mut v = vec![0_u8; l];
-        self.read_exact(&mut v)
-            .map_err(|_| EZBpfError::CursorError)?;
+        self.read_into(&mut v)?;
         Ok(v)
     }
 
+    fn read_into(&mut self, b: &mut [u8]) -> Result<(), EZBpfError> {
+        let offset = self.position();
+        self.read_exact(b).map_err(|_| EZBpfError::CursorError {
+            offset: Some(offset),
+            len: b.len(),
+        })
+    }
+
     fn remainder(&mut self) -> u64 {
-        let pos = self.position();
-        let end = self.seek(SeekFrom::End(0)).unwrap();
-        self.seek(SeekFrom::Start(pos)).unwrap();
-        end - pos
+        (self.get_ref().len() as u64).saturating_sub(self.position())
     }
 }
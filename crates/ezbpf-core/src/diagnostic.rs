@@ -0,0 +1,100 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{address_space::Address, errors::EZBpfError};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Severity {
+    Warning, // The value is unusual but was parsed as-is.
+    Error,   // The piece could not be parsed and was skipped or replaced.
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+// Something that went wrong while parsing leniently
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,  // Whether the piece was kept or skipped.
+    pub offset: Option<u64>, // File offset of the problem, if it has one.
+    pub message: String,     // What went wrong, including the structure being parsed.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub data: Vec<u8>, // Raw bytes of the piece that could not be parsed.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub address: Option<Address>, // Where the offset is loaded, once the program is known.
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, e: &EZBpfError, data: &[u8]) -> Self {
+        Self {
+            severity,
+            offset: e.offset(),
+            message: e.to_string(),
+            data: data.to_vec(),
+            address: None,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        if let Some(vm_addr) = self.address.and_then(|a| a.vm_addr) {
+            write!(f, " (vm {:#x})", vm_addr)?;
+        }
+        Ok(())
+    }
+}
+
+// Decides what happens to a recoverable error. Strict parsing returns it, while
+// lenient parsing records it and lets the caller carry on with a fallback.
+#[derive(Debug, Default)]
+pub(crate) struct Recovery {
+    pub lenient: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Recovery {
+    pub fn new(lenient: bool) -> Self {
+        Self {
+            lenient,
+            diagnostics: vec![],
+        }
+    }
+
+    pub fn recover(
+        &mut self,
+        severity: Severity,
+        e: EZBpfError,
+        data: &[u8],
+    ) -> Result<(), EZBpfError> {
+        if !self.lenient {
+            return Err(e);
+        }
+        self.diagnostics.push(Diagnostic::new(severity, &e, data));
+        Ok(())
+    }
+
+    // Records a problem that was worked around whether parsing is lenient or not
+    pub fn warn(&mut self, e: EZBpfError, data: &[u8]) {
+        self.diagnostics.push(Diagnostic::new(Severity::Warning, &e, data));
+    }
+
+    // Unwraps a result, falling back to a default value when lenient
+    pub fn or<T>(&mut self, r: Result<T, EZBpfError>, default: T) -> Result<T, EZBpfError> {
+        match r {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                self.recover(Severity::Error, e, &[])?;
+                Ok(default)
+            }
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use std::{collections::HashSet, io::Cursor};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cursor::ELFCursor, errors::EZBpfError, instructions::Ix, opcodes::OpCode,
+    sbpf_version::SbpfVersion,
+};
+
+/// How the instructions of a section are told apart from data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strategy {
+    #[default]
+    LinearSweep, // Every slot that decodes is an instruction.
+    RecursiveDescent, // Only slots reached from an entry point are instructions.
+}
+
+/// How a call instruction finds what it calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CallKind {
+    Relative,   // The immediate is the distance in slots to a function in the program.
+    Hashed,     // The immediate is the murmur3 hash of the called function's slot.
+    Relocated,  // A relocation against a function symbol fills in the immediate.
+    Syscall,    // Calls into the runtime rather than the program.
+    Register,   // callx, with the target only known at runtime.
+    Unresolved, // The target could not be worked out.
+}
+
+/// A call instruction and what it calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Call {
+    pub section: usize, // Index of the section holding the call.
+    pub pc: usize,      // Slot of the call, counted from the start of its section.
+    pub kind: CallKind,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub target: Option<usize>, // Slot of the called function, in the same section.
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub name: String, // Name of the function or syscall called, if known.
+}
+
+/// A decoded run of a section: an instruction, or the bytes of a slot that
+/// doesn't hold one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Item {
+    Ix(Ix),
+    Data(Vec<u8>),
+}
+
+impl Item {
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Ix(ix) => ix.size(),
+            Self::Data(data) => data.len(),
+        }
+    }
+
+    pub fn ix(&self) -> Option<&Ix> {
+        match self {
+            Self::Ix(ix) => Some(ix),
+            Self::Data(_) => None,
+        }
+    }
+
+    pub fn to_bytes(&self, version: SbpfVersion) -> Result<Vec<u8>, EZBpfError> {
+        match self {
+            Self::Ix(ix) => ix.to_bytes(version),
+            Self::Data(data) => Ok(data.clone()),
+        }
+    }
+
+    // Whole slots are written as a little endian .quad, anything shorter byte
+    // by byte
+    pub fn to_asm(&self, version: SbpfVersion) -> Result<String, EZBpfError> {
+        match self {
+            Self::Ix(ix) => ix.to_asm(version),
+            Self::Data(data) => Ok(match <[u8; 8]>::try_from(data.as_slice()) {
+                Ok(quad) => format!(".quad {:#018x}", u64::from_le_bytes(quad)),
+                Err(_) => {
+                    let bytes: Vec<String> = data.iter().map(|b| format!("{:#04x}", b)).collect();
+                    format!(".byte {}", bytes.join(", "))
+                }
+            }),
+        }
+    }
+}
+
+/// One step of a linear sweep, at `offset` from the start of the section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot {
+    pub offset: usize,
+    pub item: Item,
+    pub error: Option<EZBpfError>, // Why the slot is data rather than an instruction.
+}
+
+/// Decodes a section slot by slot from start to end. A slot that isn't a valid
+/// instruction, including the first half of a broken lddw, becomes an 8 byte
+/// data item and decoding carries on with the next slot, so that one bad word
+/// doesn't hide the rest of the section. Error offsets are relative to the
+/// start of the section.
+#[derive(Debug, Clone)]
+pub struct LinearSweep<'a> {
+    data: &'a [u8],
+    position: usize,
+    version: SbpfVersion,
+}
+
+impl<'a> LinearSweep<'a> {
+    pub fn new(data: &'a [u8], version: SbpfVersion) -> Self {
+        Self {
+            data,
+            position: 0,
+            version,
+        }
+    }
+}
+
+impl Iterator for LinearSweep<'_> {
+    type Item = Slot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.position;
+        let rest = &self.data[offset.min(self.data.len())..];
+        if rest.is_empty() {
+            return None;
+        }
+        if rest.len() < 8 {
+            self.position = self.data.len();
+            return Some(Slot {
+                offset,
+                item: Item::Data(rest.to_vec()),
+                error: Some(EZBpfError::InvalidDataLength {
+                    offset: Some(offset as u64),
+                    len: self.data.len() as u64,
+                }),
+            });
+        }
+        // Read from the start of the section so that jump targets are
+        // counted from there
+        let mut c = Cursor::new(self.data);
+        c.set_position(offset as u64);
+        let slot = match c.read_ix(self.version) {
+            Ok(ix) => Slot {
+                offset,
+                item: Item::Ix(ix),
+                error: None,
+            },
+            Err(e) => {
+                c.set_position((offset + 8) as u64);
+                Slot {
+                    offset,
+                    item: Item::Data(rest[..8].to_vec()),
+                    error: Some(e),
+                }
+            }
+        };
+        self.position = c.position() as usize;
+        Some(slot)
+    }
+}
+
+/// The slot called by an internal call at `slot`. Before static syscalls only
+/// a call with a source of 1 is relative like a jump. Any other immediate is
+/// the hash of a syscall or function, and -1 is left for the loader to
+/// relocate to one.
+pub fn call_target(ix: &Ix, slot: usize, version: SbpfVersion) -> Option<usize> {
+    if ix.op != OpCode::Call || (!version.static_syscalls() && (ix.src != 1 || ix.imm == -1)) {
+        return None;
+    }
+    (slot as i64)
+        .checked_add(1 + ix.imm)
+        .and_then(|target| usize::try_from(target).ok())
+}
+
+/// The slot each of `items` starts at, which is what jumps and calls are
+/// relative to.
+pub(crate) fn slots(items: &[Item]) -> Vec<usize> {
+    let mut pcs = Vec::with_capacity(items.len());
+    let mut pc = 0;
+    for item in items {
+        pcs.push(pc);
+        pc += item.size().div_ceil(8);
+    }
+    pcs
+}
+
+/// Follows control flow through `items` starting from the items at `entries`,
+/// returning whether each item was reached. Jumps, conditional jumps and
+/// internal calls are followed, while exit, return and jumps into data or
+/// the middle of an instruction end a path.
+pub fn recursive_descent(items: &[Item], entries: &[usize], version: SbpfVersion) -> Vec<bool> {
+    let slots = slots(items);
+    let item_at = |slot: usize| slots.binary_search(&slot).ok();
+
+    let mut reached = vec![false; items.len()];
+    let mut pending: Vec<usize> = entries.to_vec();
+    while let Some(i) = pending.pop() {
+        let Some(Item::Ix(ix)) = items.get(i) else {
+            continue;
+        };
+        if reached[i] {
+            continue;
+        }
+        reached[i] = true;
+        let slot = slots[i];
+        let fall_through = match &ix.op {
+            OpCode::Ja => {
+                pending.extend(ix.jump_target(slot).and_then(item_at));
+                false
+            }
+            OpCode::Exit | OpCode::Return => false,
+            op if op.is_conditional_jump() => {
+                pending.extend(ix.jump_target(slot).and_then(item_at));
+                true
+            }
+            OpCode::Call => {
+                pending.extend(call_target(ix, slot, version).and_then(item_at));
+                true
+            }
+            _ => true,
+        };
+        if fall_through {
+            pending.push(i + 1);
+        }
+    }
+    reached
+}
+
+/// The registers holding a known value before each of `items`, found by
+/// following constants loaded with mov, lddw and hor64 down each basic block.
+/// Nothing is known at the start of a block, after a call or after an
+/// instruction that computes something else into a register. The lddw items at
+/// the indices in `relocated` load whatever the loader relocates them to, so
+/// nothing is known of them either.
+pub fn known_registers(
+    items: &[Item],
+    relocated: &HashSet<usize>,
+    version: SbpfVersion,
+) -> Vec<[Option<u64>; 11]> {
+    let slots = slots(items);
+    // Blocks start wherever a jump or call lands
+    let targets: HashSet<usize> = items
+        .iter()
+        .zip(&slots)
+        .filter_map(|(item, slot)| {
+            let ix = item.ix()?;
+            ix.jump_target(*slot).or_else(|| call_target(ix, *slot, version))
+        })
+        .collect();
+
+    let mut known = Vec::with_capacity(items.len());
+    let mut registers = [None; 11];
+    for (i, (item, slot)) in items.iter().zip(&slots).enumerate() {
+        if targets.contains(slot) {
+            registers = [None; 11];
+        }
+        known.push(registers);
+        let Some(ix) = item.ix() else {
+            registers = [None; 11];
+            continue;
+        };
+        let dst = ix.dst as usize;
+        match &ix.op {
+            OpCode::Ja | OpCode::Exit | OpCode::Return => registers = [None; 11],
+            // r0 is the result and r1 to r5 are the arguments
+            op if op.is_call() => registers[..6].fill(None),
+            op if op.is_jump() => {}
+            OpCode::Stb | OpCode::Sth | OpCode::Stw | OpCode::Stdw => {}
+            OpCode::Stxb | OpCode::Stxh | OpCode::Stxw | OpCode::Stxdw => {}
+            _ if dst >= registers.len() => {}
+            OpCode::Lddw if relocated.contains(&i) => registers[dst] = None,
+            OpCode::Mov64Imm | OpCode::Lddw => registers[dst] = Some(ix.imm as u64),
+            OpCode::Mov32Imm => registers[dst] = Some(ix.imm as u32 as u64),
+            OpCode::Mov64Reg => registers[dst] = registers.get(ix.src as usize).copied().flatten(),
+            OpCode::Hor64Imm => {
+                registers[dst] = registers[dst].map(|v| v | ((ix.imm as u64) << 32));
+            }
+            OpCode::Add64Imm => registers[dst] = registers[dst].map(|v| v.wrapping_add(ix.imm as u64)),
+            _ => registers[dst] = None,
+        }
+    }
+    known
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use hex_literal::hex;
+
+    use crate::{
+        disassembler::{known_registers, recursive_descent, Item, LinearSweep},
+        errors::EZBpfError,
+        sbpf_version::SbpfVersion,
+    };
+
+    #[test]
+    fn linear_sweep() {
+        // exit, a bad opcode, an lddw missing its second half, exit and a
+        // few stray bytes
+        let b = hex!("9500000000000000 ff00000000000000 1801000000000000 9500000000000000 010203");
+        let slots: Vec<_> = LinearSweep::new(&b, SbpfVersion::V0).collect();
+        assert_eq!(slots.len(), 5);
+        assert!(slots[0].error.is_none());
+        assert_eq!(slots[1].item, Item::Data(b[8..16].to_vec()));
+        assert_eq!(
+            slots[1].error,
+            Some(EZBpfError::InvalidOpcode {
+                offset: Some(8),
+                value: 0xff
+            })
+        );
+        assert_eq!(slots[2].offset, 16);
+        assert_eq!(slots[2].item.to_asm(SbpfVersion::V0).unwrap(), ".quad 0x0000000000000118");
+        assert_eq!(slots[3].offset, 24);
+        assert_eq!(slots[3].item.to_asm(SbpfVersion::V0).unwrap(), "exit");
+        assert_eq!(slots[4].item.to_asm(SbpfVersion::V0).unwrap(), ".byte 0x01, 0x02, 0x03");
+        assert!(matches!(
+            slots[4].error,
+            Some(EZBpfError::InvalidDataLength { len: 35, .. })
+        ));
+    }
+
+    #[test]
+    fn follows_control_flow() {
+        // ja +2, padding, a lone lddw half, jeq r1, 0, +1, exit, exit and
+        // call -4 back to the jeq, never reached
+        let b = hex!("0500020000000000 0000000000000000 1801000000000000 1501010000000000 9500000000000000 9500000000000000 8510000000fcffff");
+        let items: Vec<Item> = LinearSweep::new(&b, SbpfVersion::V0).map(|s| s.item).collect();
+        let reached = recursive_descent(&items, &[0], SbpfVersion::V0);
+        assert_eq!(reached, [true, false, false, true, true, true, false]);
+
+        // Calling back into the program from the unreached call
+        let reached = recursive_descent(&items, &[0, 6], SbpfVersion::V0);
+        assert!(reached[6]);
+        // An immediate of -1 is left for the loader to relocate, so isn't followed
+        let call = hex!("85100000ffffffff 9500000000000000");
+        let items: Vec<Item> = LinearSweep::new(&call, SbpfVersion::V0).map(|s| s.item).collect();
+        assert_eq!(recursive_descent(&items, &[0], SbpfVersion::V0), [true, true]);
+        // Nor is a call with a source of 0, whose immediate is a hash
+        let call = hex!("8500000001000000 9500000000000000 9500000000000000");
+        let items: Vec<Item> = LinearSweep::new(&call, SbpfVersion::V0).map(|s| s.item).collect();
+        assert_eq!(recursive_descent(&items, &[0], SbpfVersion::V0), [true, true, false]);
+    }
+
+    #[test]
+    fn constants() {
+        // mov64 r1, 5, mov64 r2, r1, add64 r2, 3, mov32 r3, 1, hor64 r3, 2,
+        // add64 r4, r1, call -1, jeq r1, 0, +0, exit
+        let b = hex!("b701000005000000 bf12000000000000 0702000003000000 b403000001000000 f703000002000000 0f14000000000000 85100000ffffffff 1501000000000000 9500000000000000");
+        let items: Vec<Item> = LinearSweep::new(&b, SbpfVersion::V2).map(|s| s.item).collect();
+        let known = known_registers(&items, &HashSet::new(), SbpfVersion::V2);
+        assert_eq!(known[0], [None; 11]);
+        assert_eq!(known[5][1..4], [Some(5), Some(8), Some(0x200000001)]);
+        assert_eq!(known[6][4], None);
+        // The call clobbers r0 to r5
+        assert_eq!(known[7][1..4], [None; 3]);
+        // The jeq lands on the exit, starting a new block
+        assert_eq!(known[8], [None; 11]);
+
+        // lddw r1, 0x120 and exit, with the lddw relocated or not
+        let b = hex!("1801000020010000 0000000000000000 9500000000000000");
+        let items: Vec<Item> = LinearSweep::new(&b, SbpfVersion::V0).map(|s| s.item).collect();
+        let known = known_registers(&items, &HashSet::new(), SbpfVersion::V0);
+        assert_eq!(known[1][1], Some(0x120));
+        let known = known_registers(&items, &HashSet::from([0]), SbpfVersion::V0);
+        assert_eq!(known[1][1], None);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fmt::Display, io::Cursor};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cursor::ELFCursor, errors::EZBpfError};
+
+pub const DYNAMIC_ENTRY_SIZE: u64 = 16; // Size of an Elf64_Dyn entry
+
+// Dynamic array tags
+pub const DT_NULL: u64 = 0x00;
+pub const DT_STRTAB: u64 = 0x05;
+pub const DT_SYMTAB: u64 = 0x06;
+pub const DT_STRSZ: u64 = 0x0a;
+pub const DT_SYMENT: u64 = 0x0b;
+pub const DT_REL: u64 = 0x11;
+pub const DT_RELSZ: u64 = 0x12;
+pub const DT_RELENT: u64 = 0x13;
+pub const DT_TEXTREL: u64 = 0x16;
+pub const DT_FLAGS: u64 = 0x1e;
+pub const DT_RELCOUNT: u64 = 0x6ffffffa;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DynamicEntry {
+    DT_NULL,                          // Marks the end of the dynamic array.
+    DT_STRTAB(u64),                   // Address of the dynamic string table.
+    DT_SYMTAB(u64),                   // Address of the dynamic symbol table.
+    DT_STRSZ(u64),                    // Size in bytes of the dynamic string table.
+    DT_SYMENT(u64),                   // Size in bytes of a dynamic symbol table entry.
+    DT_REL(u64),                      // Address of the relocation table.
+    DT_RELSZ(u64),                    // Size in bytes of the relocation table.
+    DT_RELENT(u64),                   // Size in bytes of a relocation table entry.
+    DT_TEXTREL(u64),                  // Relocations may modify non-writable segments.
+    DT_FLAGS(u64),                    // Flags for the object being loaded.
+    DT_RELCOUNT(u64),                 // Number of R_BPF_64_RELATIVE relocations.
+    Other(u64, u64),                  // Any other tag and its value, kept as-is.
+}
+
+impl DynamicEntry {
+    pub fn new(tag: u64, value: u64) -> Self {
+        match tag {
+            DT_NULL => Self::DT_NULL,
+            DT_STRTAB => Self::DT_STRTAB(value),
+            DT_SYMTAB => Self::DT_SYMTAB(value),
+            DT_STRSZ => Self::DT_STRSZ(value),
+            DT_SYMENT => Self::DT_SYMENT(value),
+            DT_REL => Self::DT_REL(value),
+            DT_RELSZ => Self::DT_RELSZ(value),
+            DT_RELENT => Self::DT_RELENT(value),
+            DT_TEXTREL => Self::DT_TEXTREL(value),
+            DT_FLAGS => Self::DT_FLAGS(value),
+            DT_RELCOUNT => Self::DT_RELCOUNT(value),
+            tag => Self::Other(tag, value),
+        }
+    }
+
+    pub fn tag(&self) -> u64 {
+        match self {
+            Self::DT_NULL => DT_NULL,
+            Self::DT_STRTAB(_) => DT_STRTAB,
+            Self::DT_SYMTAB(_) => DT_SYMTAB,
+            Self::DT_STRSZ(_) => DT_STRSZ,
+            Self::DT_SYMENT(_) => DT_SYMENT,
+            Self::DT_REL(_) => DT_REL,
+            Self::DT_RELSZ(_) => DT_RELSZ,
+            Self::DT_RELENT(_) => DT_RELENT,
+            Self::DT_TEXTREL(_) => DT_TEXTREL,
+            Self::DT_FLAGS(_) => DT_FLAGS,
+            Self::DT_RELCOUNT(_) => DT_RELCOUNT,
+            Self::Other(tag, _) => *tag,
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        match self {
+            Self::DT_NULL => 0,
+            Self::DT_STRTAB(v)
+            | Self::DT_SYMTAB(v)
+            | Self::DT_STRSZ(v)
+            | Self::DT_SYMENT(v)
+            | Self::DT_REL(v)
+            | Self::DT_RELSZ(v)
+            | Self::DT_RELENT(v)
+            | Self::DT_TEXTREL(v)
+            | Self::DT_FLAGS(v)
+            | Self::DT_RELCOUNT(v) => *v,
+            Self::Other(_, value) => *value,
+        }
+    }
+
+    pub fn from_bytes(b: &[u8]) -> Result<Self, EZBpfError> {
+        let mut c = Cursor::new(b);
+        c.read_dynamic_entry()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.tag().to_le_bytes().to_vec();
+        b.extend_from_slice(&self.value().to_le_bytes());
+        b
+    }
+}
+
+pub fn dynamic_tag_str(tag: u64) -> String {
+    match tag {
+        DT_NULL => "DT_NULL".to_string(),
+        DT_STRTAB => "DT_STRTAB".to_string(),
+        DT_SYMTAB => "DT_SYMTAB".to_string(),
+        DT_STRSZ => "DT_STRSZ".to_string(),
+        DT_SYMENT => "DT_SYMENT".to_string(),
+        DT_REL => "DT_REL".to_string(),
+        DT_RELSZ => "DT_RELSZ".to_string(),
+        DT_RELENT => "DT_RELENT".to_string(),
+        DT_TEXTREL => "DT_TEXTREL".to_string(),
+        DT_FLAGS => "DT_FLAGS".to_string(),
+        DT_RELCOUNT => "DT_RELCOUNT".to_string(),
+        tag => format!("{:#x}", tag),
+    }
+}
+
+// A dynamic entry that disagrees with what the section headers describe. The
+// Solana loader locates relocations and dynamic symbols through .dynamic, so a
+// mismatch means the loader sees a different program than a section-based tool.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DynamicMismatch {
+    pub tag: u64,             // DT_* tag being checked.
+    pub dynamic: Option<u64>, // Value in .dynamic, or None if the tag is missing.
+    pub section: Option<u64>, // Value from the section headers, or None if there is no such section.
+}
+
+impl Display for DynamicMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tag = dynamic_tag_str(self.tag);
+        match (self.dynamic, self.section) {
+            (Some(d), Some(s)) => write!(f, "{} is {:#x} but section headers say {:#x}", tag, d, s),
+            (Some(d), None) => write!(f, "{} is {:#x} but there is no matching section", tag, d),
+            (None, Some(s)) => write!(f, "{} is missing but section headers say {:#x}", tag, s),
+            (None, None) => write!(f, "{} is missing", tag),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use crate::dynamic::{DynamicEntry, DT_FLAGS};
+
+    #[test]
+    fn serialize_e2e() {
+        let b = hex!("11000000000000006002000000000000");
+        let e = DynamicEntry::from_bytes(&b).unwrap();
+        assert_eq!(e, DynamicEntry::DT_REL(0x260));
+        assert_eq!(e.to_bytes(), &b);
+
+        let b = hex!("1e000000000000000400000000000000");
+        let e = DynamicEntry::from_bytes(&b).unwrap();
+        assert_eq!(e.tag(), DT_FLAGS);
+        assert_eq!(e.value(), 4);
+        assert_eq!(e.to_bytes(), &b);
+
+        let b = hex!("fbffff6f000000000100000000000000");
+        let e = DynamicEntry::from_bytes(&b).unwrap();
+        assert_eq!(e, DynamicEntry::Other(0x6ffffffb, 1));
+        assert_eq!(e.to_bytes(), &b);
+        let json = serde_json::to_string(&e).unwrap();
+        assert_eq!(json, r#"{"Other":[1879048187,1]}"#);
+        assert_eq!(serde_json::from_str::<DynamicEntry>(&json).unwrap(), e);
+    }
+}
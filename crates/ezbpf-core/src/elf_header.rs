@@ -16,6 +16,8 @@ pub const E_TYPE: u16 = 0x03; // ET_DYN - shared object
 pub const E_MACHINE: u16 = 0xf7; // Berkeley Packet Filter
 pub const E_MACHINE_SBPF: u16 = 0x0107; // Solana Berkeley Packet Filter
 pub const E_VERSION: u32 = 0x01; // Original version of BPF
+pub const ELF_HEADER_SIZE: u64 = 64; // Size of an Elf64_Ehdr
+pub const E_SHSTRNDX_OFFSET: u64 = 0x3e; // Offset of e_shstrndx within the header
 
 fn elf_magic<S>(magic: &[u8; 4], serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -56,6 +58,38 @@ impl ELFHeader {
         c.read_elf_header()
     }
 
+    /// Every identification field that doesn't hold the value expected of an
+    /// sBPF shared object, with offsets relative to the start of the header.
+    pub fn non_standard_fields(&self) -> Vec<EZBpfError> {
+        let mut pad = [0u8; 8];
+        pad[..7].clone_from_slice(&self.ei_pad);
+        [
+            (self.ei_magic == EI_MAGIC, 0x00, "ei_magic", u32::from_le_bytes(self.ei_magic) as u64),
+            (self.ei_class == EI_CLASS, 0x04, "ei_class", self.ei_class as u64),
+            (self.ei_data == EI_DATA, 0x05, "ei_data", self.ei_data as u64),
+            (self.ei_version == EI_VERSION, 0x06, "ei_version", self.ei_version as u64),
+            (self.ei_osabi == EI_OSABI, 0x07, "ei_osabi", self.ei_osabi as u64),
+            (self.ei_abiversion == EI_ABIVERSION, 0x08, "ei_abiversion", self.ei_abiversion as u64),
+            (self.ei_pad == EI_PAD, 0x09, "ei_pad", u64::from_le_bytes(pad)),
+            (self.e_type == E_TYPE, 0x10, "e_type", self.e_type as u64),
+            (
+                self.e_machine == E_MACHINE || self.e_machine == E_MACHINE_SBPF,
+                0x12,
+                "e_machine",
+                self.e_machine as u64,
+            ),
+            (self.e_version == E_VERSION, 0x14, "e_version", self.e_version as u64),
+        ]
+        .into_iter()
+        .filter(|(valid, ..)| !valid)
+        .map(|(_, offset, field, value)| EZBpfError::NonStandardElfHeader {
+            offset: Some(offset),
+            field,
+            value,
+        })
+        .collect()
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut b = self.ei_magic.to_vec();
         b.extend_from_slice(&[
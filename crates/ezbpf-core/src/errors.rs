@@ -1,21 +1,199 @@
+use std::fmt::Display;
+
+use serde::Serialize;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+use crate::{opcodes::OpCode, sbpf_version::SbpfVersion, validate::MAX_PROGRAM_SIZE};
+
+// Every leaf error carries the file offset it was raised at, when known, and
+// the value that was rejected. Parsers that only see a slice report offsets
+// relative to that slice and callers move them into file space with `shift`.
+// Errors raised away from any file, such as rendering a lone instruction, have
+// no offset. Errors raised while parsing a larger structure are wrapped in
+// `Context` to say which one.
+#[derive(Debug, Clone, Error, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind")]
 pub enum EZBpfError {
-    #[error("Failed to read from cursor")]
-    CursorError,
-    #[error("Non-standard ELF header")]
-    NonStandardElfHeader,
-    #[error("Invalid Program Type")]
-    InvalidProgramType,
-    #[error("Invalid Section Header Type")]
-    InvalidSectionHeaderType,
-    #[error("Invalid OpCode")]
-    InvalidOpcode,
-    #[error("Invalid Immediate")]
-    InvalidImmediate,
-    #[error("Invalid data length")]
-    InvalidDataLength,
-    #[error("Invalid string")]
-    InvalidString
+    #[error("Failed to read {len} bytes{}", at(offset))]
+    CursorError { offset: Option<u64>, len: usize },
+    #[error("Non-standard ELF header, {field} is {value:#x}{}", at(offset))]
+    NonStandardElfHeader {
+        offset: Option<u64>,
+        field: &'static str,
+        value: u64,
+    },
+    #[error("Invalid OpCode {value:#04x}{}", at(offset))]
+    InvalidOpcode { offset: Option<u64>, value: u8 },
+    #[error("Invalid Immediate {value:#x}{}", at(offset))]
+    InvalidImmediate { offset: Option<u64>, value: i64 },
+    #[error("Invalid data length {len:#x}{}", at(offset))]
+    InvalidDataLength { offset: Option<u64>, len: u64 },
+    #[error("Invalid string {value:#x} in string table{}", at(offset))]
+    InvalidString { offset: Option<u64>, value: u64 },
+    #[error("Invalid section index {value}{}", at(offset))]
+    InvalidSectionIndex { offset: Option<u64>, value: u64 },
+    #[error("Invalid Relocation Symbol {value}{}", at(offset))]
+    InvalidRelocationSymbol { offset: Option<u64>, value: u32 },
+    #[error("Invalid assembly {value:?}{}", at(offset))]
+    InvalidAsm { offset: Option<u64>, value: String },
+    #[error("{op} is not supported in SBPF {version}{}", at(offset))]
+    UnsupportedOpcode {
+        offset: Option<u64>,
+        op: OpCode,
+        version: SbpfVersion,
+    },
+    #[error("Program is {len} bytes, more than the maximum of {MAX_PROGRAM_SIZE}")]
+    ProgramTooLarge { len: u64 },
+    #[error("{context}: {error}")]
+    Context {
+        context: ErrorContext,
+        error: Box<EZBpfError>,
+    },
+}
+
+// The structure that was being parsed when an error was raised
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "structure")]
+pub enum ErrorContext {
+    ElfHeader,
+    ProgramHeader { index: usize },
+    SectionHeader { index: usize },
+    Instruction { section: String, index: usize },
+    Symbol { table: String, index: usize },
+    Relocation { index: usize },
+    DynamicEntry { index: usize },
+}
+
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ElfHeader => write!(f, "ELF header"),
+            Self::ProgramHeader { index } => write!(f, "program header #{}", index),
+            Self::SectionHeader { index } => write!(f, "section header #{}", index),
+            Self::Instruction { section, index } => {
+                write!(f, "instruction #{} in {}", index, section)
+            }
+            Self::Symbol { table, index } => write!(f, "symbol #{} in {}", index, table),
+            Self::Relocation { index } => write!(f, "relocation #{}", index),
+            Self::DynamicEntry { index } => write!(f, "dynamic entry #{}", index),
+        }
+    }
+}
+
+// Where an error was raised, for the end of its message
+struct At(Option<u64>);
+
+impl Display for At {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(offset) => write!(f, " at offset {:#x}", offset),
+            None => Ok(()),
+        }
+    }
+}
+
+fn at(offset: &Option<u64>) -> At {
+    At(*offset)
+}
+
+impl EZBpfError {
+    /// File offset the error was raised at, if known.
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Self::CursorError { offset, .. }
+            | Self::NonStandardElfHeader { offset, .. }
+            | Self::InvalidOpcode { offset, .. }
+            | Self::InvalidImmediate { offset, .. }
+            | Self::InvalidDataLength { offset, .. }
+            | Self::InvalidString { offset, .. }
+            | Self::InvalidSectionIndex { offset, .. }
+            | Self::InvalidRelocationSymbol { offset, .. }
+            | Self::InvalidAsm { offset, .. }
+            | Self::UnsupportedOpcode { offset, .. } => *offset,
+            Self::ProgramTooLarge { .. } => None,
+            Self::Context { error, .. } => error.offset(),
+        }
+    }
+
+    /// The innermost error, without any context.
+    pub fn root(&self) -> &Self {
+        match self {
+            Self::Context { error, .. } => error.root(),
+            e => e,
+        }
+    }
+
+    /// Replaces the offset of the error.
+    pub fn at(mut self, to: u64) -> Self {
+        match &mut self {
+            Self::CursorError { offset, .. }
+            | Self::NonStandardElfHeader { offset, .. }
+            | Self::InvalidOpcode { offset, .. }
+            | Self::InvalidImmediate { offset, .. }
+            | Self::InvalidDataLength { offset, .. }
+            | Self::InvalidString { offset, .. }
+            | Self::InvalidSectionIndex { offset, .. }
+            | Self::InvalidRelocationSymbol { offset, .. }
+            | Self::InvalidAsm { offset, .. }
+            | Self::UnsupportedOpcode { offset, .. } => *offset = Some(to),
+            Self::ProgramTooLarge { .. } => {}
+            Self::Context { error, .. } => **error = error.as_ref().clone().at(to),
+        }
+        self
+    }
+
+    /// Moves an offset relative to a slice into file space. An unknown offset
+    /// stays unknown.
+    pub fn shift(self, base: u64) -> Self {
+        match self.offset() {
+            Some(offset) => self.at(offset.saturating_add(base)),
+            None => self,
+        }
+    }
+
+    /// Wraps the error with the structure that was being parsed.
+    pub fn context(self, context: ErrorContext) -> Self {
+        Self::Context {
+            context,
+            error: Box::new(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::{EZBpfError, ErrorContext};
+
+    #[test]
+    fn display_with_context() {
+        let e = EZBpfError::InvalidOpcode {
+            offset: Some(0x10),
+            value: 0xff,
+        }
+        .shift(0x120)
+        .context(ErrorContext::Instruction {
+            section: ".text".to_string(),
+            index: 2,
+        });
+        assert_eq!(e.offset(), Some(0x130));
+        assert_eq!(
+            e.to_string(),
+            "instruction #2 in .text: Invalid OpCode 0xff at offset 0x130"
+        );
+        assert_eq!(
+            serde_json::to_string(&e).unwrap(),
+            r#"{"kind":"Context","context":{"structure":"Instruction","section":".text","index":2},"error":{"kind":"InvalidOpcode","offset":304,"value":255}}"#
+        );
+    }
+
+    #[test]
+    fn display_without_offset() {
+        // Raised while decoding a lone opcode byte, away from any file
+        let e = EZBpfError::InvalidOpcode {
+            offset: None,
+            value: 0xff,
+        };
+        assert_eq!(e.clone().shift(0x120).offset(), None);
+        assert_eq!(e.to_string(), "Invalid OpCode 0xff");
+    }
 }
@@ -0,0 +1,21 @@
+// Small programs built with the Solana platform tools, taken from the solana-sbpf
+// test suite (tests/elfs) so that parsing can be checked against real linker output.
+use hex_literal::hex;
+
+// relative_call.rs built for sBPF v0: .dynsym and .symtab with internal calls
+pub const RELATIVE_CALL: [u8; 1568] = hex!("7F454C46020101000000000000000000030007010100000040010000000000004000000000000000E00300000000000000000000400038000300400009000700010000000500000020010000000000002001000000000000200100000000000080000000000000008000000000000000080000000000000001000000040000001002000000000000100200000000000010020000000000003C000000000000003C0000000000000008000000000000000200000006000000A001000000000000A001000000000000A0010000000000007000000000000000700000000000000008000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000791000000000000007000000010000007B01000000000000950000000000000071160000000000007B6A00FF00000000BFA10000000000000701000000FFFFFF85100000F7FFFFFFBF01000000000000BF6200000000000085100000010000009500000000000000BF200000000000000F1000000000000095000000000000001E000000000000000400000000000000060000000000000010020000000000000B000000000000001800000000000000050000000000000040020000000000000A000000000000000C00000000000000160000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000120001004001000000000000480000000000000000656E747279706F696E74004C696E6B65723A204C4C442031382E312E370000000000000000000000000000000000000000000000000000010000000400F1FF00000000000000000000000000000000260000000200010020010000000000002000000000000000610000000200010088010000000000001800000000000000A100000000020200A0010000000000000000000000000000960000001200010040010000000000004800000000000000002E74657874002E64796E616D6963002E64796E73796D002E64796E737472002E636F6D6D656E74002E73796D746162002E7368737472746162002E737472746162000072656C61746976655F63616C6C2E613330376238356461373238623630382D6367752E30005F5A4E313372656C61746976655F63616C6C313866756E6374696F6E5F737461636B5F7265663137683366666162313231636131613961346145005F5A4E313372656C61746976655F63616C6C313266756E6374696F6E5F73756D313768353062373736623964623131643930644500656E747279706F696E74005F44594E414D494300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000100000001000000060000000000000020010000000000002001000000000000800000000000000000000000000000000800000000000000000000000000000007000000060000000300000000000000A001000000000000A0010000000000007000000000000000040000000000000008000000000000001000000000000000100000000B000000020000000000000010020000000000001002000000000000300000000000000004000000010000000800000000000000180000000000000018000000030000000200000000000000400200000000000040020000000000000C000000000000000000000000000000010000000000000000000000000000002000000001000000300000000000000000000000000000004C02000000000000130000000000000000000000000000000100000000000000010000000000000029000000020000000000000000000000000000000000000060020000000000009000000000000000080000000500000008000000000000001800000000000000310000000300000000000000000000000000000000000000F00200000000000043000000000000000000000000000000010000000000000000000000000000003B00000003000000000000000000000000000000000000003303000000000000AA00000000000000000000000000000001000000000000000000000000000000");
+
+// syscall_reloc_64_32.rs built for sBPF v0: R_BPF_64_RELATIVE into .rodata and R_BPF_64_32 against an external syscall
+pub const SYSCALL_RELOC_64_32: [u8; 1640] = hex!("7F454C46020101000000000000000000030007010100000020010000000000004000000000000000A8030000000000000000000040003800040040000B0009000100000005000000200100000000000020010000000000002001000000000000300000000000000030000000000000000800000000000000010000000400000050010000000000005001000000000000500100000000000004000000000000000400000000000000080000000000000001000000040000000802000000000000080200000000000008020000000000007800000000000000780000000000000008000000000000000200000006000000580100000000000058010000000000005801000000000000B000000000000000B000000000000000080000000000000018010000500100000000000000000000B70200000400000085100000FFFFFFFFB7000000000000009500000000000000666F6F0A000000001E000000000000000400000000000000110000000000000060020000000000001200000000000000200000000000000013000000000000001000000000000000FAFFFF6F000000000100000000000000060000000000000008020000000000000B000000000000001800000000000000050000000000000050020000000000000A00000000000000100000000000000016000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000100000012000100200100000000000030000000000000000C000000100000000000000000000000000000000000000000656E747279706F696E74006C6F67002001000000000000080000000000000038010000000000000A000000020000004C696E6B65723A204C4C442031382E312E37000000000000000000000000000000000000000000000000000000000000010000000400F1FF000000000000000000000000000000003B00000000020300580100000000000000000000000000002C0000001200010020010000000000003000000000000000370000001000000000000000000000000000000000000000002E74657874002E726F64617461002E64796E616D6963002E64796E73796D002E64796E737472002E72656C2E64796E002E636F6D6D656E74002E73796D746162002E7368737472746162002E737472746162000073797363616C6C5F72656C6F635F36345F33322E353933623135303732623065646230642D6367752E3000656E747279706F696E74006C6F67005F44594E414D4943000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000010000000600000000000000200100000000000020010000000000003000000000000000000000000000000008000000000000000000000000000000070000000100000012000000000000005001000000000000500100000000000004000000000000000000000000000000010000000000000004000000000000000F00000006000000030000000000000058010000000000005801000000000000B000000000000000050000000000000008000000000000001000000000000000180000000B00000002000000000000000802000000000000080200000000000048000000000000000500000001000000080000000000000018000000000000002000000003000000020000000000000050020000000000005002000000000000100000000000000000000000000000000100000000000000000000000000000028000000090000000200000000000000600200000000000060020000000000002000000000000000040000000000000008000000000000001000000000000000310000000100000030000000000000000000000000000000800200000000000013000000000000000000000000000000010000000000000001000000000000003A0000000200000000000000000000000000000000000000980200000000000078000000000000000A0000000300000008000000000000001800000000000000420000000300000000000000000000000000000000000000100300000000000054000000000000000000000000000000010000000000000000000000000000004C000000030000000000000000000000000000000000000064030000000000004400000000000000000000000000000001000000000000000000000000000000");
+
+// reloc_64_64.rs built for sBPF v0: R_BPF_64_64 against the entrypoint symbol
+pub const RELOC_64_64: [u8; 1448] = hex!("7F454C4602010100000000000000000003000701010000002001000000000000400000000000000028030000000000000000000040003800030040000A00080001000000050000002001000000000000200100000000000020010000000000001800000000000000180000000000000008000000000000000100000004000000D801000000000000D801000000000000D8010000000000005000000000000000500000000000000008000000000000000200000006000000380100000000000038010000000000003801000000000000A000000000000000A000000000000000080000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001800000000000000000000000000000095000000000000001E0000000000000004000000000000001100000000000000180200000000000012000000000000001000000000000000130000000000000010000000000000000600000000000000D8010000000000000B000000000000001800000000000000050000000000000008020000000000000A000000000000000C00000000000000160000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000120001002001000000000000180000000000000000656E747279706F696E740000000000200100000000000001000000010000004C696E6B65723A204C4C442031382E312E37000000000000000000000000000000000000000000000000000000000000010000000400F1FF000000000000000000000000000000002F0000000002020038010000000000000000000000000000240000001200010020010000000000001800000000000000002E74657874002E64796E616D6963002E64796E73796D002E64796E737472002E72656C2E64796E002E636F6D6D656E74002E73796D746162002E7368737472746162002E737472746162000072656C6F635F36345F36342E363530303763636134326238666262302D6367752E3000656E747279706F696E74005F44594E414D4943000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000100000006000000000000002001000000000000200100000000000018000000000000000000000000000000080000000000000000000000000000000700000006000000030000000000000038010000000000003801000000000000A000000000000000040000000000000008000000000000001000000000000000100000000B0000000200000000000000D801000000000000D801000000000000300000000000000004000000010000000800000000000000180000000000000018000000030000000200000000000000080200000000000008020000000000000C000000000000000000000000000000010000000000000000000000000000002000000009000000020000000000000018020000000000001802000000000000100000000000000003000000000000000800000000000000100000000000000029000000010000003000000000000000000000000000000028020000000000001300000000000000000000000000000001000000000000000100000000000000320000000200000000000000000000000000000000000000400200000000000060000000000000000900000003000000080000000000000018000000000000003A0000000300000000000000000000000000000000000000A0020000000000004C00000000000000000000000000000001000000000000000000000000000000440000000300000000000000000000000000000000000000EC020000000000003800000000000000000000000000000001000000000000000000000000000000");
+
+// bss_section.rs built for sBPF v0: a writable SHT_NOBITS .bss section
+pub const BSS_SECTION: [u8; 1624] = hex!("7F454C4602010100000000000000000003000701010000002001000000000000400000000000000098030000000000000000000040003800030040000B000900010000000500000020010000000000002001000000000000200100000000000030000000000000003000000000000000080000000000000001000000060000000002000000000000000200000000000000020000000000005000000000000000580000000000000008000000000000000200000006000000500100000000000050010000000000005001000000000000B000000000000000B0000000000000000800000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000018010000500200000000000000000000B70200002A0000007B21000000000000B70000000000000095000000000000001E000000000000000400000000000000110000000000000040020000000000001200000000000000100000000000000013000000000000001000000000000000FAFFFF6F000000000100000000000000060000000000000000020000000000000B000000000000001800000000000000050000000000000030020000000000000A000000000000000C00000000000000160000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000120001002001000000000000300000000000000000656E747279706F696E740000000000200100000000000008000000000000004C696E6B65723A204C4C442031382E312E37000000000000000000000000000000000000000000000000000000000000010000000400F1FF000000000000000000000000000000002400000001000600500200000000000008000000000000005800000000020200500100000000000000000000000000004D0000001200010020010000000000003000000000000000002E74657874002E64796E616D6963002E64796E73796D002E64796E737472002E72656C2E64796E002E627373002E636F6D6D656E74002E73796D746162002E7368737472746162002E73747274616200006273735F73656374696F6E2E663239353663623563383263313136392D6367752E30005F5A4E31316273735F73656374696F6E3356414C313768336330323162626632613461623135364500656E747279706F696E74005F44594E414D49430000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000100000006000000000000002001000000000000200100000000000030000000000000000000000000000000080000000000000000000000000000000700000006000000030000000000000050010000000000005001000000000000B000000000000000040000000000000008000000000000001000000000000000100000000B000000020000000000000000020000000000000002000000000000300000000000000004000000010000000800000000000000180000000000000018000000030000000200000000000000300200000000000030020000000000000C0000000000000000000000000000000100000000000000000000000000000020000000090000000200000000000000400200000000000040020000000000001000000000000000030000000000000008000000000000001000000000000000290000000800000003000000000000005002000000000000500200000000000008000000000000000000000000000000080000000000000000000000000000002E000000010000003000000000000000000000000000000050020000000000001300000000000000000000000000000001000000000000000100000000000000370000000200000000000000000000000000000000000000680200000000000078000000000000000A00000004000000080000000000000018000000000000003F0000000300000000000000000000000000000000000000E002000000000000510000000000000000000000000000000100000000000000000000000000000049000000030000000000000000000000000000000000000031030000000000006100000000000000000000000000000001000000000000000000000000000000");
+
+// data_section.rs built for sBPF v0: a writable .data section
+pub const DATA_SECTION: [u8; 1632] = hex!("7F454C46020101000000000000000000030007010100000020010000000000004000000000000000A0030000000000000000000040003800030040000B000900010000000500000020010000000000002001000000000000200100000000000030000000000000003000000000000000080000000000000001000000060000000002000000000000000200000000000000020000000000005800000000000000580000000000000008000000000000000200000006000000500100000000000050010000000000005001000000000000B000000000000000B0000000000000000800000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000018010000500200000000000000000000B7020000000000007B21000000000000B70000000000000095000000000000001E000000000000000400000000000000110000000000000040020000000000001200000000000000100000000000000013000000000000001000000000000000FAFFFF6F000000000100000000000000060000000000000000020000000000000B000000000000001800000000000000050000000000000030020000000000000A000000000000000C00000000000000160000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000120001002001000000000000300000000000000000656E747279706F696E740000000000200100000000000008000000000000002A000000000000004C696E6B65723A204C4C442031382E312E37000000000000000000000000000000000000000000000000000000000000010000000400F1FF000000000000000000000000000000002500000001000600500200000000000008000000000000005A00000000020200500100000000000000000000000000004F0000001200010020010000000000003000000000000000002E74657874002E64796E616D6963002E64796E73796D002E64796E737472002E72656C2E64796E002E64617461002E636F6D6D656E74002E73796D746162002E7368737472746162002E7374727461620000646174615F73656374696F6E2E393662303138363562363633376561302D6367752E30005F5A4E3132646174615F73656374696F6E3356414C313768343161366535343664613865313531344500656E747279706F696E74005F44594E414D49430000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000100000006000000000000002001000000000000200100000000000030000000000000000000000000000000080000000000000000000000000000000700000006000000030000000000000050010000000000005001000000000000B000000000000000040000000000000008000000000000001000000000000000100000000B000000020000000000000000020000000000000002000000000000300000000000000004000000010000000800000000000000180000000000000018000000030000000200000000000000300200000000000030020000000000000C0000000000000000000000000000000100000000000000000000000000000020000000090000000200000000000000400200000000000040020000000000001000000000000000030000000000000008000000000000001000000000000000290000000100000003000000000000005002000000000000500200000000000008000000000000000000000000000000080000000000000000000000000000002F000000010000003000000000000000000000000000000058020000000000001300000000000000000000000000000001000000000000000100000000000000380000000200000000000000000000000000000000000000700200000000000078000000000000000A0000000400000008000000000000001800000000000000400000000300000000000000000000000000000000000000E80200000000000052000000000000000000000000000000010000000000000000000000000000004A00000003000000000000000000000000000000000000003A030000000000006300000000000000000000000000000001000000000000000000000000000000");
+
+// strict_header.rs built for sBPF v3: hor64, moved memory instructions and return
+pub const STRICT_HEADER: [u8; 1856] = hex!("7F454C46020101000000000000000000030007010100000020000000000000004000000000000000C0040000000000000300000040003800060040000A00080001000000010000009001000000000000000000000000000000000000000000003000000000000000300000000000000008000000000000000100000004000000C0010000000000000000000001000000000000000100000010000000000000001000000000000000080000000000000051E5746406000000D001000000000000000000000200000000000000020000000000000000000000001000000000000001000000000000000100000006000000D001000000000000000000000300000000000000030000000000000000000000001000000000000008000000000000000000000000000000D00100000000000000000000FFFFFFFF00000000FFFFFFFFA800000000000000A80000000000000008000000000000000000000000000000DD020000000000000000000000000000000000000000000000000000000000000D010000FFFFFFFF0100000000000000B401000000000000F7010000010000009C100000000000009D0000000000000085100000FBFFFFFF9D000000000000002A0000000000000000000000000000000000000000000000000000000000000000000000000000000100000002000100000000000000000020000000000000002C00000012000100200000000000000010000000000000003700000010000300000000000200000000000000000000004400000010000300001000000200000000000000000000004F00000010000400000000000300000000000000000000005B0000001000040000100000030000000000000000000000005F5A4E31337374726963745F68656164657233666F6F313768383561653639313266633038326338394500656E747279706F696E74005F737461636B5F7374617274005F737461636B5F656E64005F686561705F7374617274005F686561705F656E6400007374726963745F6865616465722E353538336166393161666362333464372D6367752E30005F5A4E31337374726963745F68656164657233666F6F3137683835616536393132666330383263383945005F5A4E31337374726963745F6865616465723556414C5F42313768393234346436396637383266643632664500656E747279706F696E74005F737461636B5F7374617274005F737461636B5F656E64005F686561705F7374617274005F686561705F656E640000000000000000000000000000000000000000000000000000000000010000000400F1FF000000000000000000000000000000002600000002000100000000000000000020000000000000005100000001000200000000000100000008000000000000007E0000001200010020000000000000001000000000000000890000001000030000000000020000000000000000000000960000001000030000100000020000000000000000000000A10000001000040000000000030000000000000000000000AD0000001000040000100000030000000000000000000000002E74657874002E726F64617461002E6273732E737461636B002E6273732E68656170002E64796E73796D002E737472746162002E73796D746162002E7368737472746162002E64796E7374720000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000010000000600000000000000000000000000000090010000000000003000000000000000000000000000000008000000000000000000000000000000070000000100000002000000000000000000000001000000C00100000000000010000000000000000000000000000000080000000000000000000000000000000F0000000800000002000000000000000000000002000000D00100000000000000100000000000000000000000000000010000000000000000000000000000001A0000000800000002000000000000000000000003000000D0010000000000000010000000000000000000000000000001000000000000000000000000000000240000000B000000020000000000000000000000FFFFFFFFD001000000000000A8000000000000000900000001000000080000000000000018000000000000002C0000000300000000000000000000000000000000000000DD02000000000000B7000000000000000000000000000000010000000000000000000000000000003400000002000000000000000000000000000000000000009803000000000000D8000000000000000600000004000000080000000000000018000000000000003C000000030000000000000000000000000000000000000070040000000000004E0000000000000000000000000000000100000000000000000000000000000046000000030000000200000000000000A8000000FFFFFFFF78020000000000006500000000000000000000000000000001000000000000000000000000000000");
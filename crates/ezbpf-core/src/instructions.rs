@@ -1,8 +1,15 @@
-use std::io::Cursor;
+use std::{io::Cursor, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{cursor::ELFCursor, errors::EZBpfError, opcodes::OpCode};
+use crate::{
+    cursor::ELFCursor,
+    errors::EZBpfError,
+    murmur3::hash_symbol_name,
+    opcodes::{OpCode, Operands},
+    sbpf_version::SbpfVersion,
+    syscalls::syscall_name,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Ix {
@@ -29,196 +36,746 @@ impl Ix {
         format!("[r{}{}]", self.src, self.off_str())
     }
 
+    /// The slot a jump at `pc` lands on, if this is a jump.
+    pub fn jump_target(&self, pc: usize) -> Option<usize> {
+        if !self.op.is_jump() {
+            return None;
+        }
+        (pc as i64)
+            .checked_add(1 + self.off as i64)
+            .and_then(|target| usize::try_from(target).ok())
+    }
+
+    // lddw is the only instruction that occupies two 8 byte slots
+    pub fn size(&self) -> usize {
+        match self.op {
+            OpCode::Lddw => 16,
+            _ => 8,
+        }
+    }
+
     pub fn op_imm_bits(&self) -> Result<String, EZBpfError> {
         Ok(match self.imm {
             16 => format!("{}16", self.op),
             32 => format!("{}32", self.op),
             64 => format!("{}64", self.op),
-            _ => return Err(EZBpfError::InvalidImmediate),
+            imm => {
+                return Err(EZBpfError::InvalidImmediate {
+                    offset: None,
+                    value: imm,
+                })
+            }
         })
     }
 }
 
 impl Ix {
-    pub fn from_bytes(b: &[u8]) -> Result<Self, EZBpfError> {
+    pub fn from_bytes(b: &[u8], version: SbpfVersion) -> Result<Self, EZBpfError> {
         let mut c = Cursor::new(b);
-        c.read_ix()
+        c.read_ix(version)
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut b = vec![self.op.clone() as u8, self.src << 4 | self.dst];
+    pub fn to_bytes(&self, version: SbpfVersion) -> Result<Vec<u8>, EZBpfError> {
+        let op = self.op.to_byte(version).ok_or(EZBpfError::UnsupportedOpcode {
+            offset: None,
+            op: self.op.clone(),
+            version,
+        })?;
+        let mut b = vec![op, self.src << 4 | self.dst];
         b.extend_from_slice(&self.off.to_le_bytes());
         b.extend_from_slice(&self.imm.to_le_bytes()[..4]);
         if self.op == OpCode::Lddw {
             b.extend_from_slice(&[0; 4]);
             b.extend_from_slice(&self.imm.to_le_bytes()[4..]);
         }
-        b
-    }
-
-    pub fn to_asm(&self) -> Result<String, EZBpfError> {
-        Ok(match self.op {
-            // lddw - (load double word) takes up two instructions. The 64 bit value
-            // is made up of two halves with the upper half being the immediate
-            // of the lddw value and the lower half being the immediate of the
-            // following instruction
-            OpCode::Lddw => format!("{} r{}, {}", self.op, self.dst, self.imm),
-            // ldx - (load x) store a 8/16/32/64 bit (byte/half/word/double word)
-            // value in a register
-            OpCode::Ldxb |
-            OpCode::Ldxh |
-            OpCode::Ldxw |
-            OpCode::Ldxdw => format!("{} r{}, {}", self.op, self.dst, self.src_off()),
-            // stb - these instructions are deprecated
-            OpCode::Stb |
-            OpCode::Sth |
-            OpCode::Stw |
-            OpCode::Stdw => format!("{} {}, {}", self.op, self.dst_off(), self.imm),
-            // stx - store a 8/16/32/64 bit value from a source register into the offset
-            // of the destination register
-            OpCode::Stxb |
-            OpCode::Stxh |
-            OpCode::Stxw |
-            OpCode::Stxdw => format!("{} {}, r{}", self.op, self.dst_off(), self.src),
-            // Math
-            OpCode::Neg32 | // Deprecated in SBFv2
-            OpCode::Neg64 => format!("{} r{}", self.op, self.dst),
-            // LE and BE OpCodes act a little differently to others. In assembly form, they are
-            // notated as be16, be32 and b64. In byte form, the bit length of the operation is 
-            // determined by the immedate value of its parent instruction, 0x10, 0x20 and 0x40
-            // accordingly (the hex of 16/32/64)
-            OpCode::Le |
-            OpCode::Be => format!("{}{}", self.op_imm_bits()?, self.dst), // Docs for this seem wrong //DC01000010000000 DC01000020000000 DC01000040000000
-            // Immedate
-            OpCode::Add32Imm |
-            OpCode::Sub32Imm |
-            OpCode::Mul32Imm |
-            OpCode::Div32Imm |
-            OpCode::Or32Imm |
-            OpCode::And32Imm |
-            OpCode::Lsh32Imm |
-            OpCode::Rsh32Imm |
-            OpCode::Mod32Imm |
-            OpCode::Xor32Imm |
-            OpCode::Arsh32Imm |
-            OpCode::Mov32Imm |
-            OpCode::Lmul32Imm |
-            OpCode::Udiv32Imm |
-            OpCode::Urem32Imm |
-            OpCode::Sdiv32Imm |
-            OpCode::Srem32Imm |
-            OpCode::Add64Imm |
-            OpCode::Sub64Imm |
-            OpCode::Mul64Imm |
-            OpCode::Div64Imm |
-            OpCode::Or64Imm |
-            OpCode::And64Imm |
-            OpCode::Lsh64Imm |
-            OpCode::Rsh64Imm |
-            OpCode::Mod64Imm |
-            OpCode::Xor64Imm |
-            OpCode::Mov64Imm |
-            OpCode::Arsh64Imm |
-            OpCode::Hor64Imm |
-            OpCode::Lmul64Imm |
-            OpCode::Uhmul64Imm |
-            OpCode::Udiv64Imm |
-            OpCode::Urem64Imm |
-            OpCode::Shmul64Imm |
-            OpCode::Sdiv64Imm |
-            OpCode::Srem64Imm => format!("{} r{}, {}", self.op, self.dst, self.imm),
-            // Register
-            OpCode::Add32Reg |
-            OpCode::Sub32Reg |
-            OpCode::Mul32Reg |
-            OpCode::Div32Reg |
-            OpCode::Or32Reg |
-            OpCode::And32Reg |
-            OpCode::Lsh32Reg |
-            OpCode::Rsh32Reg |
-            OpCode::Mod32Reg |
-            OpCode::Xor32Reg |
-            OpCode::Mov32Reg |
-            OpCode::Arsh32Reg |
-            OpCode::Lmul32Reg |
-            OpCode::Udiv32Reg |
-            OpCode::Urem32Reg |
-            OpCode::Sdiv32Reg |
-            OpCode::Srem32Reg |
-            OpCode::Add64Reg |
-            OpCode::Sub64Reg |
-            OpCode::Mul64Reg |
-            OpCode::Div64Reg |
-            OpCode::Or64Reg |
-            OpCode::And64Reg |
-            OpCode::Lsh64Reg |
-            OpCode::Rsh64Reg |
-            OpCode::Mod64Reg |
-            OpCode::Xor64Reg |
-            OpCode::Mov64Reg |
-            OpCode::Arsh64Reg |
-            OpCode::Lmul64Reg |
-            OpCode::Uhmul64Reg |
-            OpCode::Udiv64Reg |
-            OpCode::Urem64Reg |
-            OpCode::Shmul64Reg |
-            OpCode::Sdiv64Reg |
-            OpCode::Srem64Reg => format!("{} r{}, r{}", self.op, self.dst, self.src),
-
-            // Jumps
-            OpCode::Ja => format!("{} {}", self.op, self.off_str()),
-
-            // Immediates
-            OpCode::JgeImm |
-            OpCode::JltImm |
-            OpCode::JleImm |
-            OpCode::JsetImm |
-            OpCode::JneImm |
-            OpCode::JsgtImm |
-            OpCode::JsgeImm |
-            OpCode::JsltImm |
-            OpCode::JsleImm => format!("{} r{}, {}, {}", self.op, self.dst, self.imm, self.off_str()),
-            // Registers
-            OpCode::JeqImm |
-            OpCode::JgtImm |
-            OpCode::JeqReg |
-            OpCode::JgtReg |
-            OpCode::JgeReg |
-            OpCode::JltReg |
-            OpCode::JleReg |
-            OpCode::JsetReg |
-            OpCode::JneReg |
-            OpCode::JsgtReg |
-            OpCode::JsgeReg |
-            OpCode::JsltReg |
-            OpCode::JsleReg => format!("{} r{}, r{}, {}", self.op, self.dst, self.src, self.off_str()),
-
-
-            // Calls
-            OpCode::Call => format!("call {}", self.imm),
-            OpCode::Callx => format!("call r{}", self.src),
-            OpCode::Exit => format!("{}", self.op),
+        Ok(b)
+    }
+
+    pub fn to_asm(&self, version: SbpfVersion) -> Result<String, EZBpfError> {
+        let op = &self.op;
+        Ok(match op.operands() {
+            Operands::None => op.to_string(),
+            Operands::Dst => format!("{} r{}", op, self.dst),
+            Operands::DstImm => format!("{} r{}, {}", op, self.dst, self.imm),
+            Operands::DstSrc => format!("{} r{}, r{}", op, self.dst, self.src),
+            Operands::Load => format!("{} r{}, {}", op, self.dst, self.src_off()),
+            Operands::StoreImm => format!("{} {}, {}", op, self.dst_off(), self.imm),
+            Operands::StoreReg => format!("{} {}, r{}", op, self.dst_off(), self.src),
+            // Written be16, be32 and be64, with the width held in the immediate
+            Operands::Endian => format!("{} r{}", self.op_imm_bits()?, self.dst),
+            Operands::Jump | Operands::JumpImm | Operands::JumpReg => self.jump_asm(&self.off_str()),
+            // Until static syscalls, syscalls are called by the hash of their
+            // name
+            Operands::Call => match syscall_name(self.imm as u32) {
+                Some(name) if *op == OpCode::Syscall || !version.static_syscalls() => {
+                    format!("{} {}", op, name)
+                }
+                _ => format!("{} {}", op, self.imm),
+            },
+            // Before SBPFv2 the target register of callx is held in the immediate
+            Operands::CallReg if version.callx_uses_src_reg() => format!("{} r{}", op, self.src),
+            Operands::CallReg => format!("{} r{}", op, self.imm),
         })
     }
+
+    /// Like `to_asm`, but with the target of a jump given as `label` rather
+    /// than as an offset.
+    pub fn to_asm_with_label(&self, version: SbpfVersion, label: &str) -> Result<String, EZBpfError> {
+        if self.op.is_jump() {
+            Ok(self.jump_asm(label))
+        } else {
+            self.to_asm(version)
+        }
+    }
+
+    fn jump_asm(&self, target: &str) -> String {
+        match self.op.operands() {
+            Operands::JumpImm => format!("{} r{}, {}, {}", self.op, self.dst, self.imm, target),
+            Operands::JumpReg => format!("{} r{}, r{}, {}", self.op, self.dst, self.src, target),
+            _ => format!("{} {}", self.op, target),
+        }
+    }
+
+    /// Parses one instruction as written by `to_asm`. Also accepted are hex
+    /// immediates and offsets, jump offsets without a sign, spaces inside
+    /// memory operands, w registers for the 32 bit instructions and trailing
+    /// `//` or `;` comments, as well as llvm's syntax for the same
+    /// instructions, such as `r1 += 5`, `if r1 == 0 goto +3` or
+    /// `*(u64 *)(r10 - 8) = r1`. Until static syscalls, syscalls may be called
+    /// by any name, which is hashed. Error offsets are relative to the start
+    /// of `s`.
+    pub fn parse_asm(s: &str, version: SbpfVersion) -> Result<Self, EZBpfError> {
+        let invalid = |value: &str| EZBpfError::InvalidAsm {
+            offset: Some(value.as_ptr() as u64 - s.as_ptr() as u64),
+            value: value.to_string(),
+        };
+        let text = s.split("//").next().unwrap_or_default();
+        let text = text.split(';').next().unwrap_or_default().trim();
+        if text.is_empty() {
+            return Err(invalid(s.trim()));
+        }
+        if text.contains('=') || text.starts_with("if ") || text.starts_with("goto ") {
+            let rewritten = from_llvm(text).ok_or_else(|| invalid(text))?;
+            return Self::parse_asm(&rewritten, version).map_err(|_| invalid(text));
+        }
+        let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let operands = match rest.trim() {
+            "" => vec![],
+            rest => rest
+                .split(',')
+                .map(|o| Operand::parse(o.trim()).ok_or_else(|| invalid(o.trim())))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        // be16, be32 and be64 carry their width in the mnemonic
+        let (name, bits) = match mnemonic.split_at_checked(2) {
+            Some((name @ ("le" | "be"), bits @ ("16" | "32" | "64"))) => (name, bits.parse().ok()),
+            _ => (mnemonic, None),
+        };
+        OpCode::all(version)
+            .filter(|op| Into::<&str>::into(op.clone()) == name)
+            .find_map(|op| Self::from_operands(op, &operands, bits, version))
+            .ok_or_else(|| invalid(text))
+    }
+
+    // Builds an instruction if the operands have the shape `op` is written with
+    fn from_operands(
+        op: OpCode,
+        operands: &[Operand],
+        bits: Option<i64>,
+        version: SbpfVersion,
+    ) -> Option<Self> {
+        let mut ix = Ix {
+            op,
+            dst: 0,
+            src: 0,
+            off: 0,
+            imm: 0,
+        };
+        // Other than lddw, immediates are 32 bits, written signed or not
+        let imm32 = |imm: i64| match i32::try_from(imm) {
+            Ok(imm) => Some(imm as i64),
+            Err(_) => u32::try_from(imm).ok().map(|imm| imm as i32 as i64),
+        };
+        let off16 = |off: i64| i16::try_from(off).ok();
+        use Operand::{Imm, Mem, Name, Reg};
+        match (ix.op.operands(), operands) {
+            (Operands::None, []) => {}
+            (Operands::Dst, [Reg(dst)]) => ix.dst = *dst,
+            (Operands::DstImm, [Reg(dst), Imm(imm)]) => {
+                ix.dst = *dst;
+                ix.imm = match ix.op {
+                    OpCode::Lddw => *imm,
+                    _ => imm32(*imm)?,
+                };
+            }
+            (Operands::DstSrc, [Reg(dst), Reg(src)]) => (ix.dst, ix.src) = (*dst, *src),
+            (Operands::Load, [Reg(dst), Mem(src, off)]) => {
+                (ix.dst, ix.src, ix.off) = (*dst, *src, off16(*off)?)
+            }
+            (Operands::StoreImm, [Mem(dst, off), Imm(imm)]) => {
+                (ix.dst, ix.off, ix.imm) = (*dst, off16(*off)?, imm32(*imm)?)
+            }
+            (Operands::StoreReg, [Mem(dst, off), Reg(src)]) => {
+                (ix.dst, ix.src, ix.off) = (*dst, *src, off16(*off)?)
+            }
+            (Operands::Endian, [Reg(dst)]) => (ix.dst, ix.imm) = (*dst, bits?),
+            (Operands::Jump, [Imm(off)]) => ix.off = off16(*off)?,
+            (Operands::JumpImm, [Reg(dst), Imm(imm), Imm(off)]) => {
+                (ix.dst, ix.imm, ix.off) = (*dst, imm32(*imm)?, off16(*off)?)
+            }
+            (Operands::JumpReg, [Reg(dst), Reg(src), Imm(off)]) => {
+                (ix.dst, ix.src, ix.off) = (*dst, *src, off16(*off)?)
+            }
+            (Operands::Call, [Imm(imm)]) => ix.imm = imm32(*imm)?,
+            // With static syscalls, call is only ever relative
+            (Operands::Call, [Name(_)]) if ix.op == OpCode::Call && version.static_syscalls() => {
+                return None
+            }
+            (Operands::Call, [Name(name)]) => {
+                ix.imm = hash_symbol_name(name.as_bytes()) as i32 as i64
+            }
+            // Before SBPFv2 the target register of callx is held in the immediate
+            (Operands::CallReg, [Reg(reg)]) if version.callx_uses_src_reg() => ix.src = *reg,
+            (Operands::CallReg, [Reg(reg)]) => ix.imm = *reg as i64,
+            _ => return None,
+        }
+        Some(ix)
+    }
+}
+
+/// Parses with the instruction set of SBPFv0. Use `Ix::parse_asm` for other
+/// versions.
+impl FromStr for Ix {
+    type Err = EZBpfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_asm(s, SbpfVersion::V0)
+    }
+}
+
+// One comma separated operand of an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    Reg(u8),
+    Mem(u8, i64),
+    Imm(i64),
+    Name(String),
+}
+
+impl Operand {
+    fn parse(s: &str) -> Option<Self> {
+        if let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            let inner = inner.trim();
+            let split = inner.find(['+', '-']).unwrap_or(inner.len());
+            let (reg, off) = inner.split_at(split);
+            let off = match off.trim() {
+                "" => 0,
+                off => parse_int(&off.replace(' ', ""))?,
+            };
+            return Some(Self::Mem(parse_reg(reg.trim())?, off));
+        }
+        if let Some(reg) = parse_reg(s) {
+            return Some(Self::Reg(reg));
+        }
+        if let Some(imm) = parse_int(s) {
+            return Some(Self::Imm(imm));
+        }
+        is_name(s).then(|| Self::Name(s.to_string()))
+    }
+}
+
+// Rewrites an instruction in llvm's C-like syntax in the syntax of to_asm.
+// Whether an alu instruction is 32 or 64
+// bits is told by its destination being a w or r register.
+fn from_llvm(text: &str) -> Option<String> {
+    if let Some(target) = text.strip_prefix("goto ") {
+        return Some(format!("ja {}", target.trim()));
+    }
+    if let Some(condition) = text.strip_prefix("if ") {
+        let (condition, target) = condition.split_once(" goto ")?;
+        let parts: Vec<&str> = condition.split_whitespace().collect();
+        let [a, op, b] = parts[..] else {
+            return None;
+        };
+        let name = match op {
+            "==" => "jeq",
+            "!=" => "jne",
+            ">" => "jgt",
+            ">=" => "jge",
+            "<" => "jlt",
+            "<=" => "jle",
+            "&" => "jset",
+            "s>" => "jsgt",
+            "s>=" => "jsge",
+            "s<" => "jslt",
+            "s<=" => "jsle",
+            _ => return None,
+        };
+        return Some(format!("{} {}, {}, {}", name, a, b, target.trim()));
+    }
+    let (lhs, rhs) = text.split_once('=')?;
+    let (lhs, rhs) = (lhs.trim(), rhs.trim());
+    if let Some((size, dst)) = llvm_memory(lhs) {
+        let name = match parse_reg(rhs) {
+            Some(_) => "stx",
+            None => "st",
+        };
+        return Some(format!("{}{} {}, {}", name, size, dst, rhs));
+    }
+    // The destination, followed by the operator of a compound assignment
+    let split = lhs.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(lhs.len());
+    let (dst, op) = (&lhs[..split], lhs[split..].trim());
+    let bits = if dst.starts_with('w') { 32 } else { 64 };
+    let name = match op {
+        "" => {
+            if let Some((size, src)) = llvm_memory(rhs) {
+                return Some(format!("ldx{} {}, {}", size, dst, src));
+            }
+            if let Some(imm) = rhs.strip_suffix(" ll") {
+                return Some(format!("lddw {}, {}", dst, imm.trim()));
+            }
+            if rhs.strip_prefix('-').is_some_and(|src| src.trim() == dst) {
+                return Some(format!("neg{} {}", bits, dst));
+            }
+            // be16 r1 and the like are written the same way on both sides
+            if let Some((endian, src)) = rhs.split_once(' ') {
+                return (src.trim() == dst).then(|| format!("{} {}", endian, dst));
+            }
+            "mov"
+        }
+        "+" => "add",
+        "-" => "sub",
+        "*" => "mul",
+        "/" => "div",
+        "|" => "or",
+        "&" => "and",
+        "<<" => "lsh",
+        ">>" => "rsh",
+        "s>>" => "arsh",
+        "%" => "mod",
+        "^" => "xor",
+        _ => return None,
+    };
+    Some(format!("{}{} {}, {}", name, bits, dst, rhs))
+}
+
+// An llvm memory operand such as `*(u64 *)(r10 - 8)`, as the size suffix of the
+// instruction and the operand in the syntax of to_asm
+fn llvm_memory(s: &str) -> Option<(&'static str, String)> {
+    let (ty, rest) = s.strip_prefix("*(")?.split_once("*)")?;
+    let size = match ty.trim() {
+        "u8" => "b",
+        "u16" => "h",
+        "u32" => "w",
+        "u64" => "dw",
+        _ => return None,
+    };
+    let inner = rest.trim().strip_prefix('(')?.strip_suffix(')')?;
+    Some((size, format!("[{}]", inner)))
+}
+
+fn parse_reg(s: &str) -> Option<u8> {
+    let n = s.strip_prefix('r').or_else(|| s.strip_prefix('w'))?;
+    let reg = n.parse::<u8>().ok().filter(|reg| *reg <= 10)?;
+    // Leading zeros and signs aren't register numbers
+    (n == reg.to_string()).then_some(reg)
+}
+
+/// Whether `s` can name a label or syscall: letters, digits, `_` and `.`, not
+/// starting with a digit.
+pub fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Decimal or 0x prefixed hex, with an optional sign. Hex is taken as the bits
+/// of the value, so that 0xffffffffffffffff is -1.
+pub fn parse_int(s: &str) -> Option<i64> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()? as i64,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => {
+            match digits.parse::<u64>().ok()? {
+                v if negative && v == 1 << 63 => return Some(i64::MIN),
+                v => i64::try_from(v).ok()?,
+            }
+        }
+        None => return None,
+    };
+    if negative {
+        value.checked_neg()
+    } else {
+        Some(value)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use hex_literal::hex;
 
-    use crate::instructions::Ix;
+    use std::collections::HashSet;
+
+    use proptest::{prelude::*, sample::Index};
+
+    use crate::{
+        errors::EZBpfError,
+        instructions::Ix,
+        opcodes::{OpCode, Operands},
+        sbpf_version::SbpfVersion,
+    };
 
     #[test]
     fn serialize_e2e() {
         let b = hex!("9700000000000000");
-        let i = Ix::from_bytes(&b).unwrap();
-        assert_eq!(i.to_bytes(), &b);
+        let i = Ix::from_bytes(&b, SbpfVersion::V0).unwrap();
+        assert_eq!(i.to_bytes(SbpfVersion::V0).unwrap(), &b);
     }
 
     #[test]
     fn serialize_e2e_lddw() {
         let b = hex!("18010000000000000000000000000000");
-        let i = Ix::from_bytes(&b).unwrap();
-        assert_eq!(i.to_bytes(), &b);
+        let i = Ix::from_bytes(&b, SbpfVersion::V0).unwrap();
+        assert_eq!(i.to_bytes(SbpfVersion::V0).unwrap(), &b);
+    }
+
+    #[test]
+    fn versioned_opcodes() {
+        // 0x2c is mul32 before SBPFv2 and ldxb after
+        let b = hex!("2c21040000000000");
+        let v0 = Ix::from_bytes(&b, SbpfVersion::V0).unwrap();
+        assert_eq!(v0.op, OpCode::Mul32Reg);
+        assert_eq!(v0.to_asm(SbpfVersion::V0).unwrap(), "mul32 r1, r2");
+        let v2 = Ix::from_bytes(&b, SbpfVersion::V2).unwrap();
+        assert_eq!(v2.op, OpCode::Ldxb);
+        assert_eq!(v2.to_asm(SbpfVersion::V2).unwrap(), "ldxb r1, [r2+4]");
+        assert_eq!(v2.to_bytes(SbpfVersion::V2).unwrap(), &b);
+        assert_eq!(v2.to_bytes(SbpfVersion::V0).unwrap(), hex!("7121040000000000"));
+
+        // lddw, le and neg are gone in SBPFv2, while PQR and hor64 are new
+        assert!(Ix::from_bytes(&hex!("18010000000000000000000000000000"), SbpfVersion::V0).is_ok());
+        assert!(Ix::from_bytes(&hex!("d401000010000000"), SbpfVersion::V0).is_ok());
+        assert!(Ix::from_bytes(&hex!("1801000000000000"), SbpfVersion::V2).is_err());
+        assert!(Ix::from_bytes(&hex!("d401000010000000"), SbpfVersion::V2).is_err());
+        assert!(Ix::from_bytes(&hex!("f701000001000000"), SbpfVersion::V0).is_err());
+        assert!(Ix::from_bytes(&hex!("3601000003000000"), SbpfVersion::V0).is_err());
+        let hor64 = Ix::from_bytes(&hex!("f701000001000000"), SbpfVersion::V2).unwrap();
+        assert_eq!(hor64.to_asm(SbpfVersion::V2).unwrap(), "hor64 r1, 1");
+
+        // and can't be encoded in the versions they aren't in
+        let lddw = Ix::from_bytes(&hex!("18010000000000000000000000000000"), SbpfVersion::V0);
+        let e = lddw.unwrap().to_bytes(SbpfVersion::V2).unwrap_err();
+        assert_eq!(e.to_string(), "lddw is not supported in SBPF v2");
+        assert!(hor64.to_bytes(SbpfVersion::V0).is_err());
+        // exit and syscall share a byte, which is syscall from SBPFv3 on
+        let exit = Ix::from_bytes(&hex!("9500000000000000"), SbpfVersion::V0).unwrap();
+        assert!(exit.to_bytes(SbpfVersion::V3).is_err());
+        for version in [SbpfVersion::V0, SbpfVersion::V2, SbpfVersion::V3] {
+            for op in OpCode::all(version) {
+                let b = op.to_byte(version).unwrap();
+                assert_eq!(OpCode::try_from((b, version)), Ok(op));
+            }
+        }
+    }
+
+    #[test]
+    fn versioned_calls() {
+        let callx = hex!("8d00000002000000");
+        let ix = Ix::from_bytes(&callx, SbpfVersion::V0).unwrap();
+        assert_eq!(ix.to_asm(SbpfVersion::V0).unwrap(), "callx r2");
+        let callx = hex!("8d20000000000000");
+        let ix = Ix::from_bytes(&callx, SbpfVersion::V2).unwrap();
+        assert_eq!(ix.to_asm(SbpfVersion::V2).unwrap(), "callx r2");
+
+        // exit becomes syscall in SBPFv3, with return taking its place
+        let b = hex!("9500000001000000");
+        let ix = Ix::from_bytes(&b, SbpfVersion::V0).unwrap();
+        assert_eq!(ix.op, OpCode::Exit);
+        let ix = Ix::from_bytes(&b, SbpfVersion::V3).unwrap();
+        assert_eq!(ix.to_asm(SbpfVersion::V3).unwrap(), "syscall 1");
+        let ix = Ix::from_bytes(&hex!("9d00000000000000"), SbpfVersion::V3).unwrap();
+        assert_eq!(ix.to_asm(SbpfVersion::V3).unwrap(), "return");
+        assert!(Ix::from_bytes(&hex!("9d00000000000000"), SbpfVersion::V2).is_err());
+    }
+
+    #[test]
+    fn jumps() {
+        let jge = Ix::from_bytes(&hex!("350101000a000000"), SbpfVersion::V0).unwrap();
+        assert_eq!(jge.to_asm(SbpfVersion::V0).unwrap(), "jge r1, 10, +1");
+        assert_eq!(jge.to_asm_with_label(SbpfVersion::V0, "lbb_2").unwrap(), "jge r1, 10, lbb_2");
+        assert_eq!(jge.jump_target(0), Some(2));
+        let jne = Ix::from_bytes(&hex!("5d21fdff00000000"), SbpfVersion::V0).unwrap();
+        assert_eq!(jne.to_asm(SbpfVersion::V0).unwrap(), "jne r1, r2, -3");
+        // At the start of the section, the target would be before it
+        assert_eq!(jne.jump_target(0), None);
+        assert_eq!(jne.jump_target(5), Some(3));
+    }
+
+    #[test]
+    fn syscalls() {
+        let call = Ix::from_bytes(&hex!("85000000111afcb6"), SbpfVersion::V0).unwrap();
+        assert_eq!(call.to_asm(SbpfVersion::V0).unwrap(), "call abort");
+        // Static syscalls have their own opcode, and calls are always relative
+        assert_eq!(call.to_asm(SbpfVersion::V3).unwrap(), format!("call {}", call.imm));
+        let syscall = Ix::from_bytes(&hex!("95000000bd597520"), SbpfVersion::V3).unwrap();
+        assert_eq!(syscall.to_asm(SbpfVersion::V3).unwrap(), "syscall sol_log_");
+    }
+
+    #[test]
+    fn every_opcode() {
+        use SbpfVersion::{V0, V2, V3};
+        let ix = |op, dst, src, off, imm| Ix {
+            op,
+            dst,
+            src,
+            off,
+            imm,
+        };
+        // Each opcode in a version that has it, with the text llvm-objdump
+        // would give
+        let cases = [
+            (V0, ix(OpCode::Lddw, 1, 0, 0, 0x100000002), "lddw r1, 4294967298"),
+            (V0, ix(OpCode::Ldxb, 1, 2, 8, 0), "ldxb r1, [r2+8]"),
+            (V0, ix(OpCode::Ldxh, 1, 2, 8, 0), "ldxh r1, [r2+8]"),
+            (V0, ix(OpCode::Ldxw, 1, 2, 8, 0), "ldxw r1, [r2+8]"),
+            (V0, ix(OpCode::Ldxdw, 1, 2, 8, 0), "ldxdw r1, [r2+8]"),
+            (V0, ix(OpCode::Stb, 1, 0, -8, 3), "stb [r1-8], 3"),
+            (V0, ix(OpCode::Sth, 1, 0, -8, 3), "sth [r1-8], 3"),
+            (V0, ix(OpCode::Stw, 1, 0, -8, 3), "stw [r1-8], 3"),
+            (V0, ix(OpCode::Stdw, 1, 0, -8, 3), "stdw [r1-8], 3"),
+            (V0, ix(OpCode::Stxb, 1, 2, 8, 0), "stxb [r1+8], r2"),
+            (V0, ix(OpCode::Stxh, 1, 2, 8, 0), "stxh [r1+8], r2"),
+            (V0, ix(OpCode::Stxw, 1, 2, 8, 0), "stxw [r1+8], r2"),
+            (V0, ix(OpCode::Stxdw, 1, 2, 8, 0), "stxdw [r1+8], r2"),
+            (V0, ix(OpCode::Add32Imm, 1, 0, 0, -3), "add32 r1, -3"),
+            (V0, ix(OpCode::Add32Reg, 1, 2, 0, 0), "add32 r1, r2"),
+            (V0, ix(OpCode::Sub32Imm, 1, 0, 0, -3), "sub32 r1, -3"),
+            (V0, ix(OpCode::Sub32Reg, 1, 2, 0, 0), "sub32 r1, r2"),
+            (V0, ix(OpCode::Mul32Imm, 1, 0, 0, -3), "mul32 r1, -3"),
+            (V0, ix(OpCode::Mul32Reg, 1, 2, 0, 0), "mul32 r1, r2"),
+            (V0, ix(OpCode::Div32Imm, 1, 0, 0, -3), "div32 r1, -3"),
+            (V0, ix(OpCode::Div32Reg, 1, 2, 0, 0), "div32 r1, r2"),
+            (V0, ix(OpCode::Or32Imm, 1, 0, 0, -3), "or32 r1, -3"),
+            (V0, ix(OpCode::Or32Reg, 1, 2, 0, 0), "or32 r1, r2"),
+            (V0, ix(OpCode::And32Imm, 1, 0, 0, -3), "and32 r1, -3"),
+            (V0, ix(OpCode::And32Reg, 1, 2, 0, 0), "and32 r1, r2"),
+            (V0, ix(OpCode::Lsh32Imm, 1, 0, 0, -3), "lsh32 r1, -3"),
+            (V0, ix(OpCode::Lsh32Reg, 1, 2, 0, 0), "lsh32 r1, r2"),
+            (V0, ix(OpCode::Rsh32Imm, 1, 0, 0, -3), "rsh32 r1, -3"),
+            (V0, ix(OpCode::Rsh32Reg, 1, 2, 0, 0), "rsh32 r1, r2"),
+            (V0, ix(OpCode::Neg32, 1, 0, 0, 0), "neg32 r1"),
+            (V0, ix(OpCode::Mod32Imm, 1, 0, 0, -3), "mod32 r1, -3"),
+            (V0, ix(OpCode::Mod32Reg, 1, 2, 0, 0), "mod32 r1, r2"),
+            (V0, ix(OpCode::Xor32Imm, 1, 0, 0, -3), "xor32 r1, -3"),
+            (V0, ix(OpCode::Xor32Reg, 1, 2, 0, 0), "xor32 r1, r2"),
+            (V0, ix(OpCode::Mov32Imm, 1, 0, 0, -3), "mov32 r1, -3"),
+            (V0, ix(OpCode::Mov32Reg, 1, 2, 0, 0), "mov32 r1, r2"),
+            (V0, ix(OpCode::Arsh32Imm, 1, 0, 0, -3), "arsh32 r1, -3"),
+            (V0, ix(OpCode::Arsh32Reg, 1, 2, 0, 0), "arsh32 r1, r2"),
+            (V2, ix(OpCode::Lmul32Imm, 1, 0, 0, -3), "lmul32 r1, -3"),
+            (V2, ix(OpCode::Lmul32Reg, 1, 2, 0, 0), "lmul32 r1, r2"),
+            (V2, ix(OpCode::Udiv32Imm, 1, 0, 0, -3), "udiv32 r1, -3"),
+            (V2, ix(OpCode::Udiv32Reg, 1, 2, 0, 0), "udiv32 r1, r2"),
+            (V2, ix(OpCode::Urem32Imm, 1, 0, 0, -3), "urem32 r1, -3"),
+            (V2, ix(OpCode::Urem32Reg, 1, 2, 0, 0), "urem32 r1, r2"),
+            (V2, ix(OpCode::Sdiv32Imm, 1, 0, 0, -3), "sdiv32 r1, -3"),
+            (V2, ix(OpCode::Sdiv32Reg, 1, 2, 0, 0), "sdiv32 r1, r2"),
+            (V2, ix(OpCode::Srem32Imm, 1, 0, 0, -3), "srem32 r1, -3"),
+            (V2, ix(OpCode::Srem32Reg, 1, 2, 0, 0), "srem32 r1, r2"),
+            (V0, ix(OpCode::Le, 1, 0, 0, 16), "le16 r1"),
+            (V0, ix(OpCode::Be, 1, 0, 0, 64), "be64 r1"),
+            (V0, ix(OpCode::Add64Imm, 1, 0, 0, -3), "add64 r1, -3"),
+            (V0, ix(OpCode::Add64Reg, 1, 2, 0, 0), "add64 r1, r2"),
+            (V0, ix(OpCode::Sub64Imm, 1, 0, 0, -3), "sub64 r1, -3"),
+            (V0, ix(OpCode::Sub64Reg, 1, 2, 0, 0), "sub64 r1, r2"),
+            (V0, ix(OpCode::Mul64Imm, 1, 0, 0, -3), "mul64 r1, -3"),
+            (V0, ix(OpCode::Mul64Reg, 1, 2, 0, 0), "mul64 r1, r2"),
+            (V0, ix(OpCode::Div64Imm, 1, 0, 0, -3), "div64 r1, -3"),
+            (V0, ix(OpCode::Div64Reg, 1, 2, 0, 0), "div64 r1, r2"),
+            (V0, ix(OpCode::Or64Imm, 1, 0, 0, -3), "or64 r1, -3"),
+            (V0, ix(OpCode::Or64Reg, 1, 2, 0, 0), "or64 r1, r2"),
+            (V0, ix(OpCode::And64Imm, 1, 0, 0, -3), "and64 r1, -3"),
+            (V0, ix(OpCode::And64Reg, 1, 2, 0, 0), "and64 r1, r2"),
+            (V0, ix(OpCode::Lsh64Imm, 1, 0, 0, -3), "lsh64 r1, -3"),
+            (V0, ix(OpCode::Lsh64Reg, 1, 2, 0, 0), "lsh64 r1, r2"),
+            (V0, ix(OpCode::Rsh64Imm, 1, 0, 0, -3), "rsh64 r1, -3"),
+            (V0, ix(OpCode::Rsh64Reg, 1, 2, 0, 0), "rsh64 r1, r2"),
+            (V0, ix(OpCode::Neg64, 1, 0, 0, 0), "neg64 r1"),
+            (V0, ix(OpCode::Mod64Imm, 1, 0, 0, -3), "mod64 r1, -3"),
+            (V0, ix(OpCode::Mod64Reg, 1, 2, 0, 0), "mod64 r1, r2"),
+            (V0, ix(OpCode::Xor64Imm, 1, 0, 0, -3), "xor64 r1, -3"),
+            (V0, ix(OpCode::Xor64Reg, 1, 2, 0, 0), "xor64 r1, r2"),
+            (V0, ix(OpCode::Mov64Imm, 1, 0, 0, -3), "mov64 r1, -3"),
+            (V0, ix(OpCode::Mov64Reg, 1, 2, 0, 0), "mov64 r1, r2"),
+            (V0, ix(OpCode::Arsh64Imm, 1, 0, 0, -3), "arsh64 r1, -3"),
+            (V0, ix(OpCode::Arsh64Reg, 1, 2, 0, 0), "arsh64 r1, r2"),
+            (V2, ix(OpCode::Hor64Imm, 1, 0, 0, -3), "hor64 r1, -3"),
+            (V2, ix(OpCode::Lmul64Imm, 1, 0, 0, -3), "lmul64 r1, -3"),
+            (V2, ix(OpCode::Lmul64Reg, 1, 2, 0, 0), "lmul64 r1, r2"),
+            (V2, ix(OpCode::Uhmul64Imm, 1, 0, 0, -3), "uhmul64 r1, -3"),
+            (V2, ix(OpCode::Uhmul64Reg, 1, 2, 0, 0), "uhmul64 r1, r2"),
+            (V2, ix(OpCode::Udiv64Imm, 1, 0, 0, -3), "udiv64 r1, -3"),
+            (V2, ix(OpCode::Udiv64Reg, 1, 2, 0, 0), "udiv64 r1, r2"),
+            (V2, ix(OpCode::Urem64Imm, 1, 0, 0, -3), "urem64 r1, -3"),
+            (V2, ix(OpCode::Urem64Reg, 1, 2, 0, 0), "urem64 r1, r2"),
+            (V2, ix(OpCode::Shmul64Imm, 1, 0, 0, -3), "shmul64 r1, -3"),
+            (V2, ix(OpCode::Shmul64Reg, 1, 2, 0, 0), "shmul64 r1, r2"),
+            (V2, ix(OpCode::Sdiv64Imm, 1, 0, 0, -3), "sdiv64 r1, -3"),
+            (V2, ix(OpCode::Sdiv64Reg, 1, 2, 0, 0), "sdiv64 r1, r2"),
+            (V2, ix(OpCode::Srem64Imm, 1, 0, 0, -3), "srem64 r1, -3"),
+            (V2, ix(OpCode::Srem64Reg, 1, 2, 0, 0), "srem64 r1, r2"),
+            (V0, ix(OpCode::Ja, 0, 0, -2, 0), "ja -2"),
+            (V0, ix(OpCode::JeqImm, 1, 0, 3, 7), "jeq r1, 7, +3"),
+            (V0, ix(OpCode::JeqReg, 1, 2, 3, 0), "jeq r1, r2, +3"),
+            (V0, ix(OpCode::JgtImm, 1, 0, 3, 7), "jgt r1, 7, +3"),
+            (V0, ix(OpCode::JgtReg, 1, 2, 3, 0), "jgt r1, r2, +3"),
+            (V0, ix(OpCode::JgeImm, 1, 0, 3, 7), "jge r1, 7, +3"),
+            (V0, ix(OpCode::JgeReg, 1, 2, 3, 0), "jge r1, r2, +3"),
+            (V0, ix(OpCode::JltImm, 1, 0, 3, 7), "jlt r1, 7, +3"),
+            (V0, ix(OpCode::JltReg, 1, 2, 3, 0), "jlt r1, r2, +3"),
+            (V0, ix(OpCode::JleImm, 1, 0, 3, 7), "jle r1, 7, +3"),
+            (V0, ix(OpCode::JleReg, 1, 2, 3, 0), "jle r1, r2, +3"),
+            (V0, ix(OpCode::JsetImm, 1, 0, 3, 7), "jset r1, 7, +3"),
+            (V0, ix(OpCode::JsetReg, 1, 2, 3, 0), "jset r1, r2, +3"),
+            (V0, ix(OpCode::JneImm, 1, 0, 3, 7), "jne r1, 7, +3"),
+            (V0, ix(OpCode::JneReg, 1, 2, 3, 0), "jne r1, r2, +3"),
+            (V0, ix(OpCode::JsgtImm, 1, 0, 3, 7), "jsgt r1, 7, +3"),
+            (V0, ix(OpCode::JsgtReg, 1, 2, 3, 0), "jsgt r1, r2, +3"),
+            (V0, ix(OpCode::JsgeImm, 1, 0, 3, 7), "jsge r1, 7, +3"),
+            (V0, ix(OpCode::JsgeReg, 1, 2, 3, 0), "jsge r1, r2, +3"),
+            (V0, ix(OpCode::JsltImm, 1, 0, 3, 7), "jslt r1, 7, +3"),
+            (V0, ix(OpCode::JsltReg, 1, 2, 3, 0), "jslt r1, r2, +3"),
+            (V0, ix(OpCode::JsleImm, 1, 0, 3, 7), "jsle r1, 7, +3"),
+            (V0, ix(OpCode::JsleReg, 1, 2, 3, 0), "jsle r1, r2, +3"),
+            (V0, ix(OpCode::Call, 0, 0, 0, 5), "call 5"),
+            (V0, ix(OpCode::Callx, 0, 0, 0, 3), "callx r3"),
+            (V0, ix(OpCode::Exit, 0, 0, 0, 0), "exit"),
+            (V3, ix(OpCode::Syscall, 0, 0, 0, 0x207559bd), "syscall sol_log_"),
+            (V3, ix(OpCode::Return, 0, 0, 0, 0), "return"),
+        ];
+        let ops: HashSet<String> = cases.iter().map(|(_, ix, _)| format!("{:?}", ix.op)).collect();
+        assert_eq!(ops.len(), 118);
+        for (version, ix, asm) in cases {
+            let decoded = Ix::from_bytes(&ix.to_bytes(version).unwrap(), version).unwrap();
+            assert_eq!(decoded.op, ix.op);
+            assert_eq!(decoded.to_asm(version).unwrap(), asm, "{:?}", ix.op);
+            assert_eq!(Ix::parse_asm(asm, version).unwrap(), decoded);
+        }
+    }
+
+    #[test]
+    fn parse_asm() {
+        let ix: Ix = "ldxdw r1, [r2 + 0x8]".parse().unwrap();
+        assert_eq!((ix.op, ix.dst, ix.src, ix.off), (OpCode::Ldxdw, 1, 2, 8));
+        let ix: Ix = "stw [r10 - 4], 0xffffffff".parse().unwrap();
+        assert_eq!((ix.off, ix.imm), (-4, -1));
+        let ix: Ix = "jeq w1, 7, 3 // unsigned offset".parse().unwrap();
+        assert_eq!((ix.op, ix.imm, ix.off), (OpCode::JeqImm, 7, 3));
+        let ix: Ix = "call sol_log_".parse().unwrap();
+        assert_eq!(ix.imm, 0x207559bd);
+        let ix = Ix::parse_asm("callx r3", SbpfVersion::V2).unwrap();
+        assert_eq!((ix.src, ix.imm), (3, 0));
+        assert_eq!("exit ; done".parse::<Ix>().unwrap().op, OpCode::Exit);
+        // call is only relative once syscalls are static
+        assert!(Ix::parse_asm("call sol_log_", SbpfVersion::V3).is_err());
+        let ix = Ix::parse_asm("syscall sol_log_", SbpfVersion::V3).unwrap();
+        assert_eq!(ix.imm, 0x207559bd);
+
+        // llvm's syntax for the same instructions
+        let llvm = [
+            ("r1 = 5", "mov64 r1, 5"),
+            ("w1 += w2", "add32 r1, r2"),
+            ("r1 s>>= 3", "arsh64 r1, 3"),
+            ("r1 = -r1", "neg64 r1"),
+            ("r1 = be16 r1", "be16 r1"),
+            ("r0 = 0x100000002 ll", "lddw r0, 0x100000002"),
+            ("r2 = *(u8 *)(r1 + 0)", "ldxb r2, [r1]"),
+            ("*(u64 *)(r10 - 8) = r1", "stxdw [r10-8], r1"),
+            ("*(u32 *)(r1 + 4) = 7", "stw [r1+4], 7"),
+            ("if r1 == 0 goto +3", "jeq r1, 0, +3"),
+            ("if r1 s< r2 goto -1 // loop", "jslt r1, r2, -1"),
+            ("goto +2", "ja +2"),
+        ];
+        for (llvm, asm) in llvm {
+            assert_eq!(llvm.parse::<Ix>(), asm.parse::<Ix>(), "{}", llvm);
+        }
+        assert_eq!(
+            "  r1 = *(u128 *)(r2 + 0)".parse::<Ix>(),
+            Err(EZBpfError::InvalidAsm {
+                offset: Some(2),
+                value: "r1 = *(u128 *)(r2 + 0)".to_string()
+            })
+        );
+        assert!("if r1 === 0 goto +1".parse::<Ix>().is_err());
+
+        // mul64 was replaced by lmul64 in SBPFv2
+        assert_eq!(
+            Ix::parse_asm("mul64 r1, 2", SbpfVersion::V2),
+            Err(EZBpfError::InvalidAsm {
+                offset: Some(0),
+                value: "mul64 r1, 2".to_string()
+            })
+        );
+        assert_eq!(
+            "add64 r1, [r11]".parse::<Ix>(),
+            Err(EZBpfError::InvalidAsm {
+                offset: Some(10),
+                value: "[r11]".to_string()
+            })
+        );
+        assert!("add32 r1, 0x100000000".parse::<Ix>().is_err());
+        assert!("neg64 r1, r2".parse::<Ix>().is_err());
+        assert!("le r1".parse::<Ix>().is_err());
+        assert!("".parse::<Ix>().is_err());
+    }
+
+    // An instruction with only the fields its assembly shows
+    fn canonical(op: OpCode, version: SbpfVersion, dst: u8, src: u8, off: i16, imm: i64) -> Ix {
+        let mut ix = Ix {
+            op,
+            dst: 0,
+            src: 0,
+            off: 0,
+            imm: 0,
+        };
+        let imm32 = imm as i32 as i64;
+        match ix.op.operands() {
+            Operands::None => {}
+            Operands::Dst => ix.dst = dst,
+            Operands::DstImm if ix.op == OpCode::Lddw => (ix.dst, ix.imm) = (dst, imm),
+            Operands::DstImm => (ix.dst, ix.imm) = (dst, imm32),
+            Operands::DstSrc => (ix.dst, ix.src) = (dst, src),
+            Operands::Load | Operands::StoreReg => (ix.dst, ix.src, ix.off) = (dst, src, off),
+            Operands::StoreImm => (ix.dst, ix.off, ix.imm) = (dst, off, imm32),
+            Operands::Endian => (ix.dst, ix.imm) = (dst, [16, 32, 64][imm.unsigned_abs() as usize % 3]),
+            Operands::Jump => ix.off = off,
+            Operands::JumpImm => (ix.dst, ix.imm, ix.off) = (dst, imm32, off),
+            Operands::JumpReg => (ix.dst, ix.src, ix.off) = (dst, src, off),
+            Operands::Call => ix.imm = imm32,
+            Operands::CallReg if version.callx_uses_src_reg() => ix.src = src,
+            Operands::CallReg => ix.imm = src as i64,
+        }
+        ix
+    }
+
+    proptest! {
+        #[test]
+        fn asm_round_trip(
+            version in 0..4u32,
+            op in any::<Index>(),
+            dst in 0..=10u8,
+            src in 0..=10u8,
+            off: i16,
+            imm: i64,
+        ) {
+            let version = SbpfVersion::from(version);
+            let ops: Vec<OpCode> = OpCode::all(version).collect();
+            let ix = canonical(op.get(&ops).clone(), version, dst, src, off, imm);
+            let ix = Ix::from_bytes(&ix.to_bytes(version).unwrap(), version).unwrap();
+            let asm = ix.to_asm(version).unwrap();
+            prop_assert_eq!(Ix::parse_asm(&asm, version), Ok(ix), "{}", asm);
+        }
     }
 }
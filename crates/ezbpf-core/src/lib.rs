@@ -1,9 +1,25 @@
+pub mod address_space;
 pub mod cursor;
+pub mod diagnostic;
+pub mod disassembler;
+pub mod dynamic;
 pub mod elf_header;
 pub mod errors;
 pub mod instructions;
+pub mod murmur3;
 pub mod opcodes;
 pub mod program;
 pub mod program_header;
+pub mod program_ref;
+pub mod relocation;
+pub mod sbpf_version;
 pub mod section_header;
 pub mod section_header_entry;
+pub mod segment_map;
+pub mod string_table;
+pub mod symbol;
+pub mod syscalls;
+pub mod validate;
+
+#[cfg(test)]
+mod fixtures;
@@ -0,0 +1,61 @@
+// MurmurHash3 x86 32 bit, seeded with 0. Before static syscalls the loader
+// replaces call immediates with the hash of the syscall name or of the called
+// function's slot.
+
+const C1: u32 = 0xcc9e2d51;
+const C2: u32 = 0x1b873593;
+
+fn mix(k: u32) -> u32 {
+    k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2)
+}
+
+pub fn murmur3_32(data: &[u8]) -> u32 {
+    let mut h: u32 = 0;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        h ^= mix(k);
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k = tail
+            .iter()
+            .rev()
+            .fold(0u32, |k, b| (k << 8) | *b as u32);
+        h ^= mix(k);
+    }
+    h ^= data.len() as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^ (h >> 16)
+}
+
+/// The call immediate for a function registered by name, as the entrypoint
+/// and syscalls are.
+pub fn hash_symbol_name(name: &[u8]) -> u32 {
+    murmur3_32(name)
+}
+
+/// The call immediate for a function at slot `pc`.
+pub fn hash_function(pc: usize) -> u32 {
+    murmur3_32(&(pc as u64).to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::murmur3::{hash_function, hash_symbol_name, murmur3_32};
+
+    #[test]
+    fn known_hashes() {
+        assert_eq!(murmur3_32(b""), 0);
+        assert_eq!(murmur3_32(b"a"), 0x3c2569b2);
+        assert_eq!(murmur3_32(b"abcd"), 0x43ed676a);
+        assert_eq!(hash_symbol_name(b"sol_log_"), 0x207559bd);
+        assert_eq!(hash_symbol_name(b"abort"), 0xb6fc1a11);
+        assert_eq!(hash_symbol_name(b"entrypoint"), 0x71e3cf81);
+        assert_eq!(hash_function(0), murmur3_32(&[0; 8]));
+    }
+}
@@ -3,127 +3,146 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-use crate::errors::EZBpfError;
+use crate::{errors::EZBpfError, sbpf_version::SbpfVersion};
 
-#[repr(u8)]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum OpCode {
-    Lddw = 0x18,
-    Ldxb = 0x71,
-    Ldxh = 0x69,
-    Ldxw = 0x61,
-    Ldxdw = 0x79,
-    Stb = 0x72,
-    Sth = 0x6a,
-    Stw = 0x62,
-    Stdw = 0x7a,
-    Stxb = 0x73,
-    Stxh = 0x6b,
-    Stxw = 0x63,
-    Stxdw = 0x7b,
-    Add32Imm = 0x04,
-    Add32Reg = 0x0c,
-    Sub32Imm = 0x14,
-    Sub32Reg = 0x1c,
-    Mul32Imm = 0x24,
-    Mul32Reg = 0x2c,
-    Div32Imm = 0x34,
-    Div32Reg = 0x3c,
-    Or32Imm = 0x44,
-    Or32Reg = 0x4c,
-    And32Imm = 0x54,
-    And32Reg = 0x5c,
-    Lsh32Imm = 0x64,
-    Lsh32Reg = 0x6c,
-    Rsh32Imm = 0x74,
-    Rsh32Reg = 0x7c,
-    Neg32 = 0x84,
-    Mod32Imm = 0x94,
-    Mod32Reg = 0x9c,
-    Xor32Imm = 0xa4,
-    Xor32Reg = 0xac,
-    Mov32Imm = 0xb4,
-    Mov32Reg = 0xbc,
-    Arsh32Imm = 0xc4,
-    Arsh32Reg = 0xcc,
-    Lmul32Imm = 0x86,
-    Lmul32Reg = 0x8e,
-    Udiv32Imm = 0x46,
-    Udiv32Reg = 0x4e,
-    Urem32Imm = 0x66,
-    Urem32Reg = 0x6e,
-    Sdiv32Imm = 0xc6,
-    Sdiv32Reg = 0xce,
-    Srem32Imm = 0xe6,
-    Srem32Reg = 0xee,
-    Le = 0xd4,
-    Be = 0xdc,
-    Add64Imm = 0x07,
-    Add64Reg = 0x0f,
-    Sub64Imm = 0x17,
-    Sub64Reg = 0x1f,
-    Mul64Imm = 0x27,
-    Mul64Reg = 0x2f,
-    Div64Imm = 0x37,
-    Div64Reg = 0x3f,
-    Or64Imm = 0x47,
-    Or64Reg = 0x4f,
-    And64Imm = 0x57,
-    And64Reg = 0x5f,
-    Lsh64Imm = 0x67,
-    Lsh64Reg = 0x6f,
-    Rsh64Imm = 0x77,
-    Rsh64Reg = 0x7f,
-    Neg64 = 0x87,
-    Mod64Imm = 0x97,
-    Mod64Reg = 0x9f,
-    Xor64Imm = 0xa7,
-    Xor64Reg = 0xaf,
-    Mov64Imm = 0xb7,
-    Mov64Reg = 0xbf,
-    Arsh64Imm = 0xc7,
-    Arsh64Reg = 0xcf,
-    Hor64Imm = 0xf7,
-    Lmul64Imm = 0x96,
-    Lmul64Reg = 0x9e,
-    Uhmul64Imm = 0x36,
-    Uhmul64Reg = 0x3e,
-    Udiv64Imm = 0x56,
-    Udiv64Reg = 0x5e,
-    Urem64Imm = 0x76,
-    Urem64Reg = 0x7e,
-    Shmul64Imm = 0xb6,
-    Shmul64Reg = 0xbe,
-    Sdiv64Imm = 0xd6,
-    Sdiv64Reg = 0xde,
-    Srem64Imm = 0xf6,
-    Srem64Reg = 0xfe,
-    Ja = 0x05,
-    JeqImm = 0x15,
-    JeqReg = 0x1d,
-    JgtImm = 0x25,
-    JgtReg = 0x2d,
-    JgeImm = 0x35,
-    JgeReg = 0x3d,
-    JltImm = 0xa5,
-    JltReg = 0xad,
-    JleImm = 0xb5,
-    JleReg = 0xbd,
-    JsetImm = 0x45,
-    JsetReg = 0x4d,
-    JneImm = 0x55,
-    JneReg = 0x5d,
-    JsgtImm = 0x65,
-    JsgtReg = 0x6d,
-    JsgeImm = 0x75,
-    JsgeReg = 0x7d,
-    JsltImm = 0xc5,
-    JsltReg = 0xcd,
-    JsleImm = 0xd5,
-    JsleReg = 0xdd,
-    Call = 0x85,
-    Callx = 0x8d,
-    Exit = 0x95,
+    Lddw,
+    Ldxb,
+    Ldxh,
+    Ldxw,
+    Ldxdw,
+    Stb,
+    Sth,
+    Stw,
+    Stdw,
+    Stxb,
+    Stxh,
+    Stxw,
+    Stxdw,
+    Add32Imm,
+    Add32Reg,
+    Sub32Imm,
+    Sub32Reg,
+    Mul32Imm,
+    Mul32Reg,
+    Div32Imm,
+    Div32Reg,
+    Or32Imm,
+    Or32Reg,
+    And32Imm,
+    And32Reg,
+    Lsh32Imm,
+    Lsh32Reg,
+    Rsh32Imm,
+    Rsh32Reg,
+    Neg32,
+    Mod32Imm,
+    Mod32Reg,
+    Xor32Imm,
+    Xor32Reg,
+    Mov32Imm,
+    Mov32Reg,
+    Arsh32Imm,
+    Arsh32Reg,
+    Lmul32Imm,
+    Lmul32Reg,
+    Udiv32Imm,
+    Udiv32Reg,
+    Urem32Imm,
+    Urem32Reg,
+    Sdiv32Imm,
+    Sdiv32Reg,
+    Srem32Imm,
+    Srem32Reg,
+    Le,
+    Be,
+    Add64Imm,
+    Add64Reg,
+    Sub64Imm,
+    Sub64Reg,
+    Mul64Imm,
+    Mul64Reg,
+    Div64Imm,
+    Div64Reg,
+    Or64Imm,
+    Or64Reg,
+    And64Imm,
+    And64Reg,
+    Lsh64Imm,
+    Lsh64Reg,
+    Rsh64Imm,
+    Rsh64Reg,
+    Neg64,
+    Mod64Imm,
+    Mod64Reg,
+    Xor64Imm,
+    Xor64Reg,
+    Mov64Imm,
+    Mov64Reg,
+    Arsh64Imm,
+    Arsh64Reg,
+    Hor64Imm,
+    Lmul64Imm,
+    Lmul64Reg,
+    Uhmul64Imm,
+    Uhmul64Reg,
+    Udiv64Imm,
+    Udiv64Reg,
+    Urem64Imm,
+    Urem64Reg,
+    Shmul64Imm,
+    Shmul64Reg,
+    Sdiv64Imm,
+    Sdiv64Reg,
+    Srem64Imm,
+    Srem64Reg,
+    Ja,
+    JeqImm,
+    JeqReg,
+    JgtImm,
+    JgtReg,
+    JgeImm,
+    JgeReg,
+    JltImm,
+    JltReg,
+    JleImm,
+    JleReg,
+    JsetImm,
+    JsetReg,
+    JneImm,
+    JneReg,
+    JsgtImm,
+    JsgtReg,
+    JsgeImm,
+    JsgeReg,
+    JsltImm,
+    JsltReg,
+    JsleImm,
+    JsleReg,
+    Call,
+    Callx,
+    Exit,
+    Syscall,
+    Return,
+}
+
+/// How the operands of an instruction are written in assembly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operands {
+    None,     // exit
+    Dst,      // neg64 r1
+    DstImm,   // add64 r1, 1
+    DstSrc,   // add64 r1, r2
+    Load,     // ldxdw r1, [r2+8]
+    StoreImm, // stdw [r1+8], 1
+    StoreReg, // stxdw [r1+8], r2
+    Endian,   // be16 r1, with the width taken from the immediate
+    Jump,     // ja +1
+    JumpImm,  // jeq r1, 1, +1
+    JumpReg,  // jeq r1, r2, +1
+    Call,     // call 1, or the name of the syscall the immediate hashes
+    CallReg,  // callx r1
 }
 
 impl Display for OpCode {
@@ -136,131 +155,446 @@ impl TryFrom<u8> for OpCode {
     type Error = EZBpfError;
 
     fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Ok(match value {
-            0x18 => OpCode::Lddw,
-            0x71 => OpCode::Ldxb,
-            0x69 => OpCode::Ldxh,
-            0x61 => OpCode::Ldxw,
-            0x79 => OpCode::Ldxdw,
-            0x72 => OpCode::Stb,
-            0x6a => OpCode::Sth,
-            0x62 => OpCode::Stw,
-            0x7a => OpCode::Stdw,
-            0x73 => OpCode::Stxb,
-            0x6b => OpCode::Stxh,
-            0x63 => OpCode::Stxw,
-            0x7b => OpCode::Stxdw,
-            0x04 => OpCode::Add32Imm,
-            0x0c => OpCode::Add32Reg,
-            0x14 => OpCode::Sub32Imm,
-            0x1c => OpCode::Sub32Reg,
-            0x24 => OpCode::Mul32Imm,
-            0x2c => OpCode::Mul32Reg,
-            0x34 => OpCode::Div32Imm,
-            0x3c => OpCode::Div32Reg,
-            0x44 => OpCode::Or32Imm,
-            0x4c => OpCode::Or32Reg,
-            0x54 => OpCode::And32Imm,
-            0x5c => OpCode::And32Reg,
-            0x64 => OpCode::Lsh32Imm,
-            0x6c => OpCode::Lsh32Reg,
-            0x74 => OpCode::Rsh32Imm,
-            0x7c => OpCode::Rsh32Reg,
-            0x84 => OpCode::Neg32,
-            0x94 => OpCode::Mod32Imm,
-            0x9c => OpCode::Mod32Reg,
-            0xa4 => OpCode::Xor32Imm,
-            0xac => OpCode::Xor32Reg,
-            0xb4 => OpCode::Mov32Imm,
-            0xbc => OpCode::Mov32Reg,
-            0xc4 => OpCode::Arsh32Imm,
-            0xcc => OpCode::Arsh32Reg,
-            0x86 => OpCode::Lmul32Imm,
-            0x8e => OpCode::Lmul32Reg,
-            0x46 => OpCode::Udiv32Imm,
-            0x4e => OpCode::Udiv32Reg,
-            0x66 => OpCode::Urem32Imm,
-            0x6e => OpCode::Urem32Reg,
-            0xc6 => OpCode::Sdiv32Imm,
-            0xce => OpCode::Sdiv32Reg,
-            0xe6 => OpCode::Srem32Imm,
-            0xee => OpCode::Srem32Reg,
-            0xd4 => OpCode::Le,
-            0xdc => OpCode::Be,
-            0x07 => OpCode::Add64Imm,
-            0x0f => OpCode::Add64Reg,
-            0x17 => OpCode::Sub64Imm,
-            0x1f => OpCode::Sub64Reg,
-            0x27 => OpCode::Mul64Imm,
-            0x2f => OpCode::Mul64Reg,
-            0x37 => OpCode::Div64Imm,
-            0x3f => OpCode::Div64Reg,
-            0x47 => OpCode::Or64Imm,
-            0x4f => OpCode::Or64Reg,
-            0x57 => OpCode::And64Imm,
-            0x5f => OpCode::And64Reg,
-            0x67 => OpCode::Lsh64Imm,
-            0x6f => OpCode::Lsh64Reg,
-            0x77 => OpCode::Rsh64Imm,
-            0x7f => OpCode::Rsh64Reg,
-            0x87 => OpCode::Neg64,
-            0x97 => OpCode::Mod64Imm,
-            0x9f => OpCode::Mod64Reg,
-            0xa7 => OpCode::Xor64Imm,
-            0xaf => OpCode::Xor64Reg,
-            0xb7 => OpCode::Mov64Imm,
-            0xbf => OpCode::Mov64Reg,
-            0xc7 => OpCode::Arsh64Imm,
-            0xcf => OpCode::Arsh64Reg,
-            0xf7 => OpCode::Hor64Imm,
-            0x96 => OpCode::Lmul64Imm,
-            0x9e => OpCode::Lmul64Reg,
-            0x36 => OpCode::Uhmul64Imm,
-            0x3e => OpCode::Uhmul64Reg,
-            0x56 => OpCode::Udiv64Imm,
-            0x5e => OpCode::Udiv64Reg,
-            0x76 => OpCode::Urem64Imm,
-            0x7e => OpCode::Urem64Reg,
-            0xb6 => OpCode::Shmul64Imm,
-            0xbe => OpCode::Shmul64Reg,
-            0xd6 => OpCode::Sdiv64Imm,
-            0xde => OpCode::Sdiv64Reg,
-            0xf6 => OpCode::Srem64Imm,
-            0xfe => OpCode::Srem64Reg,
-            0x05 => OpCode::Ja,
-            0x15 => OpCode::JeqImm,
-            0x1d => OpCode::JeqReg,
-            0x25 => OpCode::JgtImm,
-            0x2d => OpCode::JgtReg,
-            0x35 => OpCode::JgeImm,
-            0x3d => OpCode::JgeReg,
-            0xa5 => OpCode::JltImm,
-            0xad => OpCode::JltReg,
-            0xb5 => OpCode::JleImm,
-            0xbd => OpCode::JleReg,
-            0x45 => OpCode::JsetImm,
-            0x4d => OpCode::JsetReg,
-            0x55 => OpCode::JneImm,
-            0x5d => OpCode::JneReg,
-            0x65 => OpCode::JsgtImm,
-            0x6d => OpCode::JsgtReg,
-            0x75 => OpCode::JsgeImm,
-            0x7d => OpCode::JsgeReg,
-            0xc5 => OpCode::JsltImm,
-            0xcd => OpCode::JsltReg,
-            0xd5 => OpCode::JsleImm,
-            0xdd => OpCode::JsleReg,
-            0x85 => OpCode::Call,
-            0x8d => OpCode::Callx,
-            0x95 => OpCode::Exit,
-            _ => return Err(EZBpfError::InvalidOpcode),
+        OpCode::try_from((value, SbpfVersion::V0))
+    }
+}
+
+// Decodes an opcode with the opcode table of a particular SBPF version
+impl TryFrom<(u8, SbpfVersion)> for OpCode {
+    type Error = EZBpfError;
+
+    fn try_from((value, version): (u8, SbpfVersion)) -> Result<Self, Self::Error> {
+        Ok(match (value, version) {
+            (0x18, v) if !v.disable_lddw() => OpCode::Lddw,
+            (0x71, v) if !v.move_memory_instruction_classes() => OpCode::Ldxb,
+            (0x69, v) if !v.move_memory_instruction_classes() => OpCode::Ldxh,
+            (0x61, v) if !v.move_memory_instruction_classes() => OpCode::Ldxw,
+            (0x79, v) if !v.move_memory_instruction_classes() => OpCode::Ldxdw,
+            (0x72, v) if !v.move_memory_instruction_classes() => OpCode::Stb,
+            (0x6a, v) if !v.move_memory_instruction_classes() => OpCode::Sth,
+            (0x62, v) if !v.move_memory_instruction_classes() => OpCode::Stw,
+            (0x7a, v) if !v.move_memory_instruction_classes() => OpCode::Stdw,
+            (0x73, v) if !v.move_memory_instruction_classes() => OpCode::Stxb,
+            (0x6b, v) if !v.move_memory_instruction_classes() => OpCode::Stxh,
+            (0x63, v) if !v.move_memory_instruction_classes() => OpCode::Stxw,
+            (0x7b, v) if !v.move_memory_instruction_classes() => OpCode::Stxdw,
+            // Memory instruction classes moved into the space freed up by PQR
+            (0x2c, v) if v.move_memory_instruction_classes() => OpCode::Ldxb,
+            (0x3c, v) if v.move_memory_instruction_classes() => OpCode::Ldxh,
+            (0x8c, v) if v.move_memory_instruction_classes() => OpCode::Ldxw,
+            (0x9c, v) if v.move_memory_instruction_classes() => OpCode::Ldxdw,
+            (0x27, v) if v.move_memory_instruction_classes() => OpCode::Stb,
+            (0x37, v) if v.move_memory_instruction_classes() => OpCode::Sth,
+            (0x87, v) if v.move_memory_instruction_classes() => OpCode::Stw,
+            (0x97, v) if v.move_memory_instruction_classes() => OpCode::Stdw,
+            (0x2f, v) if v.move_memory_instruction_classes() => OpCode::Stxb,
+            (0x3f, v) if v.move_memory_instruction_classes() => OpCode::Stxh,
+            (0x8f, v) if v.move_memory_instruction_classes() => OpCode::Stxw,
+            (0x9f, v) if v.move_memory_instruction_classes() => OpCode::Stxdw,
+            (0x04, _) => OpCode::Add32Imm,
+            (0x0c, _) => OpCode::Add32Reg,
+            (0x14, _) => OpCode::Sub32Imm,
+            (0x1c, _) => OpCode::Sub32Reg,
+            (0x24, v) if !v.enable_pqr() => OpCode::Mul32Imm,
+            (0x2c, v) if !v.enable_pqr() => OpCode::Mul32Reg,
+            (0x34, v) if !v.enable_pqr() => OpCode::Div32Imm,
+            (0x3c, v) if !v.enable_pqr() => OpCode::Div32Reg,
+            (0x44, _) => OpCode::Or32Imm,
+            (0x4c, _) => OpCode::Or32Reg,
+            (0x54, _) => OpCode::And32Imm,
+            (0x5c, _) => OpCode::And32Reg,
+            (0x64, _) => OpCode::Lsh32Imm,
+            (0x6c, _) => OpCode::Lsh32Reg,
+            (0x74, _) => OpCode::Rsh32Imm,
+            (0x7c, _) => OpCode::Rsh32Reg,
+            (0x84, v) if !v.disable_neg() => OpCode::Neg32,
+            (0x94, v) if !v.enable_pqr() => OpCode::Mod32Imm,
+            (0x9c, v) if !v.enable_pqr() => OpCode::Mod32Reg,
+            (0xa4, _) => OpCode::Xor32Imm,
+            (0xac, _) => OpCode::Xor32Reg,
+            (0xb4, _) => OpCode::Mov32Imm,
+            (0xbc, _) => OpCode::Mov32Reg,
+            (0xc4, _) => OpCode::Arsh32Imm,
+            (0xcc, _) => OpCode::Arsh32Reg,
+            (0x86, v) if v.enable_pqr() => OpCode::Lmul32Imm,
+            (0x8e, v) if v.enable_pqr() => OpCode::Lmul32Reg,
+            (0x46, v) if v.enable_pqr() => OpCode::Udiv32Imm,
+            (0x4e, v) if v.enable_pqr() => OpCode::Udiv32Reg,
+            (0x66, v) if v.enable_pqr() => OpCode::Urem32Imm,
+            (0x6e, v) if v.enable_pqr() => OpCode::Urem32Reg,
+            (0xc6, v) if v.enable_pqr() => OpCode::Sdiv32Imm,
+            (0xce, v) if v.enable_pqr() => OpCode::Sdiv32Reg,
+            (0xe6, v) if v.enable_pqr() => OpCode::Srem32Imm,
+            (0xee, v) if v.enable_pqr() => OpCode::Srem32Reg,
+            (0xd4, v) if !v.disable_le() => OpCode::Le,
+            (0xdc, _) => OpCode::Be,
+            (0x07, _) => OpCode::Add64Imm,
+            (0x0f, _) => OpCode::Add64Reg,
+            (0x17, _) => OpCode::Sub64Imm,
+            (0x1f, _) => OpCode::Sub64Reg,
+            (0x27, v) if !v.enable_pqr() => OpCode::Mul64Imm,
+            (0x2f, v) if !v.enable_pqr() => OpCode::Mul64Reg,
+            (0x37, v) if !v.enable_pqr() => OpCode::Div64Imm,
+            (0x3f, v) if !v.enable_pqr() => OpCode::Div64Reg,
+            (0x47, _) => OpCode::Or64Imm,
+            (0x4f, _) => OpCode::Or64Reg,
+            (0x57, _) => OpCode::And64Imm,
+            (0x5f, _) => OpCode::And64Reg,
+            (0x67, _) => OpCode::Lsh64Imm,
+            (0x6f, _) => OpCode::Lsh64Reg,
+            (0x77, _) => OpCode::Rsh64Imm,
+            (0x7f, _) => OpCode::Rsh64Reg,
+            (0x87, v) if !v.disable_neg() => OpCode::Neg64,
+            (0x97, v) if !v.enable_pqr() => OpCode::Mod64Imm,
+            (0x9f, v) if !v.enable_pqr() => OpCode::Mod64Reg,
+            (0xa7, _) => OpCode::Xor64Imm,
+            (0xaf, _) => OpCode::Xor64Reg,
+            (0xb7, _) => OpCode::Mov64Imm,
+            (0xbf, _) => OpCode::Mov64Reg,
+            (0xc7, _) => OpCode::Arsh64Imm,
+            (0xcf, _) => OpCode::Arsh64Reg,
+            (0xf7, v) if v.disable_lddw() => OpCode::Hor64Imm,
+            (0x96, v) if v.enable_pqr() => OpCode::Lmul64Imm,
+            (0x9e, v) if v.enable_pqr() => OpCode::Lmul64Reg,
+            (0x36, v) if v.enable_pqr() => OpCode::Uhmul64Imm,
+            (0x3e, v) if v.enable_pqr() => OpCode::Uhmul64Reg,
+            (0x56, v) if v.enable_pqr() => OpCode::Udiv64Imm,
+            (0x5e, v) if v.enable_pqr() => OpCode::Udiv64Reg,
+            (0x76, v) if v.enable_pqr() => OpCode::Urem64Imm,
+            (0x7e, v) if v.enable_pqr() => OpCode::Urem64Reg,
+            (0xb6, v) if v.enable_pqr() => OpCode::Shmul64Imm,
+            (0xbe, v) if v.enable_pqr() => OpCode::Shmul64Reg,
+            (0xd6, v) if v.enable_pqr() => OpCode::Sdiv64Imm,
+            (0xde, v) if v.enable_pqr() => OpCode::Sdiv64Reg,
+            (0xf6, v) if v.enable_pqr() => OpCode::Srem64Imm,
+            (0xfe, v) if v.enable_pqr() => OpCode::Srem64Reg,
+            (0x05, _) => OpCode::Ja,
+            (0x15, _) => OpCode::JeqImm,
+            (0x1d, _) => OpCode::JeqReg,
+            (0x25, _) => OpCode::JgtImm,
+            (0x2d, _) => OpCode::JgtReg,
+            (0x35, _) => OpCode::JgeImm,
+            (0x3d, _) => OpCode::JgeReg,
+            (0xa5, _) => OpCode::JltImm,
+            (0xad, _) => OpCode::JltReg,
+            (0xb5, _) => OpCode::JleImm,
+            (0xbd, _) => OpCode::JleReg,
+            (0x45, _) => OpCode::JsetImm,
+            (0x4d, _) => OpCode::JsetReg,
+            (0x55, _) => OpCode::JneImm,
+            (0x5d, _) => OpCode::JneReg,
+            (0x65, _) => OpCode::JsgtImm,
+            (0x6d, _) => OpCode::JsgtReg,
+            (0x75, _) => OpCode::JsgeImm,
+            (0x7d, _) => OpCode::JsgeReg,
+            (0xc5, _) => OpCode::JsltImm,
+            (0xcd, _) => OpCode::JsltReg,
+            (0xd5, _) => OpCode::JsleImm,
+            (0xdd, _) => OpCode::JsleReg,
+            (0x85, _) => OpCode::Call,
+            (0x8d, _) => OpCode::Callx,
+            (0x95, v) if !v.static_syscalls() => OpCode::Exit,
+            (0x95, v) if v.static_syscalls() => OpCode::Syscall,
+            (0x9d, v) if v.static_syscalls() => OpCode::Return,
+            _ => return Err(EZBpfError::InvalidOpcode { offset: None, value }),
         })
     }
 }
 
+// The SBPFv0 encoding, or the SBPFv3 one for opcodes added since
 impl From<OpCode> for u8 {
     fn from(val: OpCode) -> Self {
-        val as u8
+        val.to_byte(SbpfVersion::V0)
+            .or(val.to_byte(SbpfVersion::V3))
+            .unwrap_or_default()
+    }
+}
+
+impl OpCode {
+    pub fn is_call(&self) -> bool {
+        matches!(self, OpCode::Call | OpCode::Callx | OpCode::Syscall)
+    }
+
+    pub fn is_jump(&self) -> bool {
+        *self == OpCode::Ja || self.is_conditional_jump()
+    }
+
+    /// Jumps taken depending on a comparison, which otherwise fall through to
+    /// the next instruction.
+    pub fn is_conditional_jump(&self) -> bool {
+        matches!(
+            self,
+            OpCode::JeqImm
+                | OpCode::JeqReg
+                | OpCode::JgtImm
+                | OpCode::JgtReg
+                | OpCode::JgeImm
+                | OpCode::JgeReg
+                | OpCode::JltImm
+                | OpCode::JltReg
+                | OpCode::JleImm
+                | OpCode::JleReg
+                | OpCode::JsetImm
+                | OpCode::JsetReg
+                | OpCode::JneImm
+                | OpCode::JneReg
+                | OpCode::JsgtImm
+                | OpCode::JsgtReg
+                | OpCode::JsgeImm
+                | OpCode::JsgeReg
+                | OpCode::JsltImm
+                | OpCode::JsltReg
+                | OpCode::JsleImm
+                | OpCode::JsleReg
+        )
+    }
+
+    /// Every opcode that exists in `version`.
+    pub fn all(version: SbpfVersion) -> impl Iterator<Item = OpCode> {
+        (0..=u8::MAX).filter_map(move |b| OpCode::try_from((b, version)).ok())
+    }
+
+    pub fn operands(&self) -> Operands {
+        match self {
+            // lddw takes up two slots, with the lower half of the immediate in
+            // the first and the upper half in the second
+            OpCode::Lddw => Operands::DstImm,
+            OpCode::Ldxb | OpCode::Ldxh | OpCode::Ldxw | OpCode::Ldxdw => Operands::Load,
+            OpCode::Stb | OpCode::Sth | OpCode::Stw | OpCode::Stdw => Operands::StoreImm,
+            OpCode::Stxb | OpCode::Stxh | OpCode::Stxw | OpCode::Stxdw => Operands::StoreReg,
+            OpCode::Neg32 | OpCode::Neg64 => Operands::Dst,
+            OpCode::Le | OpCode::Be => Operands::Endian,
+            OpCode::Add32Imm
+            | OpCode::Sub32Imm
+            | OpCode::Mul32Imm
+            | OpCode::Div32Imm
+            | OpCode::Or32Imm
+            | OpCode::And32Imm
+            | OpCode::Lsh32Imm
+            | OpCode::Rsh32Imm
+            | OpCode::Mod32Imm
+            | OpCode::Xor32Imm
+            | OpCode::Mov32Imm
+            | OpCode::Arsh32Imm
+            | OpCode::Lmul32Imm
+            | OpCode::Udiv32Imm
+            | OpCode::Urem32Imm
+            | OpCode::Sdiv32Imm
+            | OpCode::Srem32Imm
+            | OpCode::Add64Imm
+            | OpCode::Sub64Imm
+            | OpCode::Mul64Imm
+            | OpCode::Div64Imm
+            | OpCode::Or64Imm
+            | OpCode::And64Imm
+            | OpCode::Lsh64Imm
+            | OpCode::Rsh64Imm
+            | OpCode::Mod64Imm
+            | OpCode::Xor64Imm
+            | OpCode::Mov64Imm
+            | OpCode::Arsh64Imm
+            | OpCode::Hor64Imm
+            | OpCode::Lmul64Imm
+            | OpCode::Uhmul64Imm
+            | OpCode::Udiv64Imm
+            | OpCode::Urem64Imm
+            | OpCode::Shmul64Imm
+            | OpCode::Sdiv64Imm
+            | OpCode::Srem64Imm => Operands::DstImm,
+            OpCode::Add32Reg
+            | OpCode::Sub32Reg
+            | OpCode::Mul32Reg
+            | OpCode::Div32Reg
+            | OpCode::Or32Reg
+            | OpCode::And32Reg
+            | OpCode::Lsh32Reg
+            | OpCode::Rsh32Reg
+            | OpCode::Mod32Reg
+            | OpCode::Xor32Reg
+            | OpCode::Mov32Reg
+            | OpCode::Arsh32Reg
+            | OpCode::Lmul32Reg
+            | OpCode::Udiv32Reg
+            | OpCode::Urem32Reg
+            | OpCode::Sdiv32Reg
+            | OpCode::Srem32Reg
+            | OpCode::Add64Reg
+            | OpCode::Sub64Reg
+            | OpCode::Mul64Reg
+            | OpCode::Div64Reg
+            | OpCode::Or64Reg
+            | OpCode::And64Reg
+            | OpCode::Lsh64Reg
+            | OpCode::Rsh64Reg
+            | OpCode::Mod64Reg
+            | OpCode::Xor64Reg
+            | OpCode::Mov64Reg
+            | OpCode::Arsh64Reg
+            | OpCode::Lmul64Reg
+            | OpCode::Uhmul64Reg
+            | OpCode::Udiv64Reg
+            | OpCode::Urem64Reg
+            | OpCode::Shmul64Reg
+            | OpCode::Sdiv64Reg
+            | OpCode::Srem64Reg => Operands::DstSrc,
+            OpCode::Ja => Operands::Jump,
+            OpCode::JeqImm
+            | OpCode::JgtImm
+            | OpCode::JgeImm
+            | OpCode::JltImm
+            | OpCode::JleImm
+            | OpCode::JsetImm
+            | OpCode::JneImm
+            | OpCode::JsgtImm
+            | OpCode::JsgeImm
+            | OpCode::JsltImm
+            | OpCode::JsleImm => Operands::JumpImm,
+            OpCode::JeqReg
+            | OpCode::JgtReg
+            | OpCode::JgeReg
+            | OpCode::JltReg
+            | OpCode::JleReg
+            | OpCode::JsetReg
+            | OpCode::JneReg
+            | OpCode::JsgtReg
+            | OpCode::JsgeReg
+            | OpCode::JsltReg
+            | OpCode::JsleReg => Operands::JumpReg,
+            OpCode::Call | OpCode::Syscall => Operands::Call,
+            OpCode::Callx => Operands::CallReg,
+            OpCode::Exit | OpCode::Return => Operands::None,
+        }
+    }
+
+    /// Encodes the opcode for a particular SBPF version, or None if it doesn't
+    /// exist in that version. Only the memory instructions are encoded
+    /// differently between versions.
+    pub fn to_byte(&self, version: SbpfVersion) -> Option<u8> {
+        // Before and from SBPFv2 on
+        let (v0, v2) = match self {
+            OpCode::Lddw => (0x18, 0x18),
+            OpCode::Ldxb => (0x71, 0x2c),
+            OpCode::Ldxh => (0x69, 0x3c),
+            OpCode::Ldxw => (0x61, 0x8c),
+            OpCode::Ldxdw => (0x79, 0x9c),
+            OpCode::Stb => (0x72, 0x27),
+            OpCode::Sth => (0x6a, 0x37),
+            OpCode::Stw => (0x62, 0x87),
+            OpCode::Stdw => (0x7a, 0x97),
+            OpCode::Stxb => (0x73, 0x2f),
+            OpCode::Stxh => (0x6b, 0x3f),
+            OpCode::Stxw => (0x63, 0x8f),
+            OpCode::Stxdw => (0x7b, 0x9f),
+            OpCode::Add32Imm => (0x04, 0x04),
+            OpCode::Add32Reg => (0x0c, 0x0c),
+            OpCode::Sub32Imm => (0x14, 0x14),
+            OpCode::Sub32Reg => (0x1c, 0x1c),
+            OpCode::Mul32Imm => (0x24, 0x24),
+            OpCode::Mul32Reg => (0x2c, 0x2c),
+            OpCode::Div32Imm => (0x34, 0x34),
+            OpCode::Div32Reg => (0x3c, 0x3c),
+            OpCode::Or32Imm => (0x44, 0x44),
+            OpCode::Or32Reg => (0x4c, 0x4c),
+            OpCode::And32Imm => (0x54, 0x54),
+            OpCode::And32Reg => (0x5c, 0x5c),
+            OpCode::Lsh32Imm => (0x64, 0x64),
+            OpCode::Lsh32Reg => (0x6c, 0x6c),
+            OpCode::Rsh32Imm => (0x74, 0x74),
+            OpCode::Rsh32Reg => (0x7c, 0x7c),
+            OpCode::Neg32 => (0x84, 0x84),
+            OpCode::Mod32Imm => (0x94, 0x94),
+            OpCode::Mod32Reg => (0x9c, 0x9c),
+            OpCode::Xor32Imm => (0xa4, 0xa4),
+            OpCode::Xor32Reg => (0xac, 0xac),
+            OpCode::Mov32Imm => (0xb4, 0xb4),
+            OpCode::Mov32Reg => (0xbc, 0xbc),
+            OpCode::Arsh32Imm => (0xc4, 0xc4),
+            OpCode::Arsh32Reg => (0xcc, 0xcc),
+            OpCode::Lmul32Imm => (0x86, 0x86),
+            OpCode::Lmul32Reg => (0x8e, 0x8e),
+            OpCode::Udiv32Imm => (0x46, 0x46),
+            OpCode::Udiv32Reg => (0x4e, 0x4e),
+            OpCode::Urem32Imm => (0x66, 0x66),
+            OpCode::Urem32Reg => (0x6e, 0x6e),
+            OpCode::Sdiv32Imm => (0xc6, 0xc6),
+            OpCode::Sdiv32Reg => (0xce, 0xce),
+            OpCode::Srem32Imm => (0xe6, 0xe6),
+            OpCode::Srem32Reg => (0xee, 0xee),
+            OpCode::Le => (0xd4, 0xd4),
+            OpCode::Be => (0xdc, 0xdc),
+            OpCode::Add64Imm => (0x07, 0x07),
+            OpCode::Add64Reg => (0x0f, 0x0f),
+            OpCode::Sub64Imm => (0x17, 0x17),
+            OpCode::Sub64Reg => (0x1f, 0x1f),
+            OpCode::Mul64Imm => (0x27, 0x27),
+            OpCode::Mul64Reg => (0x2f, 0x2f),
+            OpCode::Div64Imm => (0x37, 0x37),
+            OpCode::Div64Reg => (0x3f, 0x3f),
+            OpCode::Or64Imm => (0x47, 0x47),
+            OpCode::Or64Reg => (0x4f, 0x4f),
+            OpCode::And64Imm => (0x57, 0x57),
+            OpCode::And64Reg => (0x5f, 0x5f),
+            OpCode::Lsh64Imm => (0x67, 0x67),
+            OpCode::Lsh64Reg => (0x6f, 0x6f),
+            OpCode::Rsh64Imm => (0x77, 0x77),
+            OpCode::Rsh64Reg => (0x7f, 0x7f),
+            OpCode::Neg64 => (0x87, 0x87),
+            OpCode::Mod64Imm => (0x97, 0x97),
+            OpCode::Mod64Reg => (0x9f, 0x9f),
+            OpCode::Xor64Imm => (0xa7, 0xa7),
+            OpCode::Xor64Reg => (0xaf, 0xaf),
+            OpCode::Mov64Imm => (0xb7, 0xb7),
+            OpCode::Mov64Reg => (0xbf, 0xbf),
+            OpCode::Arsh64Imm => (0xc7, 0xc7),
+            OpCode::Arsh64Reg => (0xcf, 0xcf),
+            OpCode::Hor64Imm => (0xf7, 0xf7),
+            OpCode::Lmul64Imm => (0x96, 0x96),
+            OpCode::Lmul64Reg => (0x9e, 0x9e),
+            OpCode::Uhmul64Imm => (0x36, 0x36),
+            OpCode::Uhmul64Reg => (0x3e, 0x3e),
+            OpCode::Udiv64Imm => (0x56, 0x56),
+            OpCode::Udiv64Reg => (0x5e, 0x5e),
+            OpCode::Urem64Imm => (0x76, 0x76),
+            OpCode::Urem64Reg => (0x7e, 0x7e),
+            OpCode::Shmul64Imm => (0xb6, 0xb6),
+            OpCode::Shmul64Reg => (0xbe, 0xbe),
+            OpCode::Sdiv64Imm => (0xd6, 0xd6),
+            OpCode::Sdiv64Reg => (0xde, 0xde),
+            OpCode::Srem64Imm => (0xf6, 0xf6),
+            OpCode::Srem64Reg => (0xfe, 0xfe),
+            OpCode::Ja => (0x05, 0x05),
+            OpCode::JeqImm => (0x15, 0x15),
+            OpCode::JeqReg => (0x1d, 0x1d),
+            OpCode::JgtImm => (0x25, 0x25),
+            OpCode::JgtReg => (0x2d, 0x2d),
+            OpCode::JgeImm => (0x35, 0x35),
+            OpCode::JgeReg => (0x3d, 0x3d),
+            OpCode::JltImm => (0xa5, 0xa5),
+            OpCode::JltReg => (0xad, 0xad),
+            OpCode::JleImm => (0xb5, 0xb5),
+            OpCode::JleReg => (0xbd, 0xbd),
+            OpCode::JsetImm => (0x45, 0x45),
+            OpCode::JsetReg => (0x4d, 0x4d),
+            OpCode::JneImm => (0x55, 0x55),
+            OpCode::JneReg => (0x5d, 0x5d),
+            OpCode::JsgtImm => (0x65, 0x65),
+            OpCode::JsgtReg => (0x6d, 0x6d),
+            OpCode::JsgeImm => (0x75, 0x75),
+            OpCode::JsgeReg => (0x7d, 0x7d),
+            OpCode::JsltImm => (0xc5, 0xc5),
+            OpCode::JsltReg => (0xcd, 0xcd),
+            OpCode::JsleImm => (0xd5, 0xd5),
+            OpCode::JsleReg => (0xdd, 0xdd),
+            OpCode::Call => (0x85, 0x85),
+            OpCode::Callx => (0x8d, 0x8d),
+            OpCode::Exit => (0x95, 0x95),
+            OpCode::Syscall => (0x95, 0x95),
+            OpCode::Return => (0x9d, 0x9d),
+        };
+        let b = if version.move_memory_instruction_classes() { v2 } else { v0 };
+        // An opcode removed or not yet added in this version either doesn't
+        // decode or decodes as something else, like exit and syscall
+        (OpCode::try_from((b, version)).ok()? == *self).then_some(b)
     }
 }
 
@@ -383,6 +717,8 @@ impl From<OpCode> for &str {
             OpCode::Call => "call",
             OpCode::Callx => "callx",
             OpCode::Exit => "exit",
+            OpCode::Syscall => "syscall",
+            OpCode::Return => "return",
         }
     }
 }
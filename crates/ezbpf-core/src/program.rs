@@ -1,76 +1,1580 @@
-use std::io::Cursor;
+use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    cursor::ELFCursor, elf_header::ELFHeader, errors::EZBpfError, program_header::ProgramHeader,
-    section_header::SectionHeader, section_header_entry::SectionHeaderEntry,
+    address_space::AddressSpace,
+    diagnostic::{Diagnostic, Recovery},
+    disassembler::{
+        call_target, known_registers, recursive_descent, slots, Call, CallKind, Item, Strategy,
+    },
+    dynamic::{
+        DynamicEntry, DynamicMismatch, DYNAMIC_ENTRY_SIZE, DT_REL, DT_RELENT, DT_RELSZ, DT_STRSZ,
+        DT_STRTAB, DT_SYMENT, DT_SYMTAB,
+    },
+    elf_header::{ELFHeader, ELF_HEADER_SIZE},
+    errors::EZBpfError,
+    murmur3::{hash_function, hash_symbol_name},
+    opcodes::OpCode,
+    program_header::{ProgramHeader, ProgramType},
+    program_ref::{is_segment_section, ProgramRef},
+    relocation::{Relocation, RelocationType, RELOCATION_SIZE},
+    sbpf_version::SbpfVersion,
+    section_header::{SectionHeader, SectionHeaderType},
+    section_header_entry::SectionHeaderEntry,
+    segment_map::SegmentMap,
+    string_table::StringTable,
+    symbol::{Symbol, SymbolType, SHN_UNDEF, SYMBOL_SIZE},
+    syscalls::{signature, SyscallRegistry, SyscallSignature},
+    validate::MAX_PROGRAM_SIZE,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Program {
     pub elf_header: ELFHeader,
+    #[serde(default)]
+    pub sbpf_version: SbpfVersion,
     pub program_headers: Vec<ProgramHeader>,
     pub section_headers: Vec<SectionHeader>,
     pub section_header_entries: Vec<SectionHeaderEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub symbols: Vec<Symbol>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub dynamic_symbols: Vec<Symbol>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub relocations: Vec<Relocation>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub dynamic: Vec<DynamicEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub diagnostics: Vec<Diagnostic>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub gaps: Vec<Gap>,
+}
+
+// Bytes of the file outside of the headers, header tables and sections, such
+// as non-zero padding or a header cut short by the end of the file, kept so
+// that to_bytes can put them back
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Gap {
+    pub offset: u64,   // Offset in the original file.
+    pub data: Vec<u8>, // Raw bytes, not all zero unless they end the file.
+}
+
+/// A program as written to JSON, with what each call instruction calls worked
+/// out from the rest of it.
+#[derive(Debug, Serialize)]
+pub struct ProgramJson<'a> {
+    #[serde(flatten)]
+    pub program: &'a Program,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<Call>,
 }
 
 impl Program {
     pub fn from_bytes(b: &[u8]) -> Result<Self, EZBpfError> {
-        let mut c = Cursor::new(b);
-        let elf_header = c.read_elf_header()?;
+        ProgramRef::from_bytes(b)?.try_into()
+    }
 
-        c.set_position(elf_header.e_phoff);
-        let program_headers = (0..elf_header.e_phnum)
-            .map(|_| c.read_program_header())
-            .collect::<Result<Vec<_>, _>>()?;
-        c.set_position(elf_header.e_shoff);
-        let section_headers = (0..elf_header.e_shnum)
-            .map(|_| c.read_section_header())
+    /// Parse a damaged or unusual program without giving up at the first
+    /// problem. Header tables end at the first header that can't be read,
+    /// unreadable tables and names are left empty and undecodable slots are
+    /// kept as data, with each problem and the raw bytes involved recorded in
+    /// `diagnostics`. Headers cut short by the end of the file are kept in
+    /// `gaps` and empty tables leave their sections alone, so such a program
+    /// still round-trips through `to_bytes`.
+    pub fn from_bytes_lenient(b: &[u8]) -> Result<Self, EZBpfError> {
+        Self::from_ref(ProgramRef::from_bytes_lenient(b)?, &mut Recovery::new(true))
+    }
+
+    fn from_ref(p: ProgramRef<'_>, recovery: &mut Recovery) -> Result<Self, EZBpfError> {
+        let section_header_entries = p
+            .sections
+            .iter()
+            .map(|s| {
+                SectionHeaderEntry::parse(
+                    s.label.to_string(),
+                    s.offset,
+                    s.data.to_vec(),
+                    p.sbpf_version,
+                    recovery,
+                )
+            })
             .collect::<Result<Vec<_>, _>>()?;
+        let mut diagnostics = p.diagnostics;
+        diagnostics.append(&mut recovery.diagnostics);
+        let mut program = Self {
+            elf_header: p.elf_header,
+            sbpf_version: p.sbpf_version,
+            program_headers: p.program_headers,
+            section_headers: p.section_headers,
+            section_header_entries,
+            symbols: p.symbols,
+            dynamic_symbols: p.dynamic_symbols,
+            relocations: p.relocations,
+            dynamic: p.dynamic,
+            diagnostics,
+            gaps: vec![],
+        };
+        // Diagnostics are raised before there is an address space to place them in
+        let space = program.address_space();
+        for d in program.diagnostics.iter_mut() {
+            d.address = d.offset.map(|offset| space.resolve_offset(offset));
+        }
+        program.gaps = gaps(p.bytes, &program.blocks(&program.encode_sections()?));
+        Ok(program)
+    }
+
+    /// Serialize the program back into an ELF file. Headers, tables and section
+    /// contents are written at their original offsets, so an unmodified program
+    /// round-trips byte for byte. Symbols, relocations, the dynamic array and
+    /// decoded instructions are encoded back into the sections they were read
+    /// from, and win over the data of those sections when the two disagree, so
+    /// clear the items of a section to edit its data directly.
+    /// Names are not written, only the st_name and r_sym they were resolved
+    /// from, and an empty table leaves its section alone so that one which
+    /// could not be read doesn't wipe it. When a section changes size, the file
+    /// contents after it are shifted along and the section headers, program
+    /// headers and header table offsets are updated to match. Virtual addresses
+    /// are left alone. Fails without allocating the file when it would be larger
+    /// than `MAX_PROGRAM_SIZE`, such as when a header table offset was set to
+    /// something huge.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EZBpfError> {
+        let layout = self.layout()?;
+        if layout.len > MAX_PROGRAM_SIZE {
+            return Err(EZBpfError::ProgramTooLarge { len: layout.len });
+        }
+        let mut b = vec![0u8; layout.len as usize];
+        let mut write = |offset: u64, data: &[u8]| {
+            let err = EZBpfError::InvalidDataLength {
+                offset: Some(offset),
+                len: data.len() as u64,
+            };
+            let start = usize::try_from(offset).map_err(|_| err.clone())?;
+            let end = start.checked_add(data.len()).ok_or(err.clone())?;
+            b.get_mut(start..end).ok_or(err)?.copy_from_slice(data);
+            Ok::<_, EZBpfError>(())
+        };
+        write(0, &layout.elf_header.to_bytes())?;
+        for block in &layout.blocks {
+            match block.kind {
+                BlockKind::ProgramHeaders => write(
+                    block.new_offset,
+                    &layout
+                        .program_headers
+                        .iter()
+                        .flat_map(|h| h.to_bytes())
+                        .collect::<Vec<u8>>(),
+                )?,
+                BlockKind::SectionHeaders => write(
+                    block.new_offset,
+                    &layout
+                        .section_headers
+                        .iter()
+                        .flat_map(|h| h.to_bytes())
+                        .collect::<Vec<u8>>(),
+                )?,
+                BlockKind::Section(i) | BlockKind::Segment(i) => {
+                    write(block.new_offset, &layout.sections[i])?
+                }
+                BlockKind::Gap(i) => write(block.new_offset, &self.gaps[i].data)?,
+            }
+        }
+        Ok(b)
+    }
+
+    /// Size of the file to_bytes would write, worked out without writing it.
+    pub fn file_size(&self) -> Result<u64, EZBpfError> {
+        Ok(self.layout()?.len)
+    }
+
+    // Where to_bytes puts everything and the headers updated to match, worked
+    // out without writing the file
+    fn layout(&self) -> Result<Layout, EZBpfError> {
+        let mut elf_header = self.elf_header.clone();
+        let mut program_headers = self.program_headers.clone();
+        let mut section_headers = self.section_headers.clone();
+        let sections = self.encode_sections()?;
+        let mut blocks = self.blocks(&sections);
+        for (i, gap) in self.gaps.iter().enumerate() {
+            let size = gap.data.len() as u64;
+            blocks.push(Block {
+                kind: BlockKind::Gap(i),
+                offset: gap.offset,
+                size,
+                new_offset: 0,
+                new_size: size,
+                align: 1,
+            });
+        }
+        blocks.sort_by_key(|b| b.offset);
+
+        // Only realign blocks once something before them has moved, so files
+        // with unusual alignment are still reproduced exactly
+        let mut shift: i64 = 0;
+        for b in blocks.iter_mut() {
+            let offset = b.offset.saturating_add_signed(shift);
+            b.new_offset = if shift != 0 && b.align > 1 {
+                offset.checked_next_multiple_of(b.align).unwrap_or(offset)
+            } else {
+                offset
+            };
+            shift = b.new_end() as i64 - b.end() as i64;
+        }
+
+        for b in &blocks {
+            match b.kind {
+                BlockKind::ProgramHeaders => elf_header.e_phoff = b.new_offset,
+                BlockKind::SectionHeaders => elf_header.e_shoff = b.new_offset,
+                BlockKind::Section(i) => {
+                    section_headers[i].sh_offset = b.new_offset;
+                    section_headers[i].sh_size = b.new_size;
+                }
+                BlockKind::Segment(_) | BlockKind::Gap(_) => {}
+            }
+        }
+        for h in section_headers
+            .iter_mut()
+            .filter(|h| h.sh_type == SectionHeaderType::SHT_NOBITS)
+        {
+            h.sh_offset = Block::map_offset(&blocks, h.sh_offset);
+        }
+        for h in program_headers.iter_mut() {
+            let start = Block::map_offset(&blocks, h.p_offset);
+            let end = match h.p_filesz {
+                0 => start,
+                filesz => Block::map_end(&blocks, h.p_offset.saturating_add(filesz)),
+            };
+            let filesz = end.saturating_sub(start);
+            h.p_memsz = h.p_memsz.saturating_add(filesz).saturating_sub(h.p_filesz);
+            h.p_offset = start;
+            h.p_filesz = filesz;
+        }
 
-        let shstrndx = &section_headers[elf_header.e_shstrndx as usize];
-        let shstrndx_value = b
-            [shstrndx.sh_offset as usize..shstrndx.sh_offset as usize + shstrndx.sh_size as usize]
-            .to_vec();
-
-        let mut indices: Vec<u32> = section_headers.iter().map(|h| h.sh_name).collect();
-        indices.push(shstrndx.sh_size as u32);
-        indices.sort_unstable();
-    
-        let section_header_entries = section_headers.iter().map(|s| {
-            let current_offset = s.sh_name as usize;
-            let next_index = indices.binary_search(&s.sh_name).unwrap() + 1 as usize;
-            let next_offset = *indices.get(next_index).ok_or(EZBpfError::InvalidString)? as usize;
-
-            let label = String::from_utf8(
-                shstrndx_value[current_offset..next_offset].to_vec(),
-            ).unwrap_or("default".to_string());
-            let data = b[s.sh_offset as usize..s.sh_offset as usize + s.sh_size as usize].to_vec();
-
-            SectionHeaderEntry::new(label, s.sh_offset as usize, data)
-        }).collect::<Result<Vec<_>, _>>()?;
-
-        Ok(Self {
+        let len = blocks
+            .iter()
+            .map(Block::new_end)
+            .max()
+            .unwrap_or(0)
+            .max(elf_header.e_ehsize as u64)
+            .max(ELF_HEADER_SIZE);
+        Ok(Layout {
             elf_header,
             program_headers,
             section_headers,
-            section_header_entries,
+            sections,
+            blocks,
+            len,
+        })
+    }
+
+    // The parts of the file written by to_bytes other than the ELF header and
+    // gaps, where they were read from and how big they are now
+    fn blocks(&self, sections: &[Vec<u8>]) -> Vec<Block> {
+        // Header tables are sized by what is actually written rather than by
+        // e_phentsize and e_shentsize, which a malformed file can set to anything
+        let mut blocks = vec![];
+        if !self.program_headers.is_empty() {
+            let size = self.program_headers.iter().map(|h| h.to_bytes().len() as u64).sum();
+            blocks.push(Block {
+                kind: BlockKind::ProgramHeaders,
+                offset: self.elf_header.e_phoff,
+                size,
+                new_offset: 0,
+                new_size: size,
+                align: 8,
+            });
+        }
+        if !self.section_headers.is_empty() {
+            let size = self.section_headers.iter().map(|h| h.to_bytes().len() as u64).sum();
+            blocks.push(Block {
+                kind: BlockKind::SectionHeaders,
+                offset: self.elf_header.e_shoff,
+                size,
+                new_offset: 0,
+                new_size: size,
+                align: 8,
+            });
+        }
+        for (i, (h, data)) in self.section_headers.iter().zip(sections).enumerate() {
+            if h.sh_type == SectionHeaderType::SHT_NULL || h.sh_type == SectionHeaderType::SHT_NOBITS
+            {
+                continue;
+            }
+            blocks.push(Block {
+                kind: BlockKind::Section(i),
+                offset: h.sh_offset,
+                size: h.sh_size,
+                new_offset: 0,
+                new_size: data.len() as u64,
+                align: h.sh_addralign,
+            });
+        }
+        // Without section headers the contents of the file are held by the
+        // loadable segments instead
+        if self.section_headers.is_empty() {
+            for (i, (h, data)) in self
+                .program_headers
+                .iter()
+                .filter(|h| is_segment_section(h))
+                .zip(sections)
+                .enumerate()
+            {
+                blocks.push(Block {
+                    kind: BlockKind::Segment(i),
+                    offset: h.p_offset,
+                    size: h.p_filesz,
+                    new_offset: 0,
+                    new_size: data.len() as u64,
+                    align: h.p_align,
+                });
+            }
+        }
+        blocks
+    }
+
+    // The data of every section, with the symbols, relocations, dynamic array
+    // and instructions written back into the sections they were read from
+    fn encode_sections(&self) -> Result<Vec<Vec<u8>>, EZBpfError> {
+        let mut sections = self
+            .section_header_entries
+            .iter()
+            .map(|e| e.encode_items(self.sbpf_version))
+            .collect::<Result<Vec<_>, _>>()?;
+        let index = |sh_type: SectionHeaderType| {
+            self.section_headers.iter().position(|h| h.sh_type == sh_type)
+        };
+        for (sh_type, symbols) in [
+            (SectionHeaderType::SHT_SYMTAB, &self.symbols),
+            (SectionHeaderType::SHT_DYNSYM, &self.dynamic_symbols),
+        ] {
+            if let Some(data) = index(sh_type).and_then(|i| sections.get_mut(i)) {
+                if !symbols.is_empty() {
+                    *data = symbols.iter().flat_map(Symbol::to_bytes).collect();
+                }
+            }
+        }
+
+        // Relocations are read from every SHT_REL section in turn, so each one
+        // gets back as many as it held, with any left over going to the last
+        let rel: Vec<usize> = self
+            .section_headers
+            .iter()
+            .enumerate()
+            .filter(|(i, h)| h.sh_type == SectionHeaderType::SHT_REL && *i < sections.len())
+            .map(|(i, _)| i)
+            .collect();
+        if !self.relocations.is_empty() {
+            let mut relocations = &self.relocations[..];
+            for (n, i) in rel.iter().enumerate() {
+                let count = if n + 1 == rel.len() {
+                    relocations.len()
+                } else {
+                    (sections[*i].len() / RELOCATION_SIZE as usize).min(relocations.len())
+                };
+                let (these, rest) = relocations.split_at(count);
+                sections[*i] = these.iter().flat_map(Relocation::to_bytes).collect();
+                relocations = rest;
+            }
+        }
+
+        // The dynamic array is read from PT_DYNAMIC when there is one, which
+        // may start part way into a section
+        let dynamic = self
+            .program_headers
+            .iter()
+            .find(|h| h.p_type == ProgramType::PT_DYNAMIC)
+            .map(|h| h.p_offset)
+            .or_else(|| {
+                let i = index(SectionHeaderType::SHT_DYNAMIC)?;
+                Some(self.section_headers[i].sh_offset)
+            });
+        let section = dynamic.and_then(|offset| {
+            self.section_header_entries.iter().enumerate().find_map(|(i, e)| {
+                let start = offset.checked_sub(e.offset as u64)? as usize;
+                (start < sections[i].len()).then_some((i, start))
+            })
+        });
+        if let (Some((i, start)), false) = (section, self.dynamic.is_empty()) {
+            let data = &mut sections[i];
+            // The entries it held, up to and including DT_NULL
+            let mut end = start;
+            for e in data[start..].chunks_exact(DYNAMIC_ENTRY_SIZE as usize) {
+                end += e.len();
+                if DynamicEntry::from_bytes(e).is_ok_and(|e| e == DynamicEntry::DT_NULL) {
+                    break;
+                }
+            }
+            let entries: Vec<u8> = self.dynamic.iter().flat_map(DynamicEntry::to_bytes).collect();
+            data.splice(start..end, entries);
+        }
+        Ok(sections)
+    }
+
+    /// Entries of the `.symtab` section, if the program has not been stripped.
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Entries of the `.dynsym` section.
+    pub fn dynamic_symbols(&self) -> &[Symbol] {
+        &self.dynamic_symbols
+    }
+
+    /// Entries of the `.rel.dyn` section.
+    pub fn relocations(&self) -> &[Relocation] {
+        &self.relocations
+    }
+
+    /// The relocation applied to the instruction or data at virtual address
+    /// `offset`, if any.
+    pub fn relocation_at(&self, offset: u64) -> Option<&Relocation> {
+        self.relocations.iter().find(|r| r.r_offset == offset)
+    }
+
+    /// Relocations keyed by the virtual address they apply to, for looking up
+    /// many instructions at once. Like `relocation_at`, the first relocation
+    /// at an address wins.
+    pub fn relocations_by_offset(&self) -> HashMap<u64, &Relocation> {
+        let mut relocations = HashMap::with_capacity(self.relocations.len());
+        for r in &self.relocations {
+            relocations.entry(r.r_offset).or_insert(r);
+        }
+        relocations
+    }
+
+    /// Entries of the dynamic array, read from PT_DYNAMIC like the loader does.
+    pub fn dynamic(&self) -> &[DynamicEntry] {
+        &self.dynamic
+    }
+
+    /// Resolve a string referenced from the dynamic array, such as the value of
+    /// DT_NEEDED, through the string table at DT_STRTAB.
+    pub fn dynamic_str(&self, offset: u64) -> Result<&str, EZBpfError> {
+        let err = EZBpfError::InvalidString { offset: None, value: offset };
+        let strtab = self
+            .dynamic
+            .iter()
+            .find_map(|e| match e {
+                DynamicEntry::DT_STRTAB(addr) => Some(*addr),
+                _ => None,
+            })
+            .ok_or(err.clone())?;
+        let entry = self
+            .section_headers
+            .iter()
+            .zip(&self.section_header_entries)
+            .find(|(h, _)| h.sh_type == SectionHeaderType::SHT_STRTAB && h.sh_addr == strtab)
+            .map(|(_, e)| e)
+            .ok_or(err.clone())?;
+        let offset = u32::try_from(offset).map_err(|_| err.at(entry.offset as u64))?;
+        StringTable::new(&entry.data)
+            .get(offset)
+            .map_err(|e| e.shift(entry.offset as u64))
+    }
+
+    /// Cross-check the addresses and sizes in the dynamic array against the
+    /// .rel.dyn, .dynsym and .dynstr section headers.
+    pub fn check_dynamic(&self) -> Vec<DynamicMismatch> {
+        let find_section = |sh_type: SectionHeaderType| {
+            self.section_headers.iter().find(|h| h.sh_type == sh_type)
+        };
+        let rel = find_section(SectionHeaderType::SHT_REL);
+        let dynsym = find_section(SectionHeaderType::SHT_DYNSYM);
+        let dynstr = dynsym.and_then(|h| self.section_headers.get(h.sh_link as usize));
+
+        let expected = [
+            (DT_REL, rel.map(|h| h.sh_addr)),
+            (DT_RELSZ, rel.map(|h| h.sh_size)),
+            (DT_RELENT, rel.map(|_| RELOCATION_SIZE)),
+            (DT_SYMTAB, dynsym.map(|h| h.sh_addr)),
+            (DT_SYMENT, dynsym.map(|_| SYMBOL_SIZE)),
+            (DT_STRTAB, dynstr.map(|h| h.sh_addr)),
+            (DT_STRSZ, dynstr.map(|h| h.sh_size)),
+        ];
+        expected
+            .into_iter()
+            .filter_map(|(tag, section)| {
+                let dynamic = self.dynamic.iter().find(|e| e.tag() == tag).map(|e| e.value());
+                if dynamic == section {
+                    return None;
+                }
+                Some(DynamicMismatch {
+                    tag,
+                    dynamic,
+                    section,
+                })
+            })
+            .collect()
+    }
+
+    /// Translation between file offsets, virtual addresses, VM addresses and
+    /// section and instruction indices.
+    pub fn address_space(&self) -> AddressSpace {
+        AddressSpace::new(self)
+    }
+
+    /// The sections contained in each program header.
+    pub fn segment_map(&self) -> SegmentMap {
+        SegmentMap::new(&self.program_headers, &self.section_headers)
+    }
+
+    // Addresses control flow can enter the program at: the entrypoint,
+    // function symbols and the targets of relocations
+    fn code_entries(&self, space: &AddressSpace) -> Vec<(usize, usize)> {
+        let mut vaddrs = vec![self.elf_header.e_entry];
+        let defined = |s: &&Symbol| s.st_shndx != SHN_UNDEF;
+        vaddrs.extend(
+            self.symbols
+                .iter()
+                .chain(&self.dynamic_symbols)
+                .filter(defined)
+                .filter(|s| s.st_type == SymbolType::STT_FUNC)
+                .map(|s| s.st_value),
+        );
+        for r in &self.relocations {
+            let symbol = self.dynamic_symbols.get(r.r_sym as usize).filter(defined);
+            match (r.r_sym, symbol) {
+                (0, _) => {
+                    // Relative relocations of lddw hold the address in the immediate
+                    let lddw = space
+                        .vaddr_to_offset(r.r_offset)
+                        .and_then(|offset| space.ix_at(offset))
+                        .and_then(|(section, ix)| self.section_header_entries[section].items.get(ix))
+                        .and_then(Item::ix)
+                        .filter(|ix| ix.op == OpCode::Lddw);
+                    vaddrs.extend(lddw.map(|ix| ix.imm as u64));
+                }
+                (_, Some(s)) => vaddrs.push(s.st_value),
+                (_, None) => {}
+            }
+        }
+        let mut entries: Vec<(usize, usize)> = vaddrs
+            .into_iter()
+            .filter_map(|vaddr| space.vaddr_to_offset(vaddr))
+            .filter_map(|offset| {
+                space
+                    .ix_at(offset)
+                    .filter(|(section, ix)| space.ix_offset(*section, *ix) == Some(offset))
+            })
+            .collect();
+        entries.sort_unstable();
+        entries.dedup();
+        entries
+    }
+
+    /// Which items of each section are code reached by following control flow
+    /// from the entrypoint, function symbols and relocation targets. Anything
+    /// else may be data or padding, even when it decodes as an instruction.
+    pub fn recursive_descent(&self) -> Vec<Vec<bool>> {
+        let entries = self.code_entries(&self.address_space());
+        self.section_header_entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                let starts: Vec<usize> = entries
+                    .iter()
+                    .filter(|(section, _)| *section == i)
+                    .map(|(_, ix)| *ix)
+                    .collect();
+                recursive_descent(&e.items, &starts, self.sbpf_version)
+            })
+            .collect()
+    }
+
+    pub fn to_asm(&self) -> Result<String, EZBpfError> {
+        self.to_asm_with(Strategy::LinearSweep, &SyscallRegistry::default())
+    }
+
+    /// Disassemble the program, telling code from data with `strategy` and
+    /// naming calls to any of `syscalls`. Slots that aren't code are written
+    /// out as data.
+    pub fn to_asm_with(
+        &self,
+        strategy: Strategy,
+        syscalls: &SyscallRegistry,
+    ) -> Result<String, EZBpfError> {
+        let space = self.address_space();
+        let reached = match strategy {
+            Strategy::LinearSweep => None,
+            Strategy::RecursiveDescent => Some(self.recursive_descent()),
+        };
+        let calls = self.classify_calls(syscalls);
+        let relocations = self.relocations_by_offset();
+        // Called functions without a symbol are named like one, so that jumps
+        // to them share the name
+        let mut functions = self.functions(&space);
+        functions.extend(calls.iter().filter_map(|c| {
+            c.target.map(|target| (c.section, target, 0, c.name.clone()))
+        }));
+        let mut lines = vec![];
+        for (i, e) in self.section_header_entries.iter().enumerate() {
+            let is_code = |j: usize| reached.as_ref().is_none_or(|r| r[i][j]);
+            let pcs = slots(&e.items);
+            let calls: HashMap<usize, &Call> = calls
+                .iter()
+                .filter(|c| c.section == i)
+                .map(|c| (c.pc, c))
+                .collect();
+            // Only jumps and calls landing on the start of an instruction get
+            // a label
+            let targets: HashSet<usize> = e
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| is_code(*j))
+                .filter_map(|(j, item)| {
+                    let ix = item.ix()?;
+                    ix.jump_target(pcs[j])
+                        .or_else(|| calls.get(&pcs[j]).and_then(|c| c.target))
+                })
+                .filter(|target| pcs.binary_search(target).is_ok())
+                .collect();
+            // The loader rewrites what these lddw load
+            let relocated: HashSet<usize> = pcs
+                .iter()
+                .enumerate()
+                .filter(|(_, pc)| {
+                    space
+                        .offset_to_vaddr((e.offset + *pc * 8) as u64)
+                        .and_then(|vaddr| relocations.get(&vaddr))
+                        .is_some_and(|r| {
+                            matches!(
+                                r.r_type,
+                                RelocationType::R_BPF_64_64 | RelocationType::R_BPF_64_RELATIVE
+                            )
+                        })
+                })
+                .map(|(j, _)| j)
+                .collect();
+            let known = known_registers(&e.items, &relocated, self.sbpf_version);
+            let label = |pc: usize| {
+                function_label(&functions, i, pc).unwrap_or_else(|| format!("lbb_{}", pc))
+            };
+
+            let mut position = 0;
+            for (j, item) in e.items.iter().enumerate() {
+                let offset = (e.offset + position) as u64;
+                // Items edited without updating the data no longer line up with
+                // it, in which case the item itself is written out
+                let data = e.data.get(position..position + item.size());
+                position += item.size();
+                if targets.contains(&pcs[j]) {
+                    lines.push(format!("{}:", label(pcs[j])));
+                }
+                if !is_code(j) {
+                    let data = match data {
+                        Some(data) => data.to_vec(),
+                        None => item.to_bytes(self.sbpf_version)?,
+                    };
+                    for quad in data.chunks(8) {
+                        lines.push(Item::Data(quad.to_vec()).to_asm(self.sbpf_version)?);
+                    }
+                    continue;
+                }
+                let relocation = space
+                    .offset_to_vaddr(offset)
+                    .and_then(|vaddr| relocations.get(&vaddr).copied());
+                let call_target = calls
+                    .get(&pcs[j])
+                    .and_then(|c| c.target)
+                    .filter(|target| targets.contains(target));
+                let syscall = calls
+                    .get(&pcs[j])
+                    .filter(|c| c.kind == CallKind::Syscall && !c.name.is_empty());
+                let line = match (item, relocation) {
+                    (Item::Ix(ix), Some(r)) => r.to_asm(ix, self.sbpf_version)?,
+                    (Item::Ix(ix), None) => match (ix.jump_target(pcs[j]), call_target, syscall) {
+                        (Some(target), _, _) if targets.contains(&target) => {
+                            ix.to_asm_with_label(self.sbpf_version, &label(target))?
+                        }
+                        (_, Some(target), _) => format!("{} {}", ix.op, label(target)),
+                        (_, _, Some(c)) => format!("{} {}", ix.op, c.name),
+                        _ => ix.to_asm(self.sbpf_version)?,
+                    },
+                    (Item::Data(_), _) => item.to_asm(self.sbpf_version)?,
+                };
+                let signature = syscall.and_then(|c| signature(&c.name));
+                lines.push(match signature {
+                    Some(signature) => format!("{} // {}", line, syscall_args(signature, &known[j])),
+                    None => line,
+                });
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    pub fn to_json(&self, syscalls: &SyscallRegistry) -> ProgramJson<'_> {
+        ProgramJson {
+            program: self,
+            calls: self.classify_calls(syscalls),
+        }
+    }
+
+    /// Work out what each call instruction calls. Calls within the program are
+    /// named after the function symbol at their target, or otherwise fn_
+    /// followed by the address of the target, and syscalls after the one in
+    /// `syscalls` they call.
+    pub fn classify_calls(&self, syscalls: &SyscallRegistry) -> Vec<Call> {
+        let space = self.address_space();
+        let functions = self.functions(&space);
+        let version = self.sbpf_version;
+        let entrypoint = space
+            .vaddr_to_offset(self.elf_header.e_entry)
+            .and_then(|offset| space.ix_at(offset));
+        let relocations = self.relocations_by_offset();
+        let mut calls = vec![];
+        for (i, e) in self.section_header_entries.iter().enumerate() {
+            let pcs = slots(&e.items);
+            if !e.items.iter().any(|item| item.ix().is_some_and(|ix| ix.op.is_call())) {
+                continue;
+            }
+            // Until static syscalls the loader hashes the slot of each function,
+            // except for the entrypoint which is hashed by name
+            let mut hashes: HashMap<u32, usize> =
+                pcs.iter().map(|pc| (hash_function(*pc), *pc)).collect();
+            if let Some((_, j)) = entrypoint.filter(|(section, _)| *section == i) {
+                hashes.insert(hash_symbol_name(b"entrypoint"), pcs[j]);
+            }
+            let name = |target: usize| {
+                function_label(&functions, i, target).unwrap_or_else(|| {
+                    let offset = (e.offset + target * 8) as u64;
+                    format!("fn_{:#x}", space.offset_to_vaddr(offset).unwrap_or(offset))
+                })
+            };
+            for (j, item) in e.items.iter().enumerate() {
+                let Some(ix) = item.ix() else {
+                    continue;
+                };
+                let pc = pcs[j];
+                let offset = (e.offset + pc * 8) as u64;
+                let relocation = space
+                    .offset_to_vaddr(offset)
+                    .and_then(|vaddr| relocations.get(&vaddr).copied())
+                    .filter(|r| r.r_type == RelocationType::R_BPF_64_32);
+                let (kind, target, name) = match (&ix.op, relocation) {
+                    (OpCode::Callx, _) => (CallKind::Register, None, String::new()),
+                    (OpCode::Syscall, _) => (
+                        CallKind::Syscall,
+                        None,
+                        syscalls.name(ix.imm as u32).unwrap_or_default().to_string(),
+                    ),
+                    // Like the loader, a relocation against a defined function
+                    // is a call within the program and any other is a syscall
+                    (OpCode::Call, Some(r)) => {
+                        let function = self
+                            .dynamic_symbols
+                            .get(r.r_sym as usize)
+                            .filter(|s| s.st_type == SymbolType::STT_FUNC && s.st_value != 0);
+                        match function {
+                            Some(s) => {
+                                let target = space
+                                    .vaddr_to_offset(s.st_value)
+                                    .filter(|o| space.section_at(*o) == Some(i))
+                                    .map(|o| (o - e.offset as u64) as usize / 8);
+                                (CallKind::Relocated, target, r.symbol.clone())
+                            }
+                            None => (CallKind::Syscall, None, r.symbol.clone()),
+                        }
+                    }
+                    (OpCode::Call, None) => {
+                        let relative = call_target(ix, pc, version)
+                            .filter(|target| pcs.binary_search(target).is_ok());
+                        let hashed = if version.static_syscalls() || ix.imm == -1 {
+                            None
+                        } else {
+                            hashes.get(&(ix.imm as u32)).copied()
+                        };
+                        let syscall = if version.static_syscalls() {
+                            None
+                        } else {
+                            syscalls.name(ix.imm as u32).map(str::to_string)
+                        };
+                        match (relative, hashed, syscall) {
+                            (Some(target), _, _) => (CallKind::Relative, Some(target), name(target)),
+                            (None, Some(target), _) => (CallKind::Hashed, Some(target), name(target)),
+                            (None, None, Some(name)) => (CallKind::Syscall, None, name),
+                            (None, None, None) => (CallKind::Unresolved, None, String::new()),
+                        }
+                    }
+                    _ => continue,
+                };
+                calls.push(Call {
+                    section: i,
+                    pc,
+                    kind,
+                    target,
+                    name,
+                });
+            }
+        }
+        calls
+    }
+
+    // Defined function symbols as their section, first slot, number of slots
+    // and name
+    fn functions(&self, space: &AddressSpace) -> Vec<(usize, usize, usize, String)> {
+        self.symbols
+            .iter()
+            .chain(&self.dynamic_symbols)
+            .filter(|s| s.st_type == SymbolType::STT_FUNC && s.st_shndx != SHN_UNDEF)
+            .filter(|s| !s.name.is_empty())
+            .filter_map(|s| {
+                let offset = space.vaddr_to_offset(s.st_value)?;
+                let section = space.section_at(offset)?;
+                let start = offset - self.section_header_entries[section].offset as u64;
+                Some((section, start as usize / 8, s.st_size as usize / 8, s.name.clone()))
+            })
+            .collect()
+    }
+}
+
+// The arguments of a syscall with the registers they are passed in, and
+// their values where known
+fn syscall_args(signature: &SyscallSignature, registers: &[Option<u64>; 11]) -> String {
+    let args: Vec<String> = signature
+        .args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            let value = match (registers[i + 1], arg.ty.starts_with('*')) {
+                (Some(v), true) => format!(" = {:#x}", v),
+                (Some(v), false) => format!(" = {}", v),
+                (None, _) => String::new(),
+            };
+            format!("r{} {}: {}{}", i + 1, arg.name, arg.ty, value)
         })
+        .collect();
+    if args.is_empty() {
+        "no arguments".to_string()
+    } else {
+        args.join(", ")
+    }
+}
+
+// Names a slot after the function containing it, with the distance in bytes
+// from the start of the function
+fn function_label(
+    functions: &[(usize, usize, usize, String)],
+    section: usize,
+    pc: usize,
+) -> Option<String> {
+    let function = functions.iter().find(|(s, start, len, _)| {
+        *s == section && (pc == *start || (pc > *start && pc < start + len))
+    });
+    match function {
+        Some((_, start, _, name)) if pc == *start => Some(name.clone()),
+        Some((_, start, _, name)) => Some(format!("{}+{:#x}", name, (pc - start) * 8)),
+        None => None,
+    }
+}
+
+impl TryFrom<ProgramRef<'_>> for Program {
+    type Error = EZBpfError;
+
+    fn try_from(p: ProgramRef<'_>) -> Result<Self, Self::Error> {
+        Self::from_ref(p, &mut Recovery::new(false))
+    }
+}
+
+enum BlockKind {
+    ProgramHeaders,
+    SectionHeaders,
+    Section(usize),
+    Segment(usize),
+    Gap(usize),
+}
+
+// The runs of a file not covered by its ELF header or any block, leaving out
+// those that are all zeros since to_bytes zero fills anyway
+fn gaps(b: &[u8], blocks: &[Block]) -> Vec<Gap> {
+    let mut covered: Vec<(u64, u64)> = blocks.iter().map(|b| (b.offset, b.end())).collect();
+    covered.push((0, ELF_HEADER_SIZE));
+    covered.sort();
+    let mut gaps = vec![];
+    let mut position = 0;
+    for (start, end) in covered.into_iter().chain([(u64::MAX, u64::MAX)]) {
+        let start = start.min(b.len() as u64);
+        if start > position {
+            let data = &b[position as usize..start as usize];
+            // The end of the file is kept even when it is zero, so that
+            // to_bytes writes a file of the same length
+            if data.iter().any(|b| *b != 0) || start == b.len() as u64 {
+                gaps.push(Gap {
+                    offset: position,
+                    data: data.to_vec(),
+                });
+            }
+        }
+        position = position.max(end.min(b.len() as u64));
     }
+    gaps
 }
 
+// The file written by Program::to_bytes
+struct Layout {
+    elf_header: ELFHeader,               // With the header table offsets updated.
+    program_headers: Vec<ProgramHeader>, // Moved and resized along with their contents.
+    section_headers: Vec<SectionHeader>, // Moved and resized along with their contents.
+    sections: Vec<Vec<u8>>,              // Encoded contents of each section header entry.
+    blocks: Vec<Block>,                  // Everything after the ELF header, in file order.
+    len: u64,                            // Size of the file.
+}
+
+// A contiguous run of the file written by Program::to_bytes, with its original
+// and updated position
+struct Block {
+    kind: BlockKind,
+    offset: u64,
+    size: u64,
+    new_offset: u64,
+    new_size: u64,
+    align: u64,
+}
+
+impl Block {
+    fn end(&self) -> u64 {
+        self.offset.saturating_add(self.size)
+    }
+
+    fn new_end(&self) -> u64 {
+        self.new_offset.saturating_add(self.new_size)
+    }
+
+    // Where a file offset ends up once every block has been laid out
+    fn map_offset(blocks: &[Block], offset: u64) -> u64 {
+        match blocks.iter().rev().find(|b| b.offset <= offset) {
+            Some(b) if offset <= b.end() => b.new_offset + (offset - b.offset).min(b.new_size),
+            Some(b) => offset.saturating_add(b.new_end()).saturating_sub(b.end()),
+            None => offset,
+        }
+    }
+
+    // Like map_offset, but an offset at a block boundary belongs to the block
+    // ending there rather than the one starting there, and grows or shrinks with it
+    fn map_end(blocks: &[Block], offset: u64) -> u64 {
+        match blocks.iter().rev().find(|b| b.offset < offset) {
+            Some(b) if offset == b.end() => b.new_end(),
+            Some(b) if offset < b.end() => b.new_offset + (offset - b.offset).min(b.new_size),
+            Some(b) => offset.saturating_add(b.new_end()).saturating_sub(b.end()),
+            None => offset,
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use hex_literal::hex;
 
-    use crate::program::Program;
+    use crate::{
+        address_space::MM_PROGRAM_START,
+        fixtures::{
+            BSS_SECTION, DATA_SECTION, RELATIVE_CALL, RELOC_64_64, STRICT_HEADER,
+            SYSCALL_RELOC_64_32,
+        },
+        diagnostic::Severity,
+        disassembler::{slots, Call, CallKind, Item, Strategy},
+        errors::EZBpfError,
+        instructions::Ix,
+        murmur3::hash_function,
+        program::Program,
+        program_header::ProgramType,
+        sbpf_version::SbpfVersion,
+        dynamic::{DynamicEntry, DynamicMismatch, DT_RELSZ},
+        relocation::RelocationType,
+        syscalls::SyscallRegistry,
+        symbol::{SymbolBinding, SymbolType, SymbolVisibility},
+        validate::ValidationError,
+    };
 
     #[test]
     fn try_deserialize_program() {
         let program = Program::from_bytes(&hex!("7F454C460201010000000000000000000300F700010000002001000000000000400000000000000028020000000000000000000040003800030040000600050001000000050000002001000000000000200100000000000020010000000000003000000000000000300000000000000000100000000000000100000004000000C001000000000000C001000000000000C0010000000000003C000000000000003C000000000000000010000000000000020000000600000050010000000000005001000000000000500100000000000070000000000000007000000000000000080000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000007912A000000000007911182900000000B7000000010000002D21010000000000B70000000000000095000000000000001E0000000000000004000000000000000600000000000000C0010000000000000B0000000000000018000000000000000500000000000000F0010000000000000A000000000000000C00000000000000160000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000120001002001000000000000300000000000000000656E747279706F696E7400002E74657874002E64796E737472002E64796E73796D002E64796E616D6963002E73687374727461620000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001000000010000000600000000000000200100000000000020010000000000003000000000000000000000000000000008000000000000000000000000000000170000000600000003000000000000005001000000000000500100000000000070000000000000000400000000000000080000000000000010000000000000000F0000000B0000000200000000000000C001000000000000C001000000000000300000000000000004000000010000000800000000000000180000000000000007000000030000000200000000000000F001000000000000F0010000000000000C00000000000000000000000000000001000000000000000000000000000000200000000300000000000000000000000000000000000000FC010000000000002A00000000000000000000000000000001000000000000000000000000000000")).unwrap();
         println!("{:?}", program.section_header_entries);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_symbols() {
+        let program = Program::from_bytes(&RELATIVE_CALL).unwrap();
+
+        let dynamic_symbols = program.dynamic_symbols();
+        assert_eq!(dynamic_symbols.len(), 2);
+        let entrypoint = &dynamic_symbols[1];
+        assert_eq!(entrypoint.name, "entrypoint");
+        assert_eq!(entrypoint.st_value, 0x140);
+        assert_eq!(entrypoint.st_size, 72);
+        assert_eq!(entrypoint.st_bind, SymbolBinding::STB_GLOBAL);
+        assert_eq!(entrypoint.st_type, SymbolType::STT_FUNC);
+        assert_eq!(entrypoint.st_shndx, 1);
+
+        let symbols = program.symbols();
+        assert_eq!(symbols.len(), 6);
+        assert_eq!(symbols[1].st_type, SymbolType::STT_FILE);
+        assert_eq!(
+            symbols[3].name,
+            "_ZN13relative_call12function_sum17h50b776b9db11d90dE"
+        );
+        assert_eq!(symbols[3].st_value, 0x188);
+        assert_eq!(symbols[4].name, "_DYNAMIC");
+        assert_eq!(symbols[4].visibility(), SymbolVisibility::STV_HIDDEN);
+    }
+
+    #[test]
+    fn parse_relocations() {
+        let program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        let relocations = program.relocations();
+        assert_eq!(relocations.len(), 2);
+        assert_eq!(relocations[0].r_type, RelocationType::R_BPF_64_RELATIVE);
+        assert_eq!(relocations[0].symbol, "");
+        assert_eq!(relocations[1].r_type, RelocationType::R_BPF_64_32);
+        assert_eq!(relocations[1].symbol, "log");
+        assert_eq!(program.relocation_at(0x138), Some(&relocations[1]));
+        assert_eq!(program.relocation_at(0x130), None);
+        let by_offset = program.relocations_by_offset();
+        assert_eq!(by_offset.len(), relocations.len());
+        assert_eq!(by_offset[&0x138], &relocations[1]);
+        assert_eq!(
+            program.to_asm().unwrap(),
+            "lddw r1, 0x150\nmov64 r2, 4\ncall log\nmov64 r0, 0\nexit"
+        );
+
+        let program = Program::from_bytes(&RELOC_64_64).unwrap();
+        assert_eq!(program.relocations()[0].r_type, RelocationType::R_BPF_64_64);
+        assert_eq!(program.to_asm().unwrap(), "lddw r0, entrypoint\nexit");
+    }
+
+    #[test]
+    fn section_labels() {
+        let program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        let labels: Vec<&str> = program
+            .section_header_entries
+            .iter()
+            .map(|e| e.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "", ".text", ".rodata", ".dynamic", ".dynsym", ".dynstr", ".rel.dyn", ".comment",
+                ".symtab", ".shstrtab", ".strtab"
+            ]
+        );
+        assert_eq!(program.dynamic_str(1).unwrap(), "entrypoint");
+    }
+
+    #[test]
+    fn parse_dynamic() {
+        let program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        let dynamic = program.dynamic();
+        assert_eq!(dynamic.len(), 11);
+        assert_eq!(dynamic[1], DynamicEntry::DT_REL(0x260));
+        assert_eq!(dynamic[4], DynamicEntry::DT_RELCOUNT(1));
+        assert_eq!(dynamic[10], DynamicEntry::DT_NULL);
+        assert!(program.check_dynamic().is_empty());
+
+        // Shrink DT_RELSZ so the loader would only see the first relocation
+        let mut b = SYSCALL_RELOC_64_32;
+        b[0x158 + 0x28] = 0x10;
+        let program = Program::from_bytes(&b).unwrap();
+        assert_eq!(
+            program.check_dynamic(),
+            vec![DynamicMismatch {
+                tag: DT_RELSZ,
+                dynamic: Some(0x10),
+                section: Some(0x20),
+            }]
+        );
+    }
+
+    #[test]
+    fn serialize_e2e() {
+        for b in [
+            &RELATIVE_CALL[..],
+            &SYSCALL_RELOC_64_32,
+            &RELOC_64_64,
+            &BSS_SECTION,
+            &DATA_SECTION,
+        ] {
+            let program = Program::from_bytes(b).unwrap();
+            assert_eq!(program.to_bytes().unwrap(), b);
+        }
+    }
+
+    #[test]
+    fn serialize_resized_section() {
+        let mut program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        program.section_header_entries[2].data = b"hello\n".to_vec();
+        let b = program.to_bytes().unwrap();
+
+        let patched = Program::from_bytes(&b).unwrap();
+        assert_eq!(patched.section_header_entries[2].data, b"hello\n");
+        assert_eq!(patched.section_headers[2].sh_size, 6);
+        // .dynamic keeps its 8 byte alignment
+        assert_eq!(patched.section_headers[3].sh_offset, 0x160);
+        assert_eq!(patched.program_headers[1].p_filesz, 6);
+        assert_eq!(patched.program_headers[3].p_offset, 0x160);
+        assert_eq!(patched.elf_header.e_shoff, program.elf_header.e_shoff + 8);
+        assert_eq!(patched.dynamic, program.dynamic);
+        assert_eq!(patched.relocations, program.relocations);
+        for (a, b) in patched
+            .section_header_entries
+            .iter()
+            .zip(&program.section_header_entries)
+        {
+            assert_eq!(a.label, b.label);
+            assert_eq!(a.data, b.data);
+        }
+    }
+
+    #[test]
+    fn serialize_tables() {
+        let mut program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        program.dynamic_symbols[1].st_size = 0x30;
+        program.relocations[0].r_offset = 0x128;
+        program.dynamic[4] = DynamicEntry::DT_RELCOUNT(2);
+        let exit = Ix::from_bytes(&hex!("9500000000000000"), program.sbpf_version).unwrap();
+        program.section_header_entries[1].items[2] = Item::Ix(exit);
+        let b = program.to_bytes().unwrap();
+
+        let patched = Program::from_bytes(&b).unwrap();
+        assert_eq!(patched.dynamic_symbols, program.dynamic_symbols);
+        assert_eq!(patched.relocations, program.relocations);
+        assert_eq!(patched.dynamic, program.dynamic);
+        assert_eq!(
+            patched.section_header_entries[1].items,
+            program.section_header_entries[1].items
+        );
+        assert_eq!(b.len(), SYSCALL_RELOC_64_32.len());
+
+        // Dropping the last relocation shrinks .rel.dyn
+        let mut program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        program.relocations.pop();
+        let patched = Program::from_bytes(&program.to_bytes().unwrap()).unwrap();
+        assert_eq!(patched.relocations, program.relocations);
+        assert_eq!(patched.section_headers[6].sh_size, 0x10);
+
+        // An instruction from a later version can't be written
+        let mut program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        let hor64 = Ix::from_bytes(&hex!("f701000001000000"), SbpfVersion::V2).unwrap();
+        program.section_header_entries[1].items[2] = Item::Ix(hor64);
+        let e = program.to_bytes().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "instruction #2 in .text: hor64 is not supported in SBPF v0 at offset 0x138"
+        );
+        assert!(matches!(program.validate()[..], [ValidationError::Unwritable { .. }]));
+    }
+
+    #[test]
+    fn serialize_gaps() {
+        // Non-zero padding between .rodata and .dynamic
+        let mut b = SYSCALL_RELOC_64_32;
+        b[0x154..0x158].copy_from_slice(&[1, 2, 3, 4]);
+        let mut program = Program::from_bytes(&b).unwrap();
+        assert_eq!(program.gaps.len(), 1);
+        assert_eq!((program.gaps[0].offset, &program.gaps[0].data[..]), (0x154, &[1, 2, 3, 4][..]));
+        assert_eq!(program.to_bytes().unwrap(), b);
+
+        // and it moves along with the sections around it
+        program.section_header_entries[2].data = b"foo!\n".to_vec();
+        let resized = program.to_bytes().unwrap();
+        assert_eq!(resized[0x155..0x159], [1, 2, 3, 4]);
+        assert_eq!(Program::from_bytes(&resized).unwrap().section_headers[3].sh_offset, 0x160);
+    }
+
+    #[test]
+    fn serialize_too_large() {
+        // A section header table moved far past the end of the file
+        let mut program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        program.elf_header.e_shoff = u64::MAX - 8;
+        assert_eq!(program.file_size(), Ok(u64::MAX));
+        assert_eq!(
+            program.to_bytes(),
+            Err(EZBpfError::ProgramTooLarge { len: u64::MAX })
+        );
+        assert!(program
+            .validate()
+            .contains(&ValidationError::ProgramTooLarge { size: u64::MAX }));
+    }
+
+    #[test]
+    fn error_context() {
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        b[0x130] = 0xff;
+        // An undecodable slot is kept as data even when parsing strictly
+        let program = Program::from_bytes(&b).unwrap();
+        let d = &program.diagnostics[0];
+        assert_eq!((d.severity, d.offset), (Severity::Warning, Some(0x130)));
+        assert_eq!(
+            d.message,
+            "instruction #1 in .text: Invalid OpCode 0xff at offset 0x130"
+        );
+        let items = &program.section_header_entries[1].items;
+        assert_eq!(items[1], Item::Data(b[0x130..0x138].to_vec()));
+
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        let program = Program::from_bytes(&b).unwrap();
+        let sh_name = program.elf_header.e_shoff as usize + 7 * 64;
+        b[sh_name..sh_name + 4].copy_from_slice(&0x1234u32.to_le_bytes());
+        assert_eq!(
+            Program::from_bytes(&b).unwrap_err().to_string(),
+            format!(
+                "section header #7: Invalid string 0x1234 in string table at offset {:#x}",
+                program.section_headers[9].sh_offset
+            )
+        );
+
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        b[0x12] = 0x03;
+        assert_eq!(
+            Program::from_bytes(&b).unwrap_err().to_string(),
+            "ELF header: Non-standard ELF header, e_machine is 0x103 at offset 0x12"
+        );
+    }
+
+    #[test]
+    fn malformed_input() {
+        for fixture in [
+            &RELATIVE_CALL[..],
+            &SYSCALL_RELOC_64_32[..],
+            &RELOC_64_64[..],
+            &BSS_SECTION[..],
+            &DATA_SECTION[..],
+        ] {
+            // Every truncation either parses or fails with an error, never a panic
+            for len in 0..fixture.len() {
+                if let Ok(program) = Program::from_bytes(&fixture[..len]) {
+                    let _ = program.to_asm();
+                    let _ = program.to_bytes();
+                }
+            }
+            for i in 0..fixture.len() {
+                let mut b = fixture.to_vec();
+                b[i] = !b[i];
+                if let Ok(program) = Program::from_bytes(&b) {
+                    let _ = program.to_asm();
+                    let _ = program.to_bytes();
+                }
+            }
+        }
+        assert!(Program::from_bytes(&RELATIVE_CALL[..0x3f]).is_err());
+
+        // Section header string table index past the end of the table
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        b[0x3e..0x40].copy_from_slice(&0xffffu16.to_le_bytes());
+        assert_eq!(
+            Program::from_bytes(&b).unwrap_err().to_string(),
+            "ELF header: Invalid section index 65535 at offset 0x3e"
+        );
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        let program = Program::from_bytes(&b).unwrap();
+        // A section header table cut short, a section name outside of
+        // .shstrtab and an undecodable instruction
+        b.truncate(b.len() - 32);
+        let sh_name = program.elf_header.e_shoff as usize + 7 * 64;
+        b[sh_name..sh_name + 4].copy_from_slice(&0x1234u32.to_le_bytes());
+        b[0x130] = 0xff;
+        assert!(Program::from_bytes(&b).is_err());
+
+        let lenient = Program::from_bytes_lenient(&b).unwrap();
+        assert_eq!(lenient.diagnostics.len(), 4);
+        let severities: Vec<_> = lenient.diagnostics.iter().map(|d| d.severity).collect();
+        assert_eq!(
+            severities,
+            [Severity::Error, Severity::Error, Severity::Error, Severity::Warning]
+        );
+
+        let d = &lenient.diagnostics[0];
+        assert_eq!(d.offset, Some(0x648));
+        assert_eq!(
+            d.message,
+            "section header #10: Failed to read 8 bytes at offset 0x648"
+        );
+        assert_eq!(d.data, b[0x628..]);
+        assert_eq!(lenient.section_headers.len(), program.section_headers.len() - 1);
+
+        let d = &lenient.diagnostics[1];
+        assert_eq!(d.offset, Some(program.section_headers[9].sh_offset));
+        assert_eq!(lenient.section_header_entries[7].label, "");
+        assert_eq!(lenient.section_header_entries[7].data, program.section_header_entries[7].data);
+
+        // .symtab links to the missing .strtab
+        let d = &lenient.diagnostics[2];
+        assert!(d.message.starts_with("Invalid section index 10"));
+        assert!(lenient.symbols.is_empty());
+
+        let d = &lenient.diagnostics[3];
+        assert_eq!(d.offset, Some(0x130));
+        let address = d.address.unwrap();
+        assert_eq!(address.vm_addr, Some(MM_PROGRAM_START + 0x130));
+        assert_eq!((address.section, address.ix), (Some(1), Some(1)));
+        assert!(d.to_string().ends_with("at offset 0x130 (vm 0x100000130)"));
+        assert_eq!(d.data, b[0x130..0x138]);
+        // Decoding carries on past the bad slot
+        let items = &lenient.section_header_entries[1].items;
+        assert_eq!(items[1], Item::Data(b[0x130..0x138].to_vec()));
+        assert_eq!(items[2..], program.section_header_entries[1].items[2..]);
+        assert!(lenient
+            .to_asm()
+            .unwrap()
+            .contains("\n.quad 0x00000004000002ff\ncall log\n"));
+        assert_eq!(lenient.relocations, program.relocations);
+
+        // Nothing to report on a well-formed program
+        let program = Program::from_bytes_lenient(&SYSCALL_RELOC_64_32).unwrap();
+        assert!(program.diagnostics.is_empty());
+
+        // The part of the last section header that is there is written back
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        b.truncate(b.len() - 32);
+        let program = Program::from_bytes_lenient(&b).unwrap();
+        assert_eq!(program.gaps.last().unwrap().offset, 0x628);
+        assert_eq!(program.to_bytes().unwrap(), b);
+
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        b[0x07] = 0x03;
+        let program = Program::from_bytes_lenient(&b).unwrap();
+        assert_eq!(program.diagnostics[0].severity, Severity::Warning);
+        assert_eq!(program.elf_header.ei_osabi, 0x03);
+    }
+
+    #[test]
+    fn sbpf_version() {
+        let program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        assert_eq!(program.sbpf_version, SbpfVersion::V0);
+
+        let program = Program::from_bytes(&STRICT_HEADER).unwrap();
+        assert_eq!(program.sbpf_version, SbpfVersion::V3);
+        assert_eq!(program.program_headers[2].p_type, ProgramType::PT_GNU_STACK);
+        assert_eq!(program.to_bytes().unwrap(), STRICT_HEADER);
+        let foo = "_ZN13strict_header3foo17h85ae6912fc082c89E";
+        assert_eq!(
+            program.to_asm().unwrap(),
+            format!("{foo}:\nmov32 r1, 0\nhor64 r1, 1\nldxdw r0, [r1+0]\nreturn\ncall {foo}\nreturn")
+        );
+
+        // lddw no longer exists from SBPFv2 on
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        b[0x30] = 0x02;
+        let program = Program::from_bytes(&b).unwrap();
+        assert_eq!(
+            program.diagnostics[0].message,
+            "instruction #0 in .text: Invalid OpCode 0x18 at offset 0x120"
+        );
+    }
+
+    #[test]
+    fn recursive_descent() {
+        let mut program = Program::from_bytes(&RELATIVE_CALL).unwrap();
+        let linear = program.to_asm().unwrap();
+        assert!(program.recursive_descent()[1].iter().all(|r| *r));
+        assert_eq!(program.to_asm_with(Strategy::RecursiveDescent, &SyscallRegistry::default()).unwrap(), linear);
+
+        // Without symbols, both functions are still called from the entrypoint
+        program.symbols.clear();
+        program.dynamic_symbols.clear();
+        assert!(program.recursive_descent()[1].iter().all(|r| *r));
+
+        // Starting from function_sum, nothing else is reached
+        program.elf_header.e_entry = 0x188;
+        let reached = &program.recursive_descent()[1];
+        assert_eq!(reached.iter().filter(|r| **r).count(), 3);
+        assert!(reached[13..].iter().all(|r| *r));
+        let asm = program.to_asm_with(Strategy::RecursiveDescent, &SyscallRegistry::default()).unwrap();
+        let lines: Vec<&str> = asm.lines().collect();
+        assert_eq!(lines[0], ".quad 0x0000000000001079");
+        assert_eq!(lines[13..], ["mov64 r0, r2", "add64 r0, r1", "exit"]);
+
+        // An item added past the end of the data is written from the item
+        let exit = Ix::from_bytes(&hex!("9500000000000000"), program.sbpf_version).unwrap();
+        program.section_header_entries[1].items.push(Item::Ix(exit));
+        let asm = program.to_asm_with(Strategy::RecursiveDescent, &SyscallRegistry::default()).unwrap();
+        assert_eq!(asm.lines().last(), Some(".quad 0x0000000000000095"));
+    }
+
+    #[test]
+    fn jump_labels() {
+        let mut b = RELATIVE_CALL;
+        // ja +2 to the exit of entrypoint and jne r1, 0, +1 to the exit of
+        // function_sum
+        b[0x168..0x170].copy_from_slice(&hex!("0500020000000000"));
+        b[0x188..0x190].copy_from_slice(&hex!("5501010000000000"));
+        let mut program = Program::from_bytes(&b).unwrap();
+        let text = &program.section_header_entries[1];
+        let ja = text.items[9].ix().unwrap();
+        assert_eq!(ja.jump_target(slots(&text.items)[9]), Some(12));
+        let version = program.sbpf_version;
+        assert_eq!(&Ix::parse_asm(&ja.to_asm(version).unwrap(), version).unwrap(), ja);
+        // The JSON of the section gives the target alongside the offset, and
+        // reads back without it
+        let json = serde_json::to_value(text).unwrap();
+        assert_eq!(
+            json["ixs"][9],
+            serde_json::json!({"op": "Ja", "dst": 0, "src": 0, "off": 2, "imm": 0, "target": 12})
+        );
+        let items: Vec<Item> = serde_json::from_value(json["ixs"].clone()).unwrap();
+        assert_eq!(items, text.items);
+        let function_sum = "_ZN13relative_call12function_sum17h50b776b9db11d90dE";
+        let asm = program.to_asm().unwrap();
+        let lines: Vec<&str> = asm.lines().collect();
+        let call = format!("call {}", function_sum);
+        assert_eq!(lines[10..13], ["ja entrypoint+0x40", "mov64 r2, r6", &call]);
+        assert_eq!(lines[13..15], ["entrypoint+0x40:", "exit"]);
+        assert_eq!(lines[15], format!("{}:", function_sum));
+        assert_eq!(lines[16], format!("jne r1, 0, {}+0x10", function_sum));
+        assert_eq!(lines[18], format!("{}+0x10:", function_sum));
+
+        // Without symbols, labels are named after the slot and called functions
+        // after their address
+        program.symbols.clear();
+        program.dynamic_symbols.clear();
+        let asm = program.to_asm().unwrap();
+        let lines: Vec<&str> = asm.lines().collect();
+        assert_eq!(lines[0], "fn_0x120:");
+        assert_eq!(lines[9..11], ["call fn_0x120", "ja lbb_12"]);
+        assert_eq!(lines[12..14], ["call fn_0x188", "lbb_12:"]);
+        assert_eq!(
+            lines[15..],
+            ["fn_0x188:", "jne r1, 0, lbb_15", "add64 r0, r1", "lbb_15:", "exit"]
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let mut syscalls = SyscallRegistry::default();
+        let function_sum = "_ZN13relative_call12function_sum17h50b776b9db11d90dE";
+        let program = Program::from_bytes(&RELATIVE_CALL).unwrap();
+        let calls = program.classify_calls(&syscalls);
+        let kinds: Vec<_> = calls
+            .iter()
+            .map(|c| (c.pc, c.kind, c.target))
+            .collect();
+        assert_eq!(
+            kinds,
+            [(8, CallKind::Relative, Some(0)), (11, CallKind::Relative, Some(13))]
+        );
+        assert_eq!(calls[1].name, function_sum);
+        assert_eq!(
+            serde_json::to_string(&calls[1]).unwrap(),
+            format!(r#"{{"section":1,"pc":11,"kind":"Relative","target":13,"name":"{}"}}"#, function_sum)
+        );
+        let json = serde_json::to_value(program.to_json(&syscalls)).unwrap();
+        assert_eq!(json["calls"][1]["name"], function_sum);
+        assert!(json["section_header_entries"].is_array());
+
+        // A relocation against an undefined symbol is a syscall
+        let program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        assert_eq!(
+            program.classify_calls(&syscalls),
+            [Call {
+                section: 1,
+                pc: 3,
+                kind: CallKind::Syscall,
+                target: None,
+                name: "log".to_string(),
+            }]
+        );
+
+        // With a source of 0 the immediate of call +1 is a hash rather than an
+        // offset, even though it lands on function_sum
+        let mut b = RELATIVE_CALL;
+        b[0x179] = 0x00;
+        let program = Program::from_bytes(&b).unwrap();
+        let calls = program.classify_calls(&syscalls);
+        assert_eq!((calls[1].kind, calls[1].target), (CallKind::Unresolved, None));
+        let asm = program.to_asm().unwrap();
+        assert!(asm.lines().any(|l| l == "call 1"));
+
+        // Calls by the hash of the function's slot, and through a register
+        b[0x17c..0x180].copy_from_slice(&hash_function(13).to_le_bytes());
+        b[0x140..0x148].copy_from_slice(&hex!("8d00000001000000"));
+        let program = Program::from_bytes(&b).unwrap();
+        let calls = program.classify_calls(&syscalls);
+        assert_eq!(calls[0].kind, CallKind::Register);
+        assert_eq!(
+            (calls[2].kind, calls[2].target),
+            (CallKind::Hashed, Some(13))
+        );
+        let asm = program.to_asm().unwrap();
+        assert!(asm.contains(&format!("call {}", function_sum)));
+
+        // A hash that doesn't match any slot in the section
+        b[0x17c..0x180].copy_from_slice(&hash_function(16).to_le_bytes());
+        let program = Program::from_bytes(&b).unwrap();
+        let calls = program.classify_calls(&syscalls);
+        assert_eq!(calls[2].kind, CallKind::Unresolved);
+        assert_eq!(calls[2].name, "");
+
+        // A syscall called by the hash of its name
+        b[0x17c..0x180].copy_from_slice(&hex!("bd597520"));
+        let program = Program::from_bytes(&b).unwrap();
+        let calls = program.classify_calls(&syscalls);
+        assert_eq!(calls[2].kind, CallKind::Syscall);
+        assert_eq!(calls[2].name, "sol_log_");
+        let asm = program.to_asm().unwrap();
+        let lines: Vec<&str> = asm.lines().collect();
+        // Neither argument is a constant: r1 is left over from before the call
+        // to function_stack_ref and r2 is copied from r6, loaded from memory
+        assert_eq!(
+            lines[12],
+            "call sol_log_ // r1 message: *const u8, r2 len: u64"
+        );
+
+        // With mov64 r2, 4 in place of mov64 r2, r6
+        b[0x170..0x178].copy_from_slice(&hex!("b702000004000000"));
+        let program = Program::from_bytes(&b).unwrap();
+        let asm = program.to_asm().unwrap();
+        let lines: Vec<&str> = asm.lines().collect();
+        assert_eq!(lines[12], "call sol_log_ // r1 message: *const u8, r2 len: u64 = 4");
+
+        // A syscall of the runtime's own, only known once registered
+        let hash = syscalls.register("my_runtime_syscall");
+        b[0x17c..0x180].copy_from_slice(&hash.to_le_bytes());
+        let program = Program::from_bytes(&b).unwrap();
+        let calls = program.classify_calls(&SyscallRegistry::default());
+        assert_eq!(calls[2].kind, CallKind::Unresolved);
+        let calls = program.classify_calls(&syscalls);
+        assert_eq!(calls[2].kind, CallKind::Syscall);
+        assert_eq!(calls[2].name, "my_runtime_syscall");
+        let asm = program.to_asm_with(Strategy::LinearSweep, &syscalls).unwrap();
+        let lines: Vec<&str> = asm.lines().collect();
+        assert_eq!(lines[12], "call my_runtime_syscall");
+    }
+
+    #[test]
+    fn segments_without_sections() {
+        // STRICT_HEADER with its section header table and everything after
+        // .rodata stripped
+        let mut b = STRICT_HEADER[..0x1d0].to_vec();
+        b[0x28..0x30].fill(0);
+        b[0x3c..0x40].fill(0);
+        let program = Program::from_bytes(&b).unwrap();
+        assert!(program.section_headers.is_empty());
+        let labels: Vec<&str> = program
+            .section_header_entries
+            .iter()
+            .map(|e| e.label.as_str())
+            .collect();
+        assert_eq!(labels, vec![".text", ".rodata"]);
+        assert_eq!(program.section_header_entries[0].offset, 0x190);
+        assert_eq!(program.section_header_entries[1].data, STRICT_HEADER[0x1c0..0x1d0]);
+        assert_eq!(
+            program.to_asm().unwrap(),
+            "fn_0x0:\nmov32 r1, 0\nhor64 r1, 1\nldxdw r0, [r1+0]\nreturn\ncall fn_0x0\nreturn"
+        );
+
+        // Segment contents are written back in place of sections
+        let mut program = program;
+        assert_eq!(program.to_bytes().unwrap(), b);
+        let ix = Ix::from_bytes(&hex!("9d00000000000000"), program.sbpf_version).unwrap();
+        program.section_header_entries[0].items.push(Item::Ix(ix));
+        let b = program.to_bytes().unwrap();
+        let resized = Program::from_bytes(&b).unwrap();
+        assert_eq!(resized.program_headers[0].p_filesz, 0x38);
+        assert_eq!(resized.program_headers[1].p_offset, 0x1c8);
+        assert_eq!(resized.section_header_entries[1].data, STRICT_HEADER[0x1c0..0x1d0]);
+    }
+}
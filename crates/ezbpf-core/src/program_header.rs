@@ -4,13 +4,15 @@ use serde::{Deserialize, Serialize};
 
 use crate::{cursor::ELFCursor, errors::EZBpfError};
 
+pub const PROGRAM_HEADER_SIZE: u64 = 56; // Size of an Elf64_Phdr entry
+
 // Program Segment Flags
 pub const PF_X: u8 = 0x01;
 pub const PF_W: u8 = 0x02;
 pub const PF_R: u8 = 0x04;
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[repr(u32)]
 pub enum ProgramType {
     PT_NULL = 0x00,    // Program header table entry unused.
@@ -21,37 +23,49 @@ pub enum ProgramType {
     PT_SHLIB = 0x05,   // Reserved.
     PT_PHDR = 0x06,    // Segment containing program header table itself.
     PT_TLS = 0x07,     // Thread-Local Storage template.
+    PT_GNU_EH_FRAME = 0x6474e550, // Exception handling frame header.
+    PT_GNU_STACK = 0x6474e551,    // Permissions of the stack.
+    PT_GNU_RELRO = 0x6474e552,    // Read only after relocation.
+    PT_GNU_PROPERTY = 0x6474e553, // GNU property notes.
+    Other(u32),                   // Any other type, kept as-is.
 }
 
-impl TryFrom<u32> for ProgramType {
-    type Error = EZBpfError;
-
-    fn try_from(value: u32) -> Result<Self, Self::Error> {
-        Ok(match value {
-            0 => Self::PT_NULL,
-            1 => Self::PT_LOAD,
-            2 => Self::PT_DYNAMIC,
-            3 => Self::PT_INTERP,
-            4 => Self::PT_NOTE,
-            5 => Self::PT_SHLIB,
-            6 => Self::PT_PHDR,
-            7 => Self::PT_TLS,
-            _ => return Err(EZBpfError::InvalidProgramType),
-        })
+impl From<u32> for ProgramType {
+    fn from(value: u32) -> Self {
+        match value {
+            0x00 => Self::PT_NULL,
+            0x01 => Self::PT_LOAD,
+            0x02 => Self::PT_DYNAMIC,
+            0x03 => Self::PT_INTERP,
+            0x04 => Self::PT_NOTE,
+            0x05 => Self::PT_SHLIB,
+            0x06 => Self::PT_PHDR,
+            0x07 => Self::PT_TLS,
+            0x6474e550 => Self::PT_GNU_EH_FRAME,
+            0x6474e551 => Self::PT_GNU_STACK,
+            0x6474e552 => Self::PT_GNU_RELRO,
+            0x6474e553 => Self::PT_GNU_PROPERTY,
+            value => Self::Other(value),
+        }
     }
 }
 
 impl From<ProgramType> for u32 {
     fn from(val: ProgramType) -> Self {
         match val {
-            ProgramType::PT_NULL => 0,
-            ProgramType::PT_LOAD => 1,
-            ProgramType::PT_DYNAMIC => 2,
-            ProgramType::PT_INTERP => 3,
-            ProgramType::PT_NOTE => 4,
-            ProgramType::PT_SHLIB => 5,
-            ProgramType::PT_PHDR => 6,
-            ProgramType::PT_TLS => 7,
+            ProgramType::PT_NULL => 0x00,
+            ProgramType::PT_LOAD => 0x01,
+            ProgramType::PT_DYNAMIC => 0x02,
+            ProgramType::PT_INTERP => 0x03,
+            ProgramType::PT_NOTE => 0x04,
+            ProgramType::PT_SHLIB => 0x05,
+            ProgramType::PT_PHDR => 0x06,
+            ProgramType::PT_TLS => 0x07,
+            ProgramType::PT_GNU_EH_FRAME => 0x6474e550,
+            ProgramType::PT_GNU_STACK => 0x6474e551,
+            ProgramType::PT_GNU_RELRO => 0x6474e552,
+            ProgramType::PT_GNU_PROPERTY => 0x6474e553,
+            ProgramType::Other(value) => value,
         }
     }
 }
@@ -67,6 +81,20 @@ impl From<ProgramType> for &str {
             ProgramType::PT_SHLIB => "PT_SHLIB",
             ProgramType::PT_PHDR => "PT_PHDR",
             ProgramType::PT_TLS => "PT_TLS",
+            ProgramType::PT_GNU_EH_FRAME => "PT_GNU_EH_FRAME",
+            ProgramType::PT_GNU_STACK => "PT_GNU_STACK",
+            ProgramType::PT_GNU_RELRO => "PT_GNU_RELRO",
+            ProgramType::PT_GNU_PROPERTY => "PT_GNU_PROPERTY",
+            ProgramType::Other(_) => "PT_UNKNOWN",
+        }
+    }
+}
+
+impl std::fmt::Display for ProgramType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(value) => f.pad(&format!("{:#x}", value)),
+            p => f.pad(Into::<&str>::into(p.clone())),
         }
     }
 }
@@ -119,7 +147,7 @@ impl ProgramHeader {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut b = (self.p_type.clone() as u32).to_le_bytes().to_vec();
+        let mut b = u32::from(self.p_type.clone()).to_le_bytes().to_vec();
         b.extend_from_slice(&self.p_flags.0.to_le_bytes());
         b.extend_from_slice(&self.p_offset.to_le_bytes());
         b.extend_from_slice(&self.p_vaddr.to_le_bytes());
@@ -135,7 +163,7 @@ impl ProgramHeader {
 mod tests {
     use hex_literal::hex;
 
-    use crate::program_header::ProgramHeader;
+    use crate::program_header::{ProgramHeader, ProgramType};
 
     #[test]
     fn serialize_e2e() {
@@ -143,4 +171,22 @@ mod tests {
         let h = ProgramHeader::from_bytes(&b).unwrap();
         assert_eq!(h.to_bytes(), &b)
     }
+
+    #[test]
+    fn other_types() {
+        let mut b = hex!("0100000005000000780000000000000078000000000000007800000000000000080000000000000008000000000000000010000000000000");
+        b[..4].copy_from_slice(&0x6474e551u32.to_le_bytes());
+        let h = ProgramHeader::from_bytes(&b).unwrap();
+        assert_eq!(h.p_type, ProgramType::PT_GNU_STACK);
+        assert_eq!(h.to_bytes(), &b);
+
+        b[..4].copy_from_slice(&0x70000001u32.to_le_bytes());
+        let h = ProgramHeader::from_bytes(&b).unwrap();
+        assert_eq!(h.p_type, ProgramType::Other(0x70000001));
+        assert_eq!(h.p_type.to_string(), "0x70000001");
+        assert_eq!(h.to_bytes(), &b);
+        let json = serde_json::to_string(&h.p_type).unwrap();
+        assert_eq!(json, r#"{"Other":1879048193}"#);
+        assert_eq!(serde_json::from_str::<ProgramType>(&json).unwrap(), h.p_type);
+    }
 }
@@ -0,0 +1,422 @@
+use std::{io::Cursor, str};
+
+use crate::{
+    cursor::ELFCursor,
+    diagnostic::{Diagnostic, Recovery, Severity},
+    disassembler::LinearSweep,
+    dynamic::{DynamicEntry, DYNAMIC_ENTRY_SIZE},
+    elf_header::{ELFHeader, E_SHSTRNDX_OFFSET},
+    errors::{EZBpfError, ErrorContext},
+    instructions::Ix,
+    program_header::{ProgramHeader, ProgramType, PF_X, PROGRAM_HEADER_SIZE},
+    relocation::{Relocation, RELOCATION_SIZE},
+    sbpf_version::SbpfVersion,
+    section_header::{SectionHeader, SectionHeaderType, SECTION_HEADER_SIZE},
+    string_table::StringTable,
+    symbol::{Symbol, SYMBOL_SIZE},
+};
+
+/// A borrowed view of a program. Section contents, names and instruction streams
+/// are slices into the input buffer and instructions are only decoded when
+/// iterated over. Headers, symbols, relocations and the dynamic array are small
+/// and decoded eagerly.
+#[derive(Debug, Clone)]
+pub struct ProgramRef<'a> {
+    pub bytes: &'a [u8],
+    pub elf_header: ELFHeader,
+    pub sbpf_version: SbpfVersion,
+    pub program_headers: Vec<ProgramHeader>,
+    pub section_headers: Vec<SectionHeader>,
+    pub sections: Vec<SectionRef<'a>>,
+    pub symbols: Vec<Symbol>,
+    pub dynamic_symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>,
+    pub dynamic: Vec<DynamicEntry>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A section of a [`ProgramRef`], borrowing its label and contents from the input.
+#[derive(Debug, Clone, Copy)]
+pub struct SectionRef<'a> {
+    pub label: &'a str,
+    pub offset: usize,
+    pub data: &'a [u8],
+}
+
+impl<'a> SectionRef<'a> {
+    pub fn utf8(&self) -> Option<&'a str> {
+        str::from_utf8(self.data).ok()
+    }
+
+    /// Decodes every slot of the section, keeping the ones that aren't valid
+    /// instructions as data.
+    pub fn items(&self, version: SbpfVersion) -> LinearSweep<'a> {
+        LinearSweep::new(self.data, version)
+    }
+
+    pub fn ixs(&self, version: SbpfVersion) -> IxIter<'a> {
+        IxIter {
+            c: Cursor::new(self.data),
+            version,
+        }
+    }
+}
+
+/// Decodes instructions from a section one at a time, stopping at the first
+/// slot that is not a valid instruction.
+#[derive(Debug, Clone)]
+pub struct IxIter<'a> {
+    c: Cursor<&'a [u8]>,
+    version: SbpfVersion,
+}
+
+impl Iterator for IxIter<'_> {
+    type Item = Ix;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.c.read_ix(self.version).ok()
+    }
+}
+
+impl<'a> ProgramRef<'a> {
+    pub fn from_bytes(b: &'a [u8]) -> Result<Self, EZBpfError> {
+        Self::parse(b, &mut Recovery::new(false))
+    }
+
+    /// Parse as much of a damaged or unusual file as possible. Anything that
+    /// can't be parsed is skipped or replaced and recorded in `diagnostics`.
+    /// Only a file too short to hold an ELF header is rejected.
+    pub fn from_bytes_lenient(b: &'a [u8]) -> Result<Self, EZBpfError> {
+        let mut recovery = Recovery::new(true);
+        let mut program = Self::parse(b, &mut recovery)?;
+        program.diagnostics = recovery.diagnostics;
+        Ok(program)
+    }
+
+    fn parse(b: &'a [u8], recovery: &mut Recovery) -> Result<Self, EZBpfError> {
+        let mut c = Cursor::new(b);
+        let elf_header = c
+            .read_elf_header_unchecked()
+            .map_err(|e| e.context(ErrorContext::ElfHeader))?;
+        for e in elf_header.non_standard_fields() {
+            recovery.recover(Severity::Warning, e.context(ErrorContext::ElfHeader), &[])?;
+        }
+
+        let mut program_headers = vec![];
+        for index in 0..elf_header.e_phnum as usize {
+            let offset = elf_header
+                .e_phoff
+                .saturating_add(index as u64 * PROGRAM_HEADER_SIZE);
+            c.set_position(offset);
+            match c.read_program_header() {
+                Ok(h) => program_headers.push(h),
+                Err(e) => {
+                    let e = e.context(ErrorContext::ProgramHeader { index });
+                    recovery.recover(Severity::Error, e, raw(b, offset, PROGRAM_HEADER_SIZE))?;
+                    // Reads only fail at the end of the file, so none of the
+                    // headers after this one are there either
+                    break;
+                }
+            }
+        }
+
+        let mut section_headers = vec![];
+        for index in 0..elf_header.e_shnum as usize {
+            let offset = elf_header
+                .e_shoff
+                .saturating_add(index as u64 * SECTION_HEADER_SIZE);
+            c.set_position(offset);
+            match c.read_section_header() {
+                Ok(h) => section_headers.push(h),
+                Err(e) => {
+                    let e = e.context(ErrorContext::SectionHeader { index });
+                    recovery.recover(Severity::Error, e, raw(b, offset, SECTION_HEADER_SIZE))?;
+                    break;
+                }
+            }
+        }
+
+        let shstrtab = if section_headers.is_empty() {
+            StringTable::default()
+        } else {
+            let h = section_headers
+                .get(elf_header.e_shstrndx as usize)
+                .ok_or(EZBpfError::InvalidSectionIndex {
+                    offset: Some(E_SHSTRNDX_OFFSET),
+                    value: elf_header.e_shstrndx as u64,
+                })
+                .map_err(|e| e.context(ErrorContext::ElfHeader));
+            let data = h.and_then(|h| {
+                section_data(b, h).map_err(|e| {
+                    e.context(ErrorContext::SectionHeader {
+                        index: elf_header.e_shstrndx as usize,
+                    })
+                })
+            });
+            StringTable::new(recovery.or(data, &[])?)
+        };
+        let shstrtab_offset = section_headers
+            .get(elf_header.e_shstrndx as usize)
+            .map_or(0, |h| h.sh_offset);
+
+        let mut sections = vec![];
+        for (index, s) in section_headers.iter().enumerate() {
+            let context = || ErrorContext::SectionHeader { index };
+            let label = shstrtab
+                .get(s.sh_name)
+                .map_err(|e| e.shift(shstrtab_offset).context(context()));
+            let data = match s.sh_type {
+                SectionHeaderType::SHT_NOBITS => Ok(&[][..]),
+                _ => section_data(b, s).map_err(|e| e.context(context())),
+            };
+            sections.push(SectionRef {
+                label: recovery.or(label, "")?,
+                offset: s.sh_offset as usize,
+                data: recovery.or(data, raw(b, s.sh_offset, s.sh_size))?,
+            });
+        }
+
+        // Section headers are optional from SBPFv3 on, leaving only the segments
+        // to find the bytecode and read only data in
+        if section_headers.is_empty() {
+            for (index, h) in program_headers.iter().enumerate() {
+                if !is_segment_section(h) {
+                    continue;
+                }
+                let data = slice(b, h.p_offset, h.p_filesz)
+                    .map_err(|e| e.context(ErrorContext::ProgramHeader { index }));
+                sections.push(SectionRef {
+                    label: if h.p_flags.0 & PF_X as u32 != 0 {
+                        ".text"
+                    } else {
+                        ".rodata"
+                    },
+                    offset: h.p_offset as usize,
+                    data: recovery.or(data, raw(b, h.p_offset, h.p_filesz))?,
+                });
+            }
+        }
+
+        let symbols = read_symbols(b, &section_headers, SectionHeaderType::SHT_SYMTAB);
+        let symbols = recovery.or(symbols, vec![])?;
+        let dynamic_symbols = read_symbols(b, &section_headers, SectionHeaderType::SHT_DYNSYM);
+        let dynamic_symbols = recovery.or(dynamic_symbols, vec![])?;
+        let relocations = read_relocations(b, &section_headers, &dynamic_symbols);
+        let relocations = recovery.or(relocations, vec![])?;
+        let dynamic = read_dynamic(b, &program_headers, &section_headers);
+        let dynamic = recovery.or(dynamic, vec![])?;
+
+        Ok(Self {
+            bytes: b,
+            sbpf_version: elf_header.e_flags.into(),
+            elf_header,
+            program_headers,
+            section_headers,
+            sections,
+            symbols,
+            dynamic_symbols,
+            relocations,
+            dynamic,
+            diagnostics: vec![],
+        })
+    }
+
+    pub fn section(&self, label: &str) -> Option<&SectionRef<'a>> {
+        self.sections.iter().find(|s| s.label == label)
+    }
+
+    pub fn string_table(&self, index: usize) -> Result<StringTable<'a>, EZBpfError> {
+        match (self.section_headers.get(index), self.sections.get(index)) {
+            (Some(h), Some(s)) if h.sh_type == SectionHeaderType::SHT_STRTAB => {
+                Ok(StringTable::new(s.data))
+            }
+            _ => Err(EZBpfError::InvalidSectionIndex {
+                offset: Some(self.elf_header.e_shoff),
+                value: index as u64,
+            }),
+        }
+    }
+}
+
+fn read_symbols(
+    b: &[u8],
+    section_headers: &[SectionHeader],
+    sh_type: SectionHeaderType,
+) -> Result<Vec<Symbol>, EZBpfError> {
+    let Some(h) = section_headers.iter().find(|h| h.sh_type == sh_type) else {
+        return Ok(vec![]);
+    };
+    let table = match sh_type {
+        SectionHeaderType::SHT_DYNSYM => ".dynsym",
+        _ => ".symtab",
+    };
+    let strtab = section_headers
+        .get(h.sh_link as usize)
+        .ok_or(EZBpfError::InvalidSectionIndex {
+            offset: Some(h.sh_offset),
+            value: h.sh_link as u64,
+        })?;
+    let strtab_offset = strtab.sh_offset;
+    let strtab = StringTable::new(section_data(b, strtab)?);
+    let data = section_data(b, h)?;
+    if !(data.len() as u64).is_multiple_of(SYMBOL_SIZE) {
+        return Err(EZBpfError::InvalidDataLength {
+            offset: Some(h.sh_offset),
+            len: h.sh_size,
+        });
+    }
+    data.chunks(SYMBOL_SIZE as usize)
+        .enumerate()
+        .map(|(index, e)| {
+            let offset = h.sh_offset + index as u64 * SYMBOL_SIZE;
+            let context = || ErrorContext::Symbol {
+                table: table.to_string(),
+                index,
+            };
+            let mut symbol =
+                Symbol::from_bytes(e).map_err(|e| e.shift(offset).context(context()))?;
+            symbol.name = strtab
+                .get(symbol.st_name)
+                .map_err(|e| e.shift(strtab_offset).context(context()))?
+                .to_string();
+            Ok(symbol)
+        })
+        .collect()
+}
+
+fn read_relocations(
+    b: &[u8],
+    section_headers: &[SectionHeader],
+    dynamic_symbols: &[Symbol],
+) -> Result<Vec<Relocation>, EZBpfError> {
+    let mut relocations = vec![];
+    for h in section_headers
+        .iter()
+        .filter(|h| h.sh_type == SectionHeaderType::SHT_REL)
+    {
+        let data = section_data(b, h)?;
+        if !(data.len() as u64).is_multiple_of(RELOCATION_SIZE) {
+            return Err(EZBpfError::InvalidDataLength {
+                offset: Some(h.sh_offset),
+                len: h.sh_size,
+            });
+        }
+        for (i, e) in data.chunks(RELOCATION_SIZE as usize).enumerate() {
+            let offset = h.sh_offset + i as u64 * RELOCATION_SIZE;
+            let context = ErrorContext::Relocation {
+                index: relocations.len(),
+            };
+            let mut relocation = Relocation::from_bytes(e)
+                .map_err(|e| e.shift(offset).context(context.clone()))?;
+            if relocation.r_sym != 0 {
+                relocation.symbol = dynamic_symbols
+                    .get(relocation.r_sym as usize)
+                    .ok_or(EZBpfError::InvalidRelocationSymbol {
+                        offset: Some(offset + 12),
+                        value: relocation.r_sym,
+                    })
+                    .map_err(|e| e.context(context))?
+                    .name
+                    .clone();
+            }
+            relocations.push(relocation);
+        }
+    }
+    Ok(relocations)
+}
+
+fn read_dynamic(
+    b: &[u8],
+    program_headers: &[ProgramHeader],
+    section_headers: &[SectionHeader],
+) -> Result<Vec<DynamicEntry>, EZBpfError> {
+    let (offset, data) = match program_headers
+        .iter()
+        .find(|h| h.p_type == ProgramType::PT_DYNAMIC)
+    {
+        Some(h) => (h.p_offset, slice(b, h.p_offset, h.p_filesz)?),
+        None => match section_headers
+            .iter()
+            .find(|h| h.sh_type == SectionHeaderType::SHT_DYNAMIC)
+        {
+            Some(h) => (h.sh_offset, section_data(b, h)?),
+            None => return Ok(vec![]),
+        },
+    };
+    let mut dynamic = vec![];
+    for (index, e) in data.chunks_exact(DYNAMIC_ENTRY_SIZE as usize).enumerate() {
+        let entry = DynamicEntry::from_bytes(e).map_err(|e| {
+            e.shift(offset + index as u64 * DYNAMIC_ENTRY_SIZE)
+                .context(ErrorContext::DynamicEntry { index })
+        })?;
+        let end = entry == DynamicEntry::DT_NULL;
+        dynamic.push(entry);
+        if end {
+            break;
+        }
+    }
+    Ok(dynamic)
+}
+
+// Whether a segment stands in for a section when there are no section headers
+pub(crate) fn is_segment_section(h: &ProgramHeader) -> bool {
+    h.p_type == ProgramType::PT_LOAD && h.p_filesz > 0
+}
+
+// Whatever part of a range lies within the file
+fn raw(b: &[u8], offset: u64, len: u64) -> &[u8] {
+    let start = (offset.min(b.len() as u64)) as usize;
+    let end = (offset.saturating_add(len).min(b.len() as u64)) as usize;
+    &b[start..end]
+}
+
+fn section_data<'a>(b: &'a [u8], h: &SectionHeader) -> Result<&'a [u8], EZBpfError> {
+    slice(b, h.sh_offset, h.sh_size)
+}
+
+fn slice(b: &[u8], offset: u64, len: u64) -> Result<&[u8], EZBpfError> {
+    let err = EZBpfError::InvalidDataLength {
+        offset: Some(offset),
+        len,
+    };
+    let start = usize::try_from(offset).map_err(|_| err.clone())?;
+    let end = usize::try_from(len)
+        .ok()
+        .and_then(|len| start.checked_add(len))
+        .ok_or(err.clone())?;
+    b.get(start..end).ok_or(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{fixtures::SYSCALL_RELOC_64_32, program::Program, program_ref::ProgramRef};
+
+    #[test]
+    fn borrows_input() {
+        let b = &SYSCALL_RELOC_64_32[..];
+        let program = ProgramRef::from_bytes(b).unwrap();
+        let text = program.section(".text").unwrap();
+        assert_eq!(text.data.as_ptr(), b[0x120..].as_ptr());
+        assert_eq!(text.ixs(program.sbpf_version).count(), 5);
+        assert_eq!(program.section(".rodata").unwrap().utf8(), Some("foo\n"));
+        let shstrtab = program
+            .string_table(program.elf_header.e_shstrndx as usize)
+            .unwrap();
+        assert_eq!(
+            shstrtab.get(program.section_headers[6].sh_name).unwrap(),
+            ".rel.dyn"
+        );
+
+        let owned = Program::try_from(program.clone()).unwrap();
+        assert_eq!(owned.section_header_entries.len(), program.sections.len());
+        assert_eq!(
+            owned.section_header_entries[1].items,
+            text.items(program.sbpf_version).map(|s| s.item).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            owned.section_header_entries[1].ixs().cloned().collect::<Vec<_>>(),
+            text.ixs(program.sbpf_version).collect::<Vec<_>>()
+        );
+        assert_eq!(owned.to_bytes().unwrap(), b);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{fmt::Display, io::Cursor};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cursor::ELFCursor, errors::EZBpfError, instructions::Ix, opcodes::OpCode,
+    sbpf_version::SbpfVersion,
+};
+
+pub const RELOCATION_SIZE: u64 = 16; // Size of an Elf64_Rel entry
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RelocationType {
+    R_BPF_NONE = 0x00,        // No relocation.
+    R_BPF_64_64 = 0x01,       // lddw immediate, symbol value plus the implicit addend.
+    R_BPF_64_ABS64 = 0x02,    // 64 bit absolute address of the symbol.
+    R_BPF_64_ABS32 = 0x03,    // 32 bit absolute address of the symbol.
+    R_BPF_64_NODYLD32 = 0x04, // 32 bit value resolved at link time, ignored by the loader.
+    R_BPF_64_RELATIVE = 0x08, // Address relative to the start of the program region.
+    R_BPF_64_32 = 0x0a,       // call immediate, function or syscall symbol.
+    Other(u32),               // Any other type, kept as-is.
+}
+
+impl From<u32> for RelocationType {
+    fn from(value: u32) -> Self {
+        match value {
+            0x00 => Self::R_BPF_NONE,
+            0x01 => Self::R_BPF_64_64,
+            0x02 => Self::R_BPF_64_ABS64,
+            0x03 => Self::R_BPF_64_ABS32,
+            0x04 => Self::R_BPF_64_NODYLD32,
+            0x08 => Self::R_BPF_64_RELATIVE,
+            0x0a => Self::R_BPF_64_32,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl From<RelocationType> for u32 {
+    fn from(val: RelocationType) -> Self {
+        match val {
+            RelocationType::R_BPF_NONE => 0x00,
+            RelocationType::R_BPF_64_64 => 0x01,
+            RelocationType::R_BPF_64_ABS64 => 0x02,
+            RelocationType::R_BPF_64_ABS32 => 0x03,
+            RelocationType::R_BPF_64_NODYLD32 => 0x04,
+            RelocationType::R_BPF_64_RELATIVE => 0x08,
+            RelocationType::R_BPF_64_32 => 0x0a,
+            RelocationType::Other(value) => value,
+        }
+    }
+}
+
+impl Display for RelocationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(value) => f.pad(&format!("{:#x}", value)),
+            r => f.pad(Into::<&str>::into(r.clone())),
+        }
+    }
+}
+
+impl From<RelocationType> for &str {
+    fn from(val: RelocationType) -> Self {
+        match val {
+            RelocationType::R_BPF_NONE => "R_BPF_NONE",
+            RelocationType::R_BPF_64_64 => "R_BPF_64_64",
+            RelocationType::R_BPF_64_ABS64 => "R_BPF_64_ABS64",
+            RelocationType::R_BPF_64_ABS32 => "R_BPF_64_ABS32",
+            RelocationType::R_BPF_64_NODYLD32 => "R_BPF_64_NODYLD32",
+            RelocationType::R_BPF_64_RELATIVE => "R_BPF_64_RELATIVE",
+            RelocationType::R_BPF_64_32 => "R_BPF_64_32",
+            RelocationType::Other(_) => "R_BPF_UNKNOWN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Relocation {
+    pub r_offset: u64, // Address of the instruction or data being relocated.
+    pub r_type: RelocationType, // Lower 32 bits of r_info, how the relocation is applied.
+    pub r_sym: u32, // Upper 32 bits of r_info, an index into the linked symbol table.
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub symbol: String, // Name of the referenced symbol, resolved through .dynsym.
+}
+
+impl Relocation {
+    pub fn from_bytes(b: &[u8]) -> Result<Self, EZBpfError> {
+        let mut c = Cursor::new(b);
+        c.read_relocation()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.r_offset.to_le_bytes().to_vec();
+        b.extend_from_slice(&u32::from(self.r_type.clone()).to_le_bytes());
+        b.extend_from_slice(&self.r_sym.to_le_bytes());
+        b
+    }
+
+    /// The symbol, or address when there is none, an instruction is relocated against.
+    pub fn target(&self, ix: &Ix) -> String {
+        match (self.symbol.is_empty(), ix.imm) {
+            (true, imm) => format!("{:#x}", imm),
+            (false, 0) => self.symbol.clone(),
+            (false, imm) if imm.is_negative() => format!("{}{}", self.symbol, imm),
+            (false, imm) => format!("{}+{}", self.symbol, imm),
+        }
+    }
+
+    pub fn to_asm(&self, ix: &Ix, version: SbpfVersion) -> Result<String, EZBpfError> {
+        Ok(match (&ix.op, &self.r_type) {
+            (OpCode::Lddw, _) => format!("{} r{}, {}", ix.op, ix.dst, self.target(ix)),
+            // Calls are relocated by overwriting the immediate with the hash of the
+            // symbol, so the -1 placeholder in the file is meaningless
+            (OpCode::Call, RelocationType::R_BPF_64_32) if !self.symbol.is_empty() => {
+                format!("{} {}", ix.op, self.symbol)
+            }
+            _ => ix.to_asm(version)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use crate::{
+        instructions::Ix,
+        relocation::{Relocation, RelocationType},
+        sbpf_version::SbpfVersion,
+    };
+
+    #[test]
+    fn serialize_e2e() {
+        let b = hex!("38010000000000000a00000002000000");
+        let r = Relocation::from_bytes(&b).unwrap();
+        assert_eq!(r.r_offset, 0x138);
+        assert_eq!(r.r_type, RelocationType::R_BPF_64_32);
+        assert_eq!(r.r_sym, 2);
+        assert_eq!(r.to_bytes(), &b)
+    }
+
+    #[test]
+    fn other_type() {
+        let b = hex!("38010000000000003000000002000000");
+        let r = Relocation::from_bytes(&b).unwrap();
+        assert_eq!(r.r_type, RelocationType::Other(0x30));
+        assert_eq!(r.r_type.to_string(), "0x30");
+        assert_eq!(r.to_bytes(), &b);
+        let json = serde_json::to_string(&r.r_type).unwrap();
+        assert_eq!(json, r#"{"Other":48}"#);
+        assert_eq!(serde_json::from_str::<RelocationType>(&json).unwrap(), r.r_type);
+    }
+
+    #[test]
+    fn relocated_asm() {
+        let mut r = Relocation::from_bytes(&hex!("38010000000000000a00000002000000")).unwrap();
+        r.symbol = "sol_log_".to_string();
+        let call = Ix::from_bytes(&hex!("85100000FFFFFFFF"), SbpfVersion::V0).unwrap();
+        assert_eq!(r.to_asm(&call, SbpfVersion::V0).unwrap(), "call sol_log_");
+
+        let r = Relocation::from_bytes(&hex!("20010000000000000800000000000000")).unwrap();
+        let lddw = Ix::from_bytes(&hex!("18010000500100000000000000000000"), SbpfVersion::V0).unwrap();
+        assert_eq!(r.to_asm(&lddw, SbpfVersion::V0).unwrap(), "lddw r1, 0x150");
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+pub const EF_SBPF_V2: u32 = 0x20; // e_flags used for SBPFv2 before versions were numbered
+
+// The SBPF version a program targets, taken from e_flags in the ELF header.
+// Each version changes which opcodes are legal and what some of them mean.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum SbpfVersion {
+    #[default]
+    V0, // The original instruction set.
+    V1, // Dynamic stack frames.
+    V2, // PQR arithmetic, hor64 instead of lddw, moved memory instruction classes.
+    V3, // Static syscalls and stricter ELF headers.
+}
+
+impl From<u32> for SbpfVersion {
+    fn from(e_flags: u32) -> Self {
+        match e_flags {
+            1 => Self::V1,
+            2 | EF_SBPF_V2 => Self::V2,
+            3 => Self::V3,
+            _ => Self::V0, // Unknown flags decode with the original instruction set
+        }
+    }
+}
+
+impl From<SbpfVersion> for u32 {
+    fn from(val: SbpfVersion) -> Self {
+        match val {
+            SbpfVersion::V0 => 0,
+            SbpfVersion::V1 => 1,
+            SbpfVersion::V2 => 2,
+            SbpfVersion::V3 => 3,
+        }
+    }
+}
+
+impl From<SbpfVersion> for &str {
+    fn from(val: SbpfVersion) -> Self {
+        match val {
+            SbpfVersion::V0 => "v0",
+            SbpfVersion::V1 => "v1",
+            SbpfVersion::V2 => "v2",
+            SbpfVersion::V3 => "v3",
+        }
+    }
+}
+
+impl Display for SbpfVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(Into::<&str>::into(*self))
+    }
+}
+
+impl SbpfVersion {
+    /// Stack frames are sized by adjusting r10 rather than being fixed.
+    pub fn dynamic_stack_frames(self) -> bool {
+        self >= Self::V1
+    }
+
+    /// The PQR class (lmul, uhmul, shmul, udiv, urem, sdiv, srem) replaces
+    /// mul, div and mod.
+    pub fn enable_pqr(self) -> bool {
+        self >= Self::V2
+    }
+
+    /// Section addresses at or above the program region are used as is rather
+    /// than being offset into it.
+    pub fn enable_elf_vaddr(self) -> bool {
+        self != Self::V0
+    }
+
+    pub fn disable_neg(self) -> bool {
+        self >= Self::V2
+    }
+
+    /// callx takes its target from src rather than from the immediate.
+    pub fn callx_uses_src_reg(self) -> bool {
+        self >= Self::V2
+    }
+
+    /// lddw is removed and 64 bit immediates are loaded with mov and hor64.
+    pub fn disable_lddw(self) -> bool {
+        self >= Self::V2
+    }
+
+    pub fn disable_le(self) -> bool {
+        self >= Self::V2
+    }
+
+    /// ldx, st and stx move into the ALU classes, freed up by the PQR class.
+    pub fn move_memory_instruction_classes(self) -> bool {
+        self >= Self::V2
+    }
+
+    /// Syscalls get their own opcode and call immediates become relative to
+    /// the next instruction. exit is replaced by return.
+    pub fn static_syscalls(self) -> bool {
+        self >= Self::V3
+    }
+
+    pub fn enable_stricter_elf_headers(self) -> bool {
+        self >= Self::V3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sbpf_version::SbpfVersion;
+
+    #[test]
+    fn from_e_flags() {
+        assert_eq!(SbpfVersion::from(0), SbpfVersion::V0);
+        assert_eq!(SbpfVersion::from(1), SbpfVersion::V1);
+        assert_eq!(SbpfVersion::from(2), SbpfVersion::V2);
+        assert_eq!(SbpfVersion::from(0x20), SbpfVersion::V2);
+        assert_eq!(SbpfVersion::from(3), SbpfVersion::V3);
+        assert_eq!(u32::from(SbpfVersion::V3), 3);
+        assert!(SbpfVersion::V1.dynamic_stack_frames());
+        assert!(!SbpfVersion::V1.enable_pqr());
+        assert!(SbpfVersion::V3.static_syscalls());
+    }
+}
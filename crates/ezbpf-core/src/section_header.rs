@@ -1,11 +1,51 @@
 use std::{fmt::Debug, fmt::Display, io::Cursor};
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{cursor::ELFCursor, errors::EZBpfError, instructions::Ix};
+use crate::{
+    cursor::ELFCursor,
+    disassembler::{Item, LinearSweep},
+    errors::EZBpfError,
+    instructions::Ix,
+    sbpf_version::SbpfVersion,
+};
+
+pub const SECTION_HEADER_SIZE: u64 = 64; // Size of an Elf64_Shdr entry
+
+// Section Flags
+pub const SHF_WRITE: u64 = 0x01; // Section is writable at runtime
+pub const SHF_ALLOC: u64 = 0x02; // Occupies memory during execution
+pub const SHF_EXECINSTR: u64 = 0x04; // Executable
+pub const SHF_MERGE: u64 = 0x10; // Might be merged
+pub const SHF_STRINGS: u64 = 0x20; // Contains null-terminated strings
+pub const SHF_INFO_LINK: u64 = 0x40; // sh_info contains a section index
+pub const SHF_LINK_ORDER: u64 = 0x80; // Preserve order after combining
+pub const SHF_OS_NONCONFORMING: u64 = 0x100; // Non-standard OS specific handling required
+pub const SHF_GROUP: u64 = 0x200; // Section is member of a group
+pub const SHF_TLS: u64 = 0x400; // Section holds thread-local data
+pub const SHF_COMPRESSED: u64 = 0x800; // Section with compressed data
+pub const SHF_EXCLUDE: u64 = 0x80000000; // Excluded from linking unless referenced
+pub const SHF_MASKOS: u64 = 0x0ff00000; // OS-specific
+pub const SHF_MASKPROC: u64 = 0xf0000000; // Processor-specific
+
+// Every named flag with its readelf key letter
+const SECTION_FLAGS: [(u64, &str, char); 12] = [
+    (SHF_WRITE, "SHF_WRITE", 'W'),
+    (SHF_ALLOC, "SHF_ALLOC", 'A'),
+    (SHF_EXECINSTR, "SHF_EXECINSTR", 'X'),
+    (SHF_MERGE, "SHF_MERGE", 'M'),
+    (SHF_STRINGS, "SHF_STRINGS", 'S'),
+    (SHF_INFO_LINK, "SHF_INFO_LINK", 'I'),
+    (SHF_LINK_ORDER, "SHF_LINK_ORDER", 'L'),
+    (SHF_OS_NONCONFORMING, "SHF_OS_NONCONFORMING", 'O'),
+    (SHF_GROUP, "SHF_GROUP", 'G'),
+    (SHF_TLS, "SHF_TLS", 'T'),
+    (SHF_COMPRESSED, "SHF_COMPRESSED", 'C'),
+    (SHF_EXCLUDE, "SHF_EXCLUDE", 'E'),
+];
 
 #[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[repr(u32)]
 pub enum SectionHeaderType {
     SHT_NULL = 0x00,          // Section header table entry unused
@@ -26,13 +66,18 @@ pub enum SectionHeaderType {
     SHT_GROUP = 0x11,         // Section group
     SHT_SYMTAB_SHNDX = 0x12,  //	Extended section indices
     SHT_NUM = 0x13,           // Number of defined types.
+    SHT_LLVM_ADDRSIG = 0x6fff4c03,   // Address-significant symbols
+    SHT_GNU_ATTRIBUTES = 0x6ffffff5, // Object attributes
+    SHT_GNU_HASH = 0x6ffffff6,       // GNU-style hash table
+    SHT_GNU_verdef = 0x6ffffffd,     // Version definitions
+    SHT_GNU_verneed = 0x6ffffffe,    // Version requirements
+    SHT_GNU_versym = 0x6fffffff,     // Version symbol table
+    Other(u32),                      // Any other type, kept as-is
 }
 
-impl TryFrom<u32> for SectionHeaderType {
-    type Error = EZBpfError;
-
-    fn try_from(value: u32) -> Result<Self, Self::Error> {
-        Ok(match value {
+impl From<u32> for SectionHeaderType {
+    fn from(value: u32) -> Self {
+        match value {
             0x00 => Self::SHT_NULL,
             0x01 => Self::SHT_PROGBITS,
             0x02 => Self::SHT_SYMTAB,
@@ -51,14 +96,55 @@ impl TryFrom<u32> for SectionHeaderType {
             0x11 => Self::SHT_GROUP,
             0x12 => Self::SHT_SYMTAB_SHNDX,
             0x13 => Self::SHT_NUM,
-            _ => return Err(EZBpfError::InvalidSectionHeaderType),
-        })
+            0x6fff4c03 => Self::SHT_LLVM_ADDRSIG,
+            0x6ffffff5 => Self::SHT_GNU_ATTRIBUTES,
+            0x6ffffff6 => Self::SHT_GNU_HASH,
+            0x6ffffffd => Self::SHT_GNU_verdef,
+            0x6ffffffe => Self::SHT_GNU_verneed,
+            0x6fffffff => Self::SHT_GNU_versym,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl From<SectionHeaderType> for u32 {
+    fn from(val: SectionHeaderType) -> Self {
+        match val {
+            SectionHeaderType::SHT_NULL => 0x00,
+            SectionHeaderType::SHT_PROGBITS => 0x01,
+            SectionHeaderType::SHT_SYMTAB => 0x02,
+            SectionHeaderType::SHT_STRTAB => 0x03,
+            SectionHeaderType::SHT_RELA => 0x04,
+            SectionHeaderType::SHT_HASH => 0x05,
+            SectionHeaderType::SHT_DYNAMIC => 0x06,
+            SectionHeaderType::SHT_NOTE => 0x07,
+            SectionHeaderType::SHT_NOBITS => 0x08,
+            SectionHeaderType::SHT_REL => 0x09,
+            SectionHeaderType::SHT_SHLIB => 0x0A,
+            SectionHeaderType::SHT_DYNSYM => 0x0B,
+            SectionHeaderType::SHT_INIT_ARRAY => 0x0E,
+            SectionHeaderType::SHT_FINI_ARRAY => 0x0F,
+            SectionHeaderType::SHT_PREINIT_ARRAY => 0x10,
+            SectionHeaderType::SHT_GROUP => 0x11,
+            SectionHeaderType::SHT_SYMTAB_SHNDX => 0x12,
+            SectionHeaderType::SHT_NUM => 0x13,
+            SectionHeaderType::SHT_LLVM_ADDRSIG => 0x6fff4c03,
+            SectionHeaderType::SHT_GNU_ATTRIBUTES => 0x6ffffff5,
+            SectionHeaderType::SHT_GNU_HASH => 0x6ffffff6,
+            SectionHeaderType::SHT_GNU_verdef => 0x6ffffffd,
+            SectionHeaderType::SHT_GNU_verneed => 0x6ffffffe,
+            SectionHeaderType::SHT_GNU_versym => 0x6fffffff,
+            SectionHeaderType::Other(value) => value,
+        }
     }
 }
 
 impl Display for SectionHeaderType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(Into::<&str>::into(self.clone()))
+        match self {
+            Self::Other(value) => f.pad(&format!("{:#x}", value)),
+            t => f.pad(Into::<&str>::into(t.clone())),
+        }
     }
 }
 
@@ -83,15 +169,101 @@ impl From<SectionHeaderType> for &str {
             SectionHeaderType::SHT_GROUP => "SHT_GROUP",
             SectionHeaderType::SHT_SYMTAB_SHNDX => "SHT_SYMTAB_SHNDX",
             SectionHeaderType::SHT_NUM => "SHT_NUM",
+            SectionHeaderType::SHT_LLVM_ADDRSIG => "SHT_LLVM_ADDRSIG",
+            SectionHeaderType::SHT_GNU_ATTRIBUTES => "SHT_GNU_ATTRIBUTES",
+            SectionHeaderType::SHT_GNU_HASH => "SHT_GNU_HASH",
+            SectionHeaderType::SHT_GNU_verdef => "SHT_GNU_verdef",
+            SectionHeaderType::SHT_GNU_verneed => "SHT_GNU_verneed",
+            SectionHeaderType::SHT_GNU_versym => "SHT_GNU_versym",
+            SectionHeaderType::Other(_) => "SHT_UNKNOWN",
         }
     }
 }
 
+// Serialized as a list of flag names, with any bits that don't have a name
+// given in hex so that nothing is lost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SectionFlags(pub u64);
+
+impl SectionFlags {
+    pub fn contains(&self, flag: u64) -> bool {
+        self.0 & flag == flag
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = SECTION_FLAGS
+            .iter()
+            .filter(|(flag, _, _)| self.contains(*flag))
+            .map(|(_, name, _)| name.to_string())
+            .collect();
+        let unknown = self.unknown();
+        if unknown != 0 {
+            names.push(format!("{:#x}", unknown));
+        }
+        names
+    }
+
+    fn unknown(&self) -> u64 {
+        SECTION_FLAGS.iter().fold(self.0, |bits, (flag, _, _)| bits & !flag)
+    }
+}
+
+impl From<u64> for SectionFlags {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+// Renders the flags the way readelf does, with x, o and p for unknown,
+// OS-specific and processor-specific bits
+impl Display for SectionFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut s: String = SECTION_FLAGS
+            .iter()
+            .filter(|(flag, _, _)| self.contains(*flag))
+            .map(|(_, _, key)| key)
+            .collect();
+        let unknown = self.unknown();
+        if unknown & SHF_MASKOS != 0 {
+            s.push('o');
+        }
+        if unknown & SHF_MASKPROC != 0 {
+            s.push('p');
+        }
+        if unknown & !(SHF_MASKOS | SHF_MASKPROC) != 0 {
+            s.push('x');
+        }
+        f.pad(&s)
+    }
+}
+
+impl Serialize for SectionFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.names().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SectionFlags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut bits = 0;
+        for name in Vec::<String>::deserialize(deserializer)? {
+            bits |= match SECTION_FLAGS.iter().find(|(_, n, _)| *n == name) {
+                Some((flag, _, _)) => *flag,
+                None => name
+                    .strip_prefix("0x")
+                    .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| de::Error::custom(format!("unknown section flag {}", name)))?,
+            };
+        }
+        Ok(Self(bits))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SectionHeader {
     pub sh_name: u32, // An offset to a string in the .shstrtab section that represents the name of this section.
     pub sh_type: SectionHeaderType, // Identifies the type of this header.
-    pub sh_flags: u64, // Identifies the attributes of the section.
+    pub sh_flags: SectionFlags, // Identifies the attributes of the section.
     pub sh_addr: u64, // Virtual address of the section in memory, for sections that are loaded.
     pub sh_offset: u64, // Offset of the section in the file image.
     pub sh_size: u64, // Size in bytes of the section in the file image. May be 0.
@@ -109,8 +281,8 @@ impl SectionHeader {
 
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut b = self.sh_name.to_le_bytes().to_vec();
-        b.extend_from_slice(&(self.sh_type.clone() as u32).to_le_bytes());
-        b.extend_from_slice(&self.sh_flags.to_le_bytes());
+        b.extend_from_slice(&u32::from(self.sh_type.clone()).to_le_bytes());
+        b.extend_from_slice(&self.sh_flags.0.to_le_bytes());
         b.extend_from_slice(&self.sh_addr.to_le_bytes());
         b.extend_from_slice(&self.sh_offset.to_le_bytes());
         b.extend_from_slice(&self.sh_size.to_le_bytes());
@@ -131,14 +303,13 @@ pub struct SectionHeaderEntry {
 
 impl SectionHeaderEntry {
     pub fn to_ixs(&self) -> Result<Vec<Ix>, EZBpfError> {
-        if self.data.len() % 8 != 0 {
-            return Err(EZBpfError::InvalidDataLength);
-        }
         let mut ixs: Vec<Ix> = vec![];
-        if self.data.len() >= 8 {
-            let mut c = Cursor::new(self.data.as_slice());
-            while let Ok(ix) = c.read_ix() {
-                ixs.push(ix)
+        for slot in LinearSweep::new(&self.data, SbpfVersion::V0) {
+            if let Some(e) = slot.error {
+                return Err(e.shift(self.offset as u64));
+            }
+            if let Item::Ix(ix) = slot.item {
+                ixs.push(ix);
             }
         }
         Ok(ixs)
@@ -149,7 +320,10 @@ impl SectionHeaderEntry {
 mod test {
     use hex_literal::hex;
 
-    use crate::section_header::SectionHeader;
+    use crate::section_header::{
+        SectionFlags, SectionHeader, SectionHeaderType, SHF_ALLOC, SHF_EXECINSTR, SHF_MERGE,
+        SHF_STRINGS,
+    };
 
     #[test]
     fn serialize_e2e() {
@@ -157,4 +331,41 @@ mod test {
         let h = SectionHeader::from_bytes(&b).unwrap();
         assert_eq!(h.to_bytes(), &b)
     }
+
+    #[test]
+    fn other_types() {
+        let mut b = hex!("07000000030000000000000000000000000000000000000080000000000000000A00000000000000000000000000000001000000000000000000000000000000");
+        b[4..8].copy_from_slice(&0x6ffffff6u32.to_le_bytes());
+        let h = SectionHeader::from_bytes(&b).unwrap();
+        assert_eq!(h.sh_type, SectionHeaderType::SHT_GNU_HASH);
+        assert_eq!(h.to_bytes(), &b);
+
+        b[4..8].copy_from_slice(&0x80000000u32.to_le_bytes());
+        let h = SectionHeader::from_bytes(&b).unwrap();
+        assert_eq!(h.sh_type, SectionHeaderType::Other(0x80000000));
+        assert_eq!(h.sh_type.to_string(), "0x80000000");
+        assert_eq!(h.to_bytes(), &b);
+        let json = serde_json::to_string(&h.sh_type).unwrap();
+        assert_eq!(serde_json::from_str::<SectionHeaderType>(&json).unwrap(), h.sh_type);
+    }
+
+    #[test]
+    fn section_flags() {
+        let text = SectionFlags(SHF_ALLOC | SHF_EXECINSTR);
+        assert_eq!(text.to_string(), "AX");
+        assert_eq!(
+            serde_json::to_string(&text).unwrap(),
+            r#"["SHF_ALLOC","SHF_EXECINSTR"]"#
+        );
+        let comment = SectionFlags(SHF_MERGE | SHF_STRINGS);
+        assert_eq!(format!("{:>3}", comment), " MS");
+
+        // Bits without a name survive a round trip through JSON
+        let flags = SectionFlags(SHF_ALLOC | 0x1000 | 0x00100000 | 0x10000000);
+        assert_eq!(flags.to_string(), "Aopx");
+        let json = serde_json::to_string(&flags).unwrap();
+        assert_eq!(json, r#"["SHF_ALLOC","0x10101000"]"#);
+        assert_eq!(serde_json::from_str::<SectionFlags>(&json).unwrap(), flags);
+        assert!(serde_json::from_str::<SectionFlags>(r#"["SHF_BOGUS"]"#).is_err());
+    }
 }
@@ -1,37 +1,95 @@
-use std::{fmt::Debug, io::Cursor};
+use std::{fmt::Debug, str};
 
-use serde::{ser::Error, Deserialize, Serialize, Serializer};
-use serde_json::{error, Map, Value};
+use serde::{Deserialize, Serialize, Serializer};
 
-use crate::{cursor::ELFCursor, errors::EZBpfError, instructions::Ix};
+use crate::{
+    diagnostic::Recovery,
+    disassembler::{slots, Item, LinearSweep},
+    errors::{EZBpfError, ErrorContext},
+    instructions::Ix,
+    sbpf_version::SbpfVersion,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SectionHeaderEntry {
     pub label: String,
     pub offset: usize,
     pub data: Vec<u8>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub ixs: Vec<Ix>,
+    // Keeps the JSON of sections that decode cleanly as a list of instructions
+    #[serde(
+        rename = "ixs",
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_items",
+        default
+    )]
+    pub items: Vec<Item>,
     #[serde(skip_serializing_if = "String::is_empty")]
     pub utf8: String
 }
 
+// An item as written to JSON, with the slot a jump lands on alongside it.
+// Reading it back ignores the target, so it can't disagree with `off`.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ItemJson<'a> {
+    Jump {
+        #[serde(flatten)]
+        ix: &'a Ix,
+        target: usize,
+    },
+    Item(&'a Item),
+}
+
+fn serialize_items<S: Serializer>(items: &[Item], s: S) -> Result<S::Ok, S::Error> {
+    let pcs = slots(items);
+    s.collect_seq(items.iter().zip(pcs).map(|(item, pc)| {
+        match item.ix().and_then(|ix| Some((ix, ix.jump_target(pc)?))) {
+            Some((ix, target)) => ItemJson::Jump { ix, target },
+            None => ItemJson::Item(item),
+        }
+    }))
+}
+
 impl SectionHeaderEntry {
-    pub fn new(label: String, offset: usize, data: Vec<u8>) -> Result<Self, EZBpfError> {
+    pub fn new(
+        label: String,
+        offset: usize,
+        data: Vec<u8>,
+        version: SbpfVersion,
+    ) -> Result<Self, EZBpfError> {
+        Self::parse(label, offset, data, version, &mut Recovery::new(false))
+    }
+
+    // Slots that can't be decoded are kept as data and reported in a warning,
+    // whether or not parsing is lenient.
+    pub(crate) fn parse(
+        label: String,
+        offset: usize,
+        data: Vec<u8>,
+        version: SbpfVersion,
+        recovery: &mut Recovery,
+    ) -> Result<Self, EZBpfError> {
         let mut h = SectionHeaderEntry {
             label,
-            offset: offset,
+            offset,
             data,
-            ixs: vec![],
+            items: vec![],
             utf8: String::new()
         };
 
-        if &h.label == ".text\0" {
-            h.ixs = h.to_ixs()?;
+        if &h.label == ".text" {
+            for slot in LinearSweep::new(&h.data, version) {
+                if let Some(e) = slot.error {
+                    let e = h.error_context(e, h.items.len());
+                    let data = &h.data[slot.offset..slot.offset + slot.item.size()];
+                    recovery.warn(e, data);
+                }
+                h.items.push(slot.item);
+            }
         }
 
-        if let Ok(utf8) = String::from_utf8(h.data.clone()) {
-            h.utf8 = utf8;
+        if let Ok(utf8) = str::from_utf8(&h.data) {
+            h.utf8 = utf8.to_string();
         }
         Ok(h)
     }
@@ -40,28 +98,72 @@ impl SectionHeaderEntry {
         self.offset
     }
 
-    pub fn to_ixs(&self) -> Result<Vec<Ix>, EZBpfError> {
-        if self.data.len() % 8 != 0 {
-            return Err(EZBpfError::InvalidDataLength);
-        }
-        let mut ixs: Vec<Ix> = vec![];
-        if self.data.len() >= 8 {
-            let mut c = Cursor::new(self.data.as_slice());
-            while let Ok(ix) = c.read_ix() {
+    /// The decoded instructions, skipping any slots kept as data.
+    pub fn ixs(&self) -> impl Iterator<Item = &Ix> {
+        self.items.iter().filter_map(Item::ix)
+    }
+
+    pub fn to_ixs(&self, version: SbpfVersion) -> Result<Vec<Ix>, EZBpfError> {
+        let mut ixs = vec![];
+        for (index, slot) in LinearSweep::new(&self.data, version).enumerate() {
+            if let Some(e) = slot.error {
+                return Err(self.error_context(e, index));
+            }
+            if let Item::Ix(ix) = slot.item {
                 ixs.push(ix);
             }
         }
         Ok(ixs)
     }
 
+    fn error_context(&self, e: EZBpfError, index: usize) -> EZBpfError {
+        let e = e.shift(self.offset as u64);
+        match e {
+            // Not tied to any one instruction
+            EZBpfError::InvalidDataLength { .. } => e,
+            _ => e.context(ErrorContext::Instruction {
+                section: self.label.clone(),
+                index,
+            }),
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         self.data.clone()
     }
+
+    /// The data of the section, with the instructions in `items` encoded in
+    /// place of it if they no longer match what it decodes to. Fails on an
+    /// instruction that doesn't exist in `version`.
+    pub fn encode_items(&self, version: SbpfVersion) -> Result<Vec<u8>, EZBpfError> {
+        if self.items.is_empty() {
+            return Ok(self.data.clone());
+        }
+        let decoded = LinearSweep::new(&self.data, version).map(|slot| slot.item);
+        if decoded.eq(self.items.iter().cloned()) {
+            return Ok(self.data.clone());
+        }
+        let mut data = vec![];
+        for (index, item) in self.items.iter().enumerate() {
+            let b = item.to_bytes(version).map_err(|e| {
+                e.at(self.offset as u64 + data.len() as u64)
+                    .context(ErrorContext::Instruction {
+                        section: self.label.clone(),
+                        index,
+                    })
+            })?;
+            data.extend_from_slice(&b);
+        }
+        Ok(data)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{instructions::Ix, opcodes::OpCode, section_header_entry::SectionHeaderEntry};
+    use crate::{
+        instructions::Ix, opcodes::OpCode, sbpf_version::SbpfVersion,
+        section_header_entry::SectionHeaderEntry,
+    };
 
     #[test]
     fn serialize_e2e() {
@@ -71,9 +173,10 @@ mod test {
         ];
 
         let h = SectionHeaderEntry::new(
-            ".text\0".to_string(),
+            ".text".to_string(),
             128,
-            data.clone()
+            data.clone(),
+            SbpfVersion::V0
         ).unwrap();
 
         let ixs = vec![
@@ -92,14 +195,14 @@ mod test {
                 imm: 0,
             },
         ];
-        assert_eq!(ixs, h.to_ixs().unwrap());
+        assert_eq!(ixs, h.to_ixs(SbpfVersion::V0).unwrap());
 
         assert_eq!(
             data,
-            h.to_ixs()
+            h.to_ixs(SbpfVersion::V0)
                 .expect("Invalid IX")
                 .into_iter()
-                .flat_map(|i| i.to_bytes())
+                .flat_map(|i| i.to_bytes(SbpfVersion::V0).unwrap())
                 .collect::<Vec<u8>>()
         )
     }
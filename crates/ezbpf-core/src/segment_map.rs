@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    program_header::{ProgramHeader, ProgramType},
+    section_header::{SectionHeader, SectionHeaderType, SHF_ALLOC},
+};
+
+/// Which sections belong to which segment, like readelf's "Section to Segment
+/// mapping", along with the allocated sections the loadable segments don't
+/// cover properly.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentMap {
+    pub segments: Vec<Vec<usize>>, // Indices of the sections in each program header.
+    pub unmapped: Vec<usize>,      // SHF_ALLOC sections outside of every PT_LOAD segment.
+    pub straddling: Vec<usize>,    // Sections partly inside a PT_LOAD segment.
+}
+
+impl SegmentMap {
+    pub fn new(program_headers: &[ProgramHeader], section_headers: &[SectionHeader]) -> Self {
+        let segments = program_headers
+            .iter()
+            .map(|p| {
+                section_headers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| s.sh_type != SectionHeaderType::SHT_NULL)
+                    .filter(|(_, s)| section_in_segment(s, p))
+                    .map(|(i, _)| i)
+                    .collect()
+            })
+            .collect();
+        let mut unmapped = vec![];
+        let mut straddling = vec![];
+        let loads: Vec<&ProgramHeader> = program_headers
+            .iter()
+            .filter(|p| p.p_type == ProgramType::PT_LOAD)
+            .collect();
+        for (i, s) in section_headers.iter().enumerate() {
+            if s.sh_type == SectionHeaderType::SHT_NULL {
+                continue;
+            }
+            if loads.iter().any(|p| section_in_segment(s, p)) {
+                continue;
+            }
+            if loads.iter().any(|p| section_overlaps_segment(s, p)) {
+                straddling.push(i);
+            } else if s.sh_flags.contains(SHF_ALLOC) {
+                unmapped.push(i);
+            }
+        }
+        Self {
+            segments,
+            unmapped,
+            straddling,
+        }
+    }
+}
+
+// Whether [start, start + size) lies within [begin, begin + len). Empty
+// sections count when they start inside the segment, but not at its end.
+fn contained(start: u64, size: u64, begin: u64, len: u64) -> bool {
+    let end = begin.saturating_add(len);
+    match size {
+        0 => start >= begin && start < end,
+        _ => start >= begin && start.saturating_add(size) <= end,
+    }
+}
+
+fn overlaps(start: u64, size: u64, begin: u64, len: u64) -> bool {
+    size > 0 && start < begin.saturating_add(len) && begin < start.saturating_add(size)
+}
+
+// Sections in the file must lie within the file image of the segment, and
+// allocated ones within its memory image too. SHT_NOBITS sections take up no
+// room in the file, so only their addresses matter.
+fn section_in_segment(s: &SectionHeader, p: &ProgramHeader) -> bool {
+    // Unused entries don't describe anything
+    if p.p_type == ProgramType::PT_NULL {
+        return false;
+    }
+    let alloc = s.sh_flags.contains(SHF_ALLOC);
+    let in_memory = contained(s.sh_addr, s.sh_size, p.p_vaddr, p.p_memsz);
+    if s.sh_type == SectionHeaderType::SHT_NOBITS {
+        alloc && in_memory
+    } else {
+        contained(s.sh_offset, s.sh_size, p.p_offset, p.p_filesz) && (!alloc || in_memory)
+    }
+}
+
+fn section_overlaps_segment(s: &SectionHeader, p: &ProgramHeader) -> bool {
+    let in_memory = s.sh_flags.contains(SHF_ALLOC)
+        && overlaps(s.sh_addr, s.sh_size, p.p_vaddr, p.p_memsz);
+    if s.sh_type == SectionHeaderType::SHT_NOBITS {
+        in_memory
+    } else {
+        in_memory || overlaps(s.sh_offset, s.sh_size, p.p_offset, p.p_filesz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fixtures::{BSS_SECTION, STRICT_HEADER, SYSCALL_RELOC_64_32},
+        program::Program,
+        section_header::SectionHeaderType,
+        segment_map::SegmentMap,
+    };
+
+    #[test]
+    fn section_to_segment() {
+        let program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        let map = program.segment_map();
+        // .dynamic is only covered by PT_DYNAMIC, not by a loadable segment
+        assert_eq!(
+            map,
+            SegmentMap {
+                segments: vec![vec![1], vec![2], vec![4, 5, 6], vec![3]],
+                unmapped: vec![3],
+                straddling: vec![],
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&map).unwrap(),
+            r#"{"segments":[[1],[2],[4,5,6],[3]],"unmapped":[3],"straddling":[]}"#
+        );
+
+        // Growing .text into the .rodata segment leaves it in neither
+        let mut program = program;
+        program.section_headers[1].sh_size = 0x38;
+        let map = program.segment_map();
+        assert_eq!(map.segments[0], Vec::<usize>::new());
+        assert_eq!(map.straddling, vec![1]);
+    }
+
+    #[test]
+    fn nobits() {
+        let program = Program::from_bytes(&BSS_SECTION).unwrap();
+        let map = program.segment_map();
+        let bss = program
+            .section_headers
+            .iter()
+            .position(|h| h.sh_type == SectionHeaderType::SHT_NOBITS)
+            .unwrap();
+        assert!(map.segments.iter().any(|s| s.contains(&bss)));
+        assert!(!map.unmapped.contains(&bss));
+    }
+
+    #[test]
+    fn null_segments() {
+        let program = Program::from_bytes(&STRICT_HEADER).unwrap();
+        let map = program.segment_map();
+        assert_eq!(map.segments[0], vec![1]);
+        assert_eq!(map.segments[1], vec![2]);
+        assert!(map.segments[4].is_empty());
+        assert!(map.segments[5].is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+use std::str;
+
+use crate::errors::EZBpfError;
+
+// A view over an ELF string table such as .shstrtab, .strtab or .dynstr. Strings
+// are NUL-terminated and may be referenced from any offset, including the middle
+// of another string, as linkers merge names that share a suffix.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StringTable<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StringTable<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn get(&self, offset: u32) -> Result<&'a str, EZBpfError> {
+        let err = EZBpfError::InvalidString {
+            offset: Some(0),
+            value: offset as u64,
+        };
+        let b = self.data.get(offset as usize..).ok_or(err.clone())?;
+        let len = b.iter().position(|c| *c == 0).ok_or(err.clone())?;
+        str::from_utf8(&b[..len]).map_err(|_| err)
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::string_table::StringTable;
+
+    #[test]
+    fn get_strings() {
+        let t = StringTable::new(b"\0.text\0.rel.dyn\0.dynstr\0");
+        assert_eq!(t.get(0).unwrap(), "");
+        assert_eq!(t.get(1).unwrap(), ".text");
+        assert_eq!(t.get(7).unwrap(), ".rel.dyn");
+        // Tail-merged name sharing the end of .rel.dyn
+        assert_eq!(t.get(11).unwrap(), ".dyn");
+        assert_eq!(t.get(16).unwrap(), ".dynstr");
+        assert!(t.get(24).is_err());
+        assert!(StringTable::new(b".text").get(0).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use std::{fmt::Display, io::Cursor};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cursor::ELFCursor, errors::EZBpfError};
+
+pub const SYMBOL_SIZE: u64 = 24; // Size of an Elf64_Sym entry
+
+// Special section indices
+pub const SHN_UNDEF: u16 = 0x0000;
+pub const SHN_ABS: u16 = 0xfff1;
+pub const SHN_COMMON: u16 = 0xfff2;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SymbolBinding {
+    STB_LOCAL = 0x00,       // Not visible outside the object file.
+    STB_GLOBAL = 0x01,      // Visible to all object files being combined.
+    STB_WEAK = 0x02,        // Global symbol with lower precedence.
+    STB_GNU_UNIQUE = 0x0a,  // Unique symbol (GNU extension).
+    Other(u8),              // Any other binding, such as an OS or processor specific one, kept as-is.
+}
+
+impl From<u8> for SymbolBinding {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::STB_LOCAL,
+            0x01 => Self::STB_GLOBAL,
+            0x02 => Self::STB_WEAK,
+            0x0a => Self::STB_GNU_UNIQUE,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl From<SymbolBinding> for u8 {
+    fn from(val: SymbolBinding) -> Self {
+        match val {
+            SymbolBinding::STB_LOCAL => 0x00,
+            SymbolBinding::STB_GLOBAL => 0x01,
+            SymbolBinding::STB_WEAK => 0x02,
+            SymbolBinding::STB_GNU_UNIQUE => 0x0a,
+            SymbolBinding::Other(value) => value,
+        }
+    }
+}
+
+impl Display for SymbolBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(value) => f.pad(&other_str(*value)),
+            b => f.pad(Into::<&str>::into(b.clone())),
+        }
+    }
+}
+
+impl From<SymbolBinding> for &str {
+    fn from(val: SymbolBinding) -> Self {
+        match val {
+            SymbolBinding::STB_LOCAL => "LOCAL",
+            SymbolBinding::STB_GLOBAL => "GLOBAL",
+            SymbolBinding::STB_WEAK => "WEAK",
+            SymbolBinding::STB_GNU_UNIQUE => "UNIQUE",
+            SymbolBinding::Other(_) => "UNKNOWN",
+        }
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SymbolType {
+    STT_NOTYPE = 0x00,     // Symbol type is unspecified.
+    STT_OBJECT = 0x01,     // Symbol is a data object.
+    STT_FUNC = 0x02,       // Symbol is a code object.
+    STT_SECTION = 0x03,    // Symbol is associated with a section.
+    STT_FILE = 0x04,       // Symbol's name is a file name.
+    STT_COMMON = 0x05,     // Symbol is a common data object.
+    STT_TLS = 0x06,        // Symbol is a thread-local data object.
+    STT_GNU_IFUNC = 0x0a,  // Symbol is an indirect code object (GNU extension).
+    Other(u8),             // Any other type, such as an OS or processor specific one, kept as-is.
+}
+
+impl From<u8> for SymbolType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::STT_NOTYPE,
+            0x01 => Self::STT_OBJECT,
+            0x02 => Self::STT_FUNC,
+            0x03 => Self::STT_SECTION,
+            0x04 => Self::STT_FILE,
+            0x05 => Self::STT_COMMON,
+            0x06 => Self::STT_TLS,
+            0x0a => Self::STT_GNU_IFUNC,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl From<SymbolType> for u8 {
+    fn from(val: SymbolType) -> Self {
+        match val {
+            SymbolType::STT_NOTYPE => 0x00,
+            SymbolType::STT_OBJECT => 0x01,
+            SymbolType::STT_FUNC => 0x02,
+            SymbolType::STT_SECTION => 0x03,
+            SymbolType::STT_FILE => 0x04,
+            SymbolType::STT_COMMON => 0x05,
+            SymbolType::STT_TLS => 0x06,
+            SymbolType::STT_GNU_IFUNC => 0x0a,
+            SymbolType::Other(value) => value,
+        }
+    }
+}
+
+impl Display for SymbolType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Other(value) => f.pad(&other_str(*value)),
+            t => f.pad(Into::<&str>::into(t.clone())),
+        }
+    }
+}
+
+impl From<SymbolType> for &str {
+    fn from(val: SymbolType) -> Self {
+        match val {
+            SymbolType::STT_NOTYPE => "NOTYPE",
+            SymbolType::STT_OBJECT => "OBJECT",
+            SymbolType::STT_FUNC => "FUNC",
+            SymbolType::STT_SECTION => "SECTION",
+            SymbolType::STT_FILE => "FILE",
+            SymbolType::STT_COMMON => "COMMON",
+            SymbolType::STT_TLS => "TLS",
+            SymbolType::STT_GNU_IFUNC => "IFUNC",
+            SymbolType::Other(_) => "UNKNOWN",
+        }
+    }
+}
+
+// A binding or type without a name, written the way readelf does
+fn other_str(value: u8) -> String {
+    match value {
+        0x0a..=0x0c => format!("<OS specific>: {}", value),
+        0x0d..=0x0f => format!("<processor specific>: {}", value),
+        _ => format!("<unknown>: {}", value),
+    }
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SymbolVisibility {
+    STV_DEFAULT = 0x00,   // Visibility is specified by the binding type.
+    STV_INTERNAL = 0x01,  // Reserved, treated as hidden.
+    STV_HIDDEN = 0x02,    // Not visible to other components.
+    STV_PROTECTED = 0x03, // Visible but not preemptable.
+}
+
+impl From<u8> for SymbolVisibility {
+    fn from(value: u8) -> Self {
+        match value & 0x03 {
+            0x00 => Self::STV_DEFAULT,
+            0x01 => Self::STV_INTERNAL,
+            0x02 => Self::STV_HIDDEN,
+            _ => Self::STV_PROTECTED,
+        }
+    }
+}
+
+impl Display for SymbolVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(Into::<&str>::into(self.clone()))
+    }
+}
+
+impl From<SymbolVisibility> for &str {
+    fn from(val: SymbolVisibility) -> Self {
+        match val {
+            SymbolVisibility::STV_DEFAULT => "DEFAULT",
+            SymbolVisibility::STV_INTERNAL => "INTERNAL",
+            SymbolVisibility::STV_HIDDEN => "HIDDEN",
+            SymbolVisibility::STV_PROTECTED => "PROTECTED",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String, // Name of the symbol, resolved through the linked string table.
+    pub st_name: u32, // An offset to a string in the linked string table that represents the name of this symbol.
+    pub st_bind: SymbolBinding, // Upper nibble of st_info, the linkage visibility of this symbol.
+    pub st_type: SymbolType, // Lower nibble of st_info, the kind of entity this symbol describes.
+    pub st_other: u8, // Visibility in the lower two bits, the rest kept as found.
+    pub st_shndx: u16, // Index of the section this symbol is defined in, or one of the special SHN_* values.
+    pub st_value: u64, // Address of the symbol.
+    pub st_size: u64, // Size of the object this symbol refers to. May be 0.
+}
+
+impl Symbol {
+    pub fn from_bytes(b: &[u8]) -> Result<Self, EZBpfError> {
+        let mut c = Cursor::new(b);
+        c.read_symbol()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.st_name.to_le_bytes().to_vec();
+        b.push(u8::from(self.st_bind.clone()) << 4 | u8::from(self.st_type.clone()));
+        b.push(self.st_other);
+        b.extend_from_slice(&self.st_shndx.to_le_bytes());
+        b.extend_from_slice(&self.st_value.to_le_bytes());
+        b.extend_from_slice(&self.st_size.to_le_bytes());
+        b
+    }
+
+    pub fn visibility(&self) -> SymbolVisibility {
+        SymbolVisibility::from(self.st_other)
+    }
+
+    pub fn shndx_str(&self) -> String {
+        match self.st_shndx {
+            SHN_UNDEF => "UND".to_string(),
+            SHN_ABS => "ABS".to_string(),
+            SHN_COMMON => "COM".to_string(),
+            i => i.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use crate::symbol::{Symbol, SymbolBinding, SymbolType, SymbolVisibility};
+
+    #[test]
+    fn serialize_e2e() {
+        let b = hex!("010000001200010040010000000000004800000000000000");
+        let s = Symbol::from_bytes(&b).unwrap();
+        assert_eq!(s.st_bind, SymbolBinding::STB_GLOBAL);
+        assert_eq!(s.st_type, SymbolType::STT_FUNC);
+        assert_eq!(s.visibility(), SymbolVisibility::STV_DEFAULT);
+        assert_eq!(s.st_shndx, 1);
+        assert_eq!(s.st_value, 0x140);
+        assert_eq!(s.st_size, 72);
+        assert_eq!(s.to_bytes(), &b)
+    }
+
+    #[test]
+    fn st_other() {
+        // Bits above the visibility survive a round trip
+        let b = hex!("010000001282010040010000000000004800000000000000");
+        let s = Symbol::from_bytes(&b).unwrap();
+        assert_eq!(s.visibility(), SymbolVisibility::STV_HIDDEN);
+        assert_eq!(s.to_bytes(), &b)
+    }
+
+    #[test]
+    fn other_binding_and_type() {
+        // An OS specific binding and a processor specific type
+        let b = hex!("01000000bd00010040010000000000004800000000000000");
+        let s = Symbol::from_bytes(&b).unwrap();
+        assert_eq!(s.st_bind, SymbolBinding::Other(0x0b));
+        assert_eq!(s.st_type, SymbolType::Other(0x0d));
+        assert_eq!(s.st_bind.to_string(), "<OS specific>: 11");
+        assert_eq!(s.st_type.to_string(), "<processor specific>: 13");
+        assert_eq!(SymbolType::from(7).to_string(), "<unknown>: 7");
+        assert_eq!(s.to_bytes(), &b);
+        let json = serde_json::to_string(&s.st_bind).unwrap();
+        assert_eq!(json, r#"{"Other":11}"#);
+        assert_eq!(serde_json::from_str::<SymbolBinding>(&json).unwrap(), s.st_bind);
+    }
+}
@@ -0,0 +1,421 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::murmur3::hash_symbol_name;
+
+/// An argument of a syscall, passed in r1 to r5 in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyscallArg {
+    pub name: &'static str,
+    pub ty: &'static str,
+    pub len: Option<usize>, // Index of the argument holding the number of elements a pointer points to.
+}
+
+/// What a syscall takes and what it leaves in r0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyscallSignature {
+    pub name: &'static str,
+    pub args: &'static [SyscallArg],
+    pub returns: &'static str,
+}
+
+const fn arg(name: &'static str, ty: &'static str) -> SyscallArg {
+    SyscallArg { name, ty, len: None }
+}
+
+// A pointer to `len` elements, with the count in argument `len`
+const fn slice(name: &'static str, ty: &'static str, len: usize) -> SyscallArg {
+    SyscallArg { name, ty, len: Some(len) }
+}
+
+// Syscalls provided by the Solana runtime. Programs call them by the murmur3
+// hash of their name.
+pub const SYSCALLS: &[SyscallSignature] = &[
+    SyscallSignature {
+        name: "abort",
+        args: &[],
+        returns: "never returns",
+    },
+    SyscallSignature {
+        name: "sol_panic_",
+        args: &[
+            slice("file", "*const u8", 1),
+            arg("len", "u64"),
+            arg("line", "u64"),
+            arg("column", "u64"),
+        ],
+        returns: "never returns",
+    },
+    SyscallSignature {
+        name: "sol_log_",
+        args: &[slice("message", "*const u8", 1), arg("len", "u64")],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_log_64_",
+        args: &[
+            arg("arg1", "u64"),
+            arg("arg2", "u64"),
+            arg("arg3", "u64"),
+            arg("arg4", "u64"),
+            arg("arg5", "u64"),
+        ],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_log_compute_units_",
+        args: &[],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_log_pubkey",
+        args: &[arg("pubkey", "*const Pubkey")],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_log_data",
+        args: &[slice("data", "*const &[u8]", 1), arg("data_len", "u64")],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_create_program_address",
+        args: &[
+            slice("seeds", "*const &[u8]", 1),
+            arg("seeds_len", "u64"),
+            arg("program_id", "*const Pubkey"),
+            arg("address", "*mut Pubkey"),
+        ],
+        returns: "0 on success, 1 if the address is on the curve",
+    },
+    SyscallSignature {
+        name: "sol_try_find_program_address",
+        args: &[
+            slice("seeds", "*const &[u8]", 1),
+            arg("seeds_len", "u64"),
+            arg("program_id", "*const Pubkey"),
+            arg("address", "*mut Pubkey"),
+            arg("bump_seed", "*mut u8"),
+        ],
+        returns: "0 on success, 1 if no bump seed works",
+    },
+    SyscallSignature {
+        name: "sol_sha256",
+        args: &[
+            slice("vals", "*const &[u8]", 1),
+            arg("vals_len", "u64"),
+            arg("hash_result", "*mut [u8; 32]"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_keccak256",
+        args: &[
+            slice("vals", "*const &[u8]", 1),
+            arg("vals_len", "u64"),
+            arg("hash_result", "*mut [u8; 32]"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_blake3",
+        args: &[
+            slice("vals", "*const &[u8]", 1),
+            arg("vals_len", "u64"),
+            arg("hash_result", "*mut [u8; 32]"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_secp256k1_recover",
+        args: &[
+            arg("hash", "*const [u8; 32]"),
+            arg("recovery_id", "u64"),
+            arg("signature", "*const [u8; 64]"),
+            arg("result", "*mut [u8; 64]"),
+        ],
+        returns: "0 on success, an error code otherwise",
+    },
+    SyscallSignature {
+        name: "sol_poseidon",
+        args: &[
+            arg("parameters", "u64"),
+            arg("endianness", "u64"),
+            slice("vals", "*const &[u8]", 3),
+            arg("vals_len", "u64"),
+            arg("hash_result", "*mut [u8; 32]"),
+        ],
+        returns: "0 on success, an error code otherwise",
+    },
+    SyscallSignature {
+        name: "sol_curve_validate_point",
+        args: &[arg("curve_id", "u64"), arg("point", "*const u8"), arg("result", "*mut u8")],
+        returns: "0 if the point is valid",
+    },
+    SyscallSignature {
+        name: "sol_curve_group_op",
+        args: &[
+            arg("curve_id", "u64"),
+            arg("group_op", "u64"),
+            arg("left", "*const u8"),
+            arg("right", "*const u8"),
+            arg("result", "*mut u8"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_curve_multiscalar_mul",
+        args: &[
+            arg("curve_id", "u64"),
+            slice("scalars", "*const u8", 3),
+            slice("points", "*const u8", 3),
+            arg("points_len", "u64"),
+            arg("result", "*mut u8"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_curve_pairing_map",
+        args: &[arg("curve_id", "u64"), arg("point", "*const u8"), arg("result", "*mut u8")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_alt_bn128_group_op",
+        args: &[
+            arg("group_op", "u64"),
+            slice("input", "*const u8", 2),
+            arg("input_size", "u64"),
+            arg("result", "*mut u8"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_alt_bn128_compression",
+        args: &[
+            arg("op", "u64"),
+            slice("input", "*const u8", 2),
+            arg("input_size", "u64"),
+            arg("result", "*mut u8"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_big_mod_exp",
+        args: &[arg("params", "*const BigModExpParams"), arg("result", "*mut u8")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_get_sysvar",
+        args: &[
+            arg("sysvar_id", "*const Pubkey"),
+            slice("result", "*mut u8", 3),
+            arg("offset", "u64"),
+            arg("length", "u64"),
+        ],
+        returns: "0 on success, an error code otherwise",
+    },
+    SyscallSignature {
+        name: "sol_get_clock_sysvar",
+        args: &[arg("result", "*mut Clock")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_get_epoch_schedule_sysvar",
+        args: &[arg("result", "*mut EpochSchedule")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_get_fees_sysvar",
+        args: &[arg("result", "*mut Fees")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_get_rent_sysvar",
+        args: &[arg("result", "*mut Rent")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_get_epoch_rewards_sysvar",
+        args: &[arg("result", "*mut EpochRewards")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_get_last_restart_slot",
+        args: &[arg("result", "*mut LastRestartSlot")],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_get_epoch_stake",
+        args: &[arg("vote_address", "*const Pubkey")],
+        returns: "the stake delegated to the vote account",
+    },
+    SyscallSignature {
+        name: "sol_memcpy_",
+        args: &[slice("dst", "*mut u8", 2), slice("src", "*const u8", 2), arg("n", "u64")],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_memmove_",
+        args: &[slice("dst", "*mut u8", 2), slice("src", "*const u8", 2), arg("n", "u64")],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_memcmp_",
+        args: &[
+            slice("s1", "*const u8", 2),
+            slice("s2", "*const u8", 2),
+            arg("n", "u64"),
+            arg("result", "*mut i32"),
+        ],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_memset_",
+        args: &[slice("s", "*mut u8", 2), arg("c", "u64"), arg("n", "u64")],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_invoke_signed_c",
+        args: &[
+            arg("instruction", "*const SolInstruction"),
+            slice("account_infos", "*const SolAccountInfo", 2),
+            arg("account_infos_len", "u64"),
+            slice("signers_seeds", "*const SolSignerSeeds", 4),
+            arg("signers_seeds_len", "u64"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_invoke_signed_rust",
+        args: &[
+            arg("instruction", "*const Instruction"),
+            slice("account_infos", "*const AccountInfo", 2),
+            arg("account_infos_len", "u64"),
+            slice("signers_seeds", "*const &[&[u8]]", 4),
+            arg("signers_seeds_len", "u64"),
+        ],
+        returns: "0 on success",
+    },
+    SyscallSignature {
+        name: "sol_set_return_data",
+        args: &[slice("data", "*const u8", 1), arg("len", "u64")],
+        returns: "nothing",
+    },
+    SyscallSignature {
+        name: "sol_get_return_data",
+        args: &[slice("data", "*mut u8", 1), arg("len", "u64"), arg("program_id", "*mut Pubkey")],
+        returns: "length of the return data, 0 if there is none",
+    },
+    SyscallSignature {
+        name: "sol_get_processed_sibling_instruction",
+        args: &[
+            arg("index", "u64"),
+            arg("meta", "*mut ProcessedSiblingInstruction"),
+            arg("program_id", "*mut Pubkey"),
+            arg("data", "*mut u8"),
+            arg("accounts", "*mut AccountMeta"),
+        ],
+        returns: "1 if the instruction was found, 0 otherwise",
+    },
+    SyscallSignature {
+        name: "sol_get_stack_height",
+        args: &[],
+        returns: "the current invoke stack height",
+    },
+    SyscallSignature {
+        name: "sol_remaining_compute_units",
+        args: &[],
+        returns: "the compute units left",
+    },
+    SyscallSignature {
+        name: "sol_alloc_free_",
+        args: &[arg("size", "u64"), arg("free_addr", "u64")],
+        returns: "address of the allocation, 0 on failure",
+    },
+];
+
+/// The syscalls recognised when disassembling, by the hash they are called
+/// by. Starts out with the built-in Solana ones, and runtimes with syscalls of
+/// their own can add those.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallRegistry {
+    names: HashMap<u32, String>,
+}
+
+impl Default for SyscallRegistry {
+    fn default() -> Self {
+        Self {
+            names: SYSCALLS
+                .iter()
+                .map(|s| (hash_symbol_name(s.name.as_bytes()), s.name.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl SyscallRegistry {
+    /// Adds `name`, returning the hash it is called by.
+    pub fn register(&mut self, name: &str) -> u32 {
+        let hash = hash_symbol_name(name.as_bytes());
+        self.names.insert(hash, name.to_string());
+        hash
+    }
+
+    /// The name of the syscall called by `hash`, if it is a known one.
+    pub fn name(&self, hash: u32) -> Option<&str> {
+        self.names.get(&hash).map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.name(hash_symbol_name(name.as_bytes())) == Some(name)
+    }
+}
+
+static BUILT_IN: LazyLock<SyscallRegistry> = LazyLock::new(SyscallRegistry::default);
+
+/// The name of the built-in syscall called by `hash`.
+pub fn syscall_name(hash: u32) -> Option<&'static str> {
+    BUILT_IN.name(hash)
+}
+
+/// The signature of the built-in syscall `name`.
+pub fn signature(name: &str) -> Option<&'static SyscallSignature> {
+    SYSCALLS.iter().find(|s| s.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syscalls::{signature, syscall_name, SyscallRegistry, SYSCALLS};
+
+    #[test]
+    fn lookup() {
+        assert_eq!(syscall_name(0x207559bd), Some("sol_log_"));
+        assert_eq!(syscall_name(0xb6fc1a11), Some("abort"));
+        assert_eq!(syscall_name(0), None);
+
+        let mut syscalls = SyscallRegistry::default();
+        let hash = syscalls.register("my_runtime_syscall");
+        assert_eq!(syscalls.name(hash), Some("my_runtime_syscall"));
+        assert!(syscalls.contains("my_runtime_syscall"));
+        assert!(syscalls.contains("sol_log_"));
+        // Only the registry it was added to knows of it
+        assert_eq!(syscall_name(hash), None);
+        assert!(!SyscallRegistry::default().contains("my_runtime_syscall"));
+    }
+
+    #[test]
+    fn signatures() {
+        let log = signature("sol_log_").unwrap();
+        assert_eq!(log.args[0].name, "message");
+        assert_eq!(log.args[0].len, Some(1));
+        assert_eq!(signature("my_runtime_syscall"), None);
+        // At most five arguments fit in r1 to r5, and lengths must be u64
+        // arguments of the same syscall
+        for s in SYSCALLS {
+            assert!(s.args.len() <= 5, "{}", s.name);
+            for a in s.args {
+                if let Some(len) = a.len {
+                    assert_eq!(s.args[len].ty, "u64", "{}", s.name);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    elf_header::ELF_HEADER_SIZE,
+    errors::EZBpfError,
+    program::Program,
+    program_header::{ProgramType, PF_X, PROGRAM_HEADER_SIZE},
+    relocation::RelocationType,
+    section_header::{SectionHeaderType, SECTION_HEADER_SIZE, SHF_WRITE},
+};
+
+pub const MAX_PROGRAM_SIZE: u64 = 10 * 1024 * 1024; // Largest account a program can be deployed to
+pub const SECTION_NAME_LENGTH_MAXIMUM: usize = 15; // Longest name the loader reads, 16 bytes with the NUL
+
+// A reason the Solana ELF loader would refuse to deploy the program
+#[derive(Debug, Clone, Error, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind")]
+pub enum ValidationError {
+    #[error("Non-standard ELF header, {field} is {value:#x}")]
+    InvalidFileHeader { field: &'static str, value: u64 },
+    #[error("Expected exactly one .text section, found {count}")]
+    NotOneTextSection { count: usize },
+    #[error("Expected exactly one executable PT_LOAD segment, found {count}")]
+    NotOneTextSegment { count: usize },
+    #[error(".text size {size:#x} is not a multiple of 8")]
+    InvalidTextSize { size: u64 },
+    #[error("Entrypoint {entry:#x} is outside of .text")]
+    EntrypointOutOfBounds { entry: u64 },
+    #[error("Entrypoint {entry:#x} is not aligned to an instruction")]
+    UnalignedEntrypoint { entry: u64 },
+    #[error("Section header #0 is {sh_type}, not SHT_NULL")]
+    FirstSectionNotNull { sh_type: SectionHeaderType },
+    #[error("Section #{index} name {name:?} is longer than {SECTION_NAME_LENGTH_MAXIMUM} bytes")]
+    SectionNameTooLong { index: usize, name: String },
+    #[error("Writable section {name} is not supported")]
+    WritableSection { index: usize, name: String },
+    #[error("Section {name} extends past the end of the file")]
+    SectionOutOfBounds { index: usize, name: String },
+    #[error("Section {name} overlaps the {table}")]
+    SectionOverlap {
+        index: usize,
+        name: String,
+        table: &'static str,
+    },
+    #[error("Section {name} is not in file order")]
+    SectionNotInOrder { index: usize, name: String },
+    #[error("Unsupported relocation {r_type} at {offset:#x}")]
+    UnsupportedRelocation {
+        offset: u64,
+        r_type: RelocationType,
+    },
+    #[error("Program header #{index} alignment {align:#x} is not a power of two")]
+    InvalidSegmentAlignment { index: usize, align: u64 },
+    #[error("Program header #{index} offset {offset:#x} and address {vaddr:#x} differ modulo alignment {align:#x}")]
+    MisalignedSegment {
+        index: usize,
+        offset: u64,
+        vaddr: u64,
+        align: u64,
+    },
+    #[error("Program header #{index} overlaps the segment before it")]
+    SegmentOverlap { index: usize },
+    #[error("Program header #{index} extends past the end of the file")]
+    SegmentOutOfBounds { index: usize },
+    #[error("Program is {size} bytes, more than the maximum of {MAX_PROGRAM_SIZE}")]
+    ProgramTooLarge { size: u64 },
+    #[error("Program can't be written: {error}")]
+    Unwritable { error: EZBpfError },
+}
+
+impl Program {
+    /// Run the checks the Solana ELF loader performs when a program is
+    /// deployed and return every violation rather than stopping at the first.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        let len = match self.file_size() {
+            Ok(len) => {
+                if len > MAX_PROGRAM_SIZE {
+                    errors.push(ValidationError::ProgramTooLarge { size: len });
+                }
+                len
+            }
+            // Without a size nothing can be said to be past the end of the file
+            Err(error) => {
+                errors.push(ValidationError::Unwritable { error });
+                u64::MAX
+            }
+        };
+
+        // From SBPFv3 on the loader finds everything through the program
+        // headers and section headers are optional
+        let sectionless =
+            self.section_headers.is_empty() && self.sbpf_version.enable_stricter_elf_headers();
+        let h = &self.elf_header;
+        errors.extend(h.non_standard_fields().into_iter().filter_map(|e| match e {
+            EZBpfError::NonStandardElfHeader { field, value, .. } => {
+                Some(ValidationError::InvalidFileHeader { field, value })
+            }
+            _ => None,
+        }));
+        for (valid, field, value) in [
+            (h.e_ehsize as u64 == ELF_HEADER_SIZE, "e_ehsize", h.e_ehsize),
+            (h.e_phentsize as u64 == PROGRAM_HEADER_SIZE, "e_phentsize", h.e_phentsize),
+            (
+                h.e_shentsize as u64 == SECTION_HEADER_SIZE || sectionless,
+                "e_shentsize",
+                h.e_shentsize,
+            ),
+            (h.e_shstrndx < h.e_shnum || sectionless, "e_shstrndx", h.e_shstrndx),
+        ] {
+            if !valid {
+                errors.push(ValidationError::InvalidFileHeader {
+                    field,
+                    value: value as u64,
+                });
+            }
+        }
+
+        self.validate_segments(len, &mut errors);
+        if sectionless {
+            self.validate_text_segment(&mut errors);
+        } else {
+            self.validate_sections(len, &mut errors);
+        }
+
+        for r in &self.relocations {
+            match r.r_type {
+                RelocationType::R_BPF_64_64
+                | RelocationType::R_BPF_64_RELATIVE
+                | RelocationType::R_BPF_64_32 => {}
+                _ => errors.push(ValidationError::UnsupportedRelocation {
+                    offset: r.r_offset,
+                    r_type: r.r_type.clone(),
+                }),
+            }
+        }
+        errors
+    }
+
+    fn validate_segments(&self, len: u64, errors: &mut Vec<ValidationError>) {
+        // PT_LOAD segments have to be in ascending order without overlapping
+        // in memory, and their contents have to be in the file
+        let mut vaddr_end = 0;
+        for (index, p) in self.program_headers.iter().enumerate() {
+            if p.p_align > 1 {
+                if !p.p_align.is_power_of_two() {
+                    errors.push(ValidationError::InvalidSegmentAlignment {
+                        index,
+                        align: p.p_align,
+                    });
+                } else if p.p_offset % p.p_align != p.p_vaddr % p.p_align {
+                    errors.push(ValidationError::MisalignedSegment {
+                        index,
+                        offset: p.p_offset,
+                        vaddr: p.p_vaddr,
+                        align: p.p_align,
+                    });
+                }
+            }
+            if p.p_type != ProgramType::PT_LOAD {
+                continue;
+            }
+            if p.p_vaddr < vaddr_end {
+                errors.push(ValidationError::SegmentOverlap { index });
+            }
+            vaddr_end = p.p_vaddr.saturating_add(p.p_memsz);
+            if p.p_offset.saturating_add(p.p_filesz) > len {
+                errors.push(ValidationError::SegmentOutOfBounds { index });
+            }
+        }
+    }
+
+    // Without section headers the bytecode is the one executable PT_LOAD
+    // segment, which takes the place of .text
+    fn validate_text_segment(&self, errors: &mut Vec<ValidationError>) {
+        let text: Vec<_> = self
+            .program_headers
+            .iter()
+            .filter(|p| p.p_type == ProgramType::PT_LOAD && p.p_flags.0 & PF_X as u32 != 0)
+            .collect();
+        match text[..] {
+            [p] => validate_text(self.elf_header.e_entry, p.p_vaddr, p.p_filesz, errors),
+            _ => errors.push(ValidationError::NotOneTextSegment { count: text.len() }),
+        }
+    }
+
+    fn validate_sections(&self, len: u64, errors: &mut Vec<ValidationError>) {
+        let h = &self.elf_header;
+        if let Some(s) = self.section_headers.first() {
+            if s.sh_type != SectionHeaderType::SHT_NULL {
+                errors.push(ValidationError::FirstSectionNotNull {
+                    sh_type: s.sh_type.clone(),
+                });
+            }
+        }
+
+        let tables = [
+            ("ELF header", 0, ELF_HEADER_SIZE),
+            (
+                "program header table",
+                h.e_phoff,
+                h.e_phoff
+                    .saturating_add(self.program_headers.len() as u64 * PROGRAM_HEADER_SIZE),
+            ),
+            (
+                "section header table",
+                h.e_shoff,
+                h.e_shoff
+                    .saturating_add(self.section_headers.len() as u64 * SECTION_HEADER_SIZE),
+            ),
+        ];
+        let mut offset = 0;
+        let mut text = 0;
+        for (index, (s, e)) in self
+            .section_headers
+            .iter()
+            .zip(&self.section_header_entries)
+            .enumerate()
+        {
+            let name = e.label.clone();
+            if name.len() > SECTION_NAME_LENGTH_MAXIMUM {
+                errors.push(ValidationError::SectionNameTooLong {
+                    index,
+                    name: name.clone(),
+                });
+            }
+            if name.starts_with(".bss")
+                || (s.sh_flags.contains(SHF_WRITE)
+                    && name.starts_with(".data")
+                    && !name.starts_with(".data.rel"))
+            {
+                errors.push(ValidationError::WritableSection {
+                    index,
+                    name: name.clone(),
+                });
+            }
+            if name == ".text" {
+                text += 1;
+                validate_text(h.e_entry, s.sh_addr, s.sh_size, errors);
+            }
+
+            if s.sh_type == SectionHeaderType::SHT_NOBITS {
+                continue;
+            }
+            let (start, end) = (s.sh_offset, s.sh_offset.saturating_add(s.sh_size));
+            for (table, table_start, table_end) in tables {
+                if start < table_end && table_start < end {
+                    errors.push(ValidationError::SectionOverlap {
+                        index,
+                        name: name.clone(),
+                        table,
+                    });
+                }
+            }
+            if start < offset {
+                errors.push(ValidationError::SectionNotInOrder {
+                    index,
+                    name: name.clone(),
+                });
+            }
+            offset = end;
+            if end > len {
+                errors.push(ValidationError::SectionOutOfBounds { index, name });
+            }
+        }
+        if text != 1 {
+            errors.push(ValidationError::NotOneTextSection { count: text });
+        }
+    }
+}
+
+// The bytecode has to be whole instructions with the entrypoint on one of them
+fn validate_text(entry: u64, addr: u64, size: u64, errors: &mut Vec<ValidationError>) {
+    if !size.is_multiple_of(8) {
+        errors.push(ValidationError::InvalidTextSize { size });
+    }
+    if entry < addr || entry >= addr.saturating_add(size) {
+        errors.push(ValidationError::EntrypointOutOfBounds { entry });
+    } else if !(entry - addr).is_multiple_of(8) {
+        errors.push(ValidationError::UnalignedEntrypoint { entry });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fixtures::{
+            BSS_SECTION, DATA_SECTION, RELATIVE_CALL, RELOC_64_64, STRICT_HEADER,
+            SYSCALL_RELOC_64_32,
+        },
+        program::Program,
+        program_header::ProgramType,
+        relocation::RelocationType,
+        validate::ValidationError,
+    };
+
+    #[test]
+    fn valid_programs() {
+        for b in [&RELATIVE_CALL[..], &SYSCALL_RELOC_64_32[..], &RELOC_64_64[..]] {
+            assert_eq!(Program::from_bytes(b).unwrap().validate(), vec![]);
+        }
+    }
+
+    #[test]
+    fn writable_sections() {
+        let errors = Program::from_bytes(&BSS_SECTION).unwrap().validate();
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, ValidationError::WritableSection { name, .. } if name == ".bss")));
+        let errors = Program::from_bytes(&DATA_SECTION).unwrap().validate();
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, ValidationError::WritableSection { name, .. } if name == ".data")));
+    }
+
+    #[test]
+    fn report_every_violation() {
+        let mut program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        program.elf_header.e_entry += 4;
+        program.elf_header.e_machine = 0x03;
+        program.program_headers[0].p_align = 0x30;
+        program.relocations[0].r_type = RelocationType::R_BPF_64_ABS64;
+        program.section_header_entries[2].label = ".rodata.very_long_name".to_string();
+        let errors = program.validate();
+        assert_eq!(
+            errors
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "Non-standard ELF header, e_machine is 0x3",
+                "Program header #0 alignment 0x30 is not a power of two",
+                "Entrypoint 0x124 is not aligned to an instruction",
+                "Section #2 name \".rodata.very_long_name\" is longer than 15 bytes",
+                "Unsupported relocation R_BPF_64_ABS64 at 0x120",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_relocation() {
+        // A relocation type the loader has never heard of still parses, so
+        // that validate can report it
+        let mut b = SYSCALL_RELOC_64_32.to_vec();
+        let r = Program::from_bytes(&b).unwrap().relocations[0].to_bytes();
+        let at = b.windows(r.len()).position(|w| w == r).unwrap();
+        b[at + 8..at + 12].copy_from_slice(&0x30u32.to_le_bytes());
+        let program = Program::from_bytes(&b).unwrap();
+        assert_eq!(program.relocations[0].r_type, RelocationType::Other(0x30));
+        assert_eq!(program.to_bytes().unwrap(), b);
+        let errors: Vec<String> = program.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, ["Unsupported relocation 0x30 at 0x120"]);
+    }
+
+    #[test]
+    fn segment_overlap() {
+        let mut program = Program::from_bytes(&SYSCALL_RELOC_64_32).unwrap();
+        assert_eq!(program.program_headers[1].p_type, ProgramType::PT_LOAD);
+        // Zero-filled memory at the end of a segment counts too
+        program.program_headers[0].p_memsz += 0x1000;
+        assert_eq!(program.validate(), [ValidationError::SegmentOverlap { index: 1 }]);
+    }
+
+    #[test]
+    fn without_section_headers() {
+        let mut b = STRICT_HEADER;
+        b[0x28..0x30].fill(0); // e_shoff
+        b[0x3c..0x40].fill(0); // e_shnum and e_shstrndx
+        let mut program = Program::from_bytes(&b).unwrap();
+        assert!(program.section_headers.is_empty());
+        assert_eq!(program.validate(), []);
+
+        program.elf_header.e_entry += 4;
+        assert_eq!(
+            program.validate(),
+            [ValidationError::UnalignedEntrypoint { entry: 0x24 }]
+        );
+    }
+}
@@ -1,6 +1,8 @@
-use ezbpf_core::program::Program as EBPFProgram;
+use ezbpf_core::{
+    errors::EZBpfError, program::Program as EBPFProgram, syscalls::SyscallRegistry,
+};
 use serde::{Deserialize, Serialize};
-use serde_wasm_bindgen::to_value;
+use serde_wasm_bindgen::{to_value, Serializer};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -9,17 +11,61 @@ pub struct Program {
     inner: EBPFProgram,
 }
 
+// Parse errors are handed to JS as objects carrying the offset, the structure
+// being parsed and the offending value alongside a readable message
+#[derive(Serialize)]
+struct ParseError<'a> {
+    message: String,
+    offset: Option<u64>,
+    error: &'a EZBpfError,
+}
+
+fn parse_error(e: EZBpfError) -> JsValue {
+    let error = ParseError {
+        message: e.to_string(),
+        offset: e.offset(),
+        error: &e,
+    };
+    error
+        .serialize(&Serializer::json_compatible())
+        .unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+}
+
 #[wasm_bindgen]
 impl Program {
     #[wasm_bindgen]
     pub fn from_bytes(b: &[u8]) -> Result<Program, JsValue> {
         EBPFProgram::from_bytes(b)
             .map(|program| Program { inner: program })
-            .map_err(|e| JsValue::from_str(&format!("{:?}", e)))
+            .map_err(parse_error)
+    }
+
+    #[wasm_bindgen]
+    pub fn from_bytes_lenient(b: &[u8]) -> Result<Program, JsValue> {
+        EBPFProgram::from_bytes_lenient(b)
+            .map(|program| Program { inner: program })
+            .map_err(parse_error)
+    }
+
+    #[wasm_bindgen]
+    pub fn validate(&self) -> Result<JsValue, JsValue> {
+        to_value(&self.inner.validate()).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn segment_map(&self) -> Result<JsValue, JsValue> {
+        to_value(&self.inner.segment_map()).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // Where a VM address lands in the file, or undefined if it isn't mapped
+    #[wasm_bindgen]
+    pub fn resolve(&self, vm_addr: u64) -> Result<JsValue, JsValue> {
+        let address = self.inner.address_space().resolve_vm(vm_addr);
+        to_value(&address).map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
     #[wasm_bindgen]
     pub fn to_json(&self) -> Result<JsValue, JsValue> {
-        to_value(&self.inner).map_err(|e| JsValue::from_str(&e.to_string()))
+        to_value(&self.inner.to_json(&SyscallRegistry::default())).map_err(|e| JsValue::from_str(&e.to_string()))
     }
-}
\ No newline at end of file
+}